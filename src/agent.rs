@@ -6,15 +6,30 @@
 //! - 代理工作流的工具集成
 //! - 使用 tokio 的异步编程
 //! - AI 应用中的代理模式
+//! - 把底层库的错误归类为调用者可以分支处理的错误枚举
 
-use anyhow::Result;
-use rig::client::{CompletionClient, ProviderClient};
-use rig::completion::Prompt;
-use rig::providers::ollama;
-use tracing::{debug, info};
+use futures::future::join_all;
+use futures::StreamExt;
+use rig::agent::{Agent, MultiTurnStreamItem};
+use rig::completion::{CompletionError, CompletionModel, GetTokenUsage, Prompt, PromptError};
+use rig::http_client::Error as HttpError;
+use rig::message::{Message, ToolResultContent};
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent, StreamingPrompt};
+use rig::tool::ToolSetError;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use thiserror::Error;
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
-use crate::tools::WebSearchTool;
+use crate::config::{Config, LlmProvider};
+use crate::deep::{self, ResearchPlan};
+use crate::events::{EventSink, ResearchEvent};
+use crate::fetch::PageFetcher;
+use crate::llm::{with_llm, Llm, LlmBackend, LlmError};
+use crate::report::{ResearchReport, SourceRegistry};
+use crate::search::{SearchError, SearchResult};
+use crate::tools::{FetchPageTool, WebSearchTool};
+use crate::verify::{self, LlmJudge};
 
 // =============================================================================
 // 系统提示
@@ -25,19 +40,278 @@ const RESEARCH_SYSTEM_PROMPT: &str = r#"
 
 重要说明：
 1. 使用 web_search 工具一次以查找相关信息
-2. 获取搜索结果后，立即将其合成摘要
+2. 使用 fetch_page 工具阅读 2-3 个最相关的结果页面
 3. 不要进行多次搜索请求 - 一次搜索就足够了
 4. 如果第一次搜索没有结果，尝试一个更简单的查询，然后总结
+5. 摘要必须基于你阅读的页面内容，而不是只看标题
 
-收到搜索结果后的回复格式：
-- **概述**：简要介绍主题
-- **找到的关键来源**：列出搜索中的 URL
-- **摘要**：综合这些来源可能涵盖的内容，基于它们的标题/域名
-- **下一步**：建议用户可能探索的内容
+引用规则：
+- 工具返回的每个来源都有编号，例如 [1]、[2]
+- 每个事实性陈述后面都要加上它所依据的来源编号，例如"Tokio 是最流行的运行时 [1]。"
+- 只使用工具给出的编号，不要在正文中写 URL，也不要自己写来源列表
 
-收到搜索结果后始终提供回复。不要无限期地继续搜索。
+阅读页面后的回复格式（使用 Markdown 标题）：
+## 概述
+简要介绍主题
+## 摘要
+综合页面内容中的关键信息，每条都带引用编号
+## 下一步
+建议用户可能探索的内容
+
+阅读页面后始终提供回复。不要无限期地继续搜索或获取页面。
 "#;
 
+/// 交互模式的系统提示：在研究提示的基础上允许针对之前回答的追问。
+const CONVERSATION_SYSTEM_PROMPT: &str = r#"
+你是一个有用的 AI 研究助手，正在与用户进行多轮研究对话。
+
+重要说明：
+1. 新主题：使用 web_search 查找信息，再用 fetch_page 阅读 2-3 个最相关的页面
+2. 追问（例如"展开第 2 点"、"找反方观点"）：基于之前的回答继续，
+   只有在需要新信息时才再次搜索
+3. 回答必须基于工具返回的内容，而不是只看标题
+
+引用规则：
+- 工具返回的每个来源都有编号，例如 [1]、[2]；编号在整个对话中保持不变
+- 每个事实性陈述后面都要加上它所依据的来源编号
+- 只使用工具给出的编号，不要在正文中写 URL，也不要自己写来源列表
+
+使用 Markdown 标题组织回答。不要无限期地继续搜索或获取页面。
+"#;
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 研究代理可能失败的原因。
+///
+/// 每个变体对应一种用户可以采取不同行动的失败：启动服务器、拉取模型、
+/// 换更大的上下文窗口等。CLI 根据变体给出提示并返回不同的退出码。
+///
+/// # Rust 概念：错误分类
+///
+/// Rig 返回的错误层层嵌套（`StreamingError` → `CompletionError` →
+/// `http_client::Error` → `reqwest::Error`），而且服务器返回的错误只有文本。
+/// 在代理边界把它们归类一次，调用者就可以用 `match` 处理，
+/// 而不是在错误消息里查找关键词。
+#[derive(Error, Debug)]
+pub enum AgentError {
+    #[error("无法连接到 {provider} 服务器 {url}")]
+    LlmUnreachable { provider: LlmProvider, url: String },
+
+    #[error("{provider} 服务器上找不到模型 {model}")]
+    ModelNotFound {
+        provider: LlmProvider,
+        model: String,
+    },
+
+    #[error("对话超出了模型的上下文窗口：{message}")]
+    ContextOverflow { message: String },
+
+    #[error("{stage}超时")]
+    Timeout { stage: &'static str },
+
+    #[error("代理在 {max_turns} 轮工具调用内没有给出回答")]
+    TurnLimit { max_turns: usize },
+
+    #[error("工具调用失败：{0}")]
+    ToolFailed(String),
+
+    #[error("被搜索提供商限速")]
+    SearchRateLimited,
+
+    #[error("搜索失败：{0}")]
+    Search(SearchError),
+
+    #[error(transparent)]
+    Client(#[from] LlmError),
+
+    #[error("{stage}失败：{message}")]
+    Llm {
+        stage: &'static str,
+        message: String,
+    },
+}
+
+/// 各种服务器报告上下文溢出时使用的措辞（小写）
+///
+/// llama.cpp："exceeds the available context size"；vLLM/OpenAI："maximum context length"；
+/// 其他服务器多数会提到 context window 或 too many tokens。
+const CONTEXT_OVERFLOW_PATTERNS: &[&str] = &[
+    "context size",
+    "context length",
+    "context window",
+    "too many tokens",
+];
+
+/// 服务器返回的错误响应体（错误不是服务器的错误响应时返回 None）。
+///
+/// Rig 的 Ollama 客户端把它保存在 `InvalidStatusCodeWithMessage` 中；
+/// OpenAI 兼容客户端把状态码和响应体拼接成 `ProviderError` 的消息。
+fn error_body<'a>(error: &'a (dyn StdError + 'static)) -> Option<&'a str> {
+    if let Some(HttpError::InvalidStatusCodeWithMessage(_, body)) = error.downcast_ref() {
+        return Some(body);
+    }
+    match error.downcast_ref() {
+        Some(CompletionError::ProviderError(message)) => Some(
+            message
+                .split_once("with message: ")
+                .map_or(message.as_str(), |(_, body)| body),
+        ),
+        _ => None,
+    }
+}
+
+/// 响应体是否是"模型不存在"错误：
+/// Ollama 返回 `{"error":"model '<名称>' not found"}`，
+/// OpenAI 兼容服务器返回 `{"error":{"code":"model_not_found",...}}`。
+fn is_model_not_found(body: &str, config: &Config) -> bool {
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(body.trim()) else {
+        return false;
+    };
+    match config.provider {
+        LlmProvider::Ollama => body["error"]
+            .as_str()
+            .is_some_and(|e| e.starts_with(&format!("model '{}' not found", config.model))),
+        LlmProvider::OpenAiCompatible => body["error"]["code"] == "model_not_found",
+    }
+}
+
+impl AgentError {
+    /// 根据 LLM 调用返回的错误判断失败原因。
+    ///
+    /// 沿着 `source()` 链查找有类型的错误：Rig 的轮数上限和工具错误、
+    /// 底层的 `reqwest::Error`（连接失败、超时），以及服务器返回的错误响应体。
+    /// 模型不存在按响应体中的结构化错误识别；上下文溢出各服务器的格式不同，
+    /// 只能按关键词识别。
+    fn from_llm(stage: &'static str, error: &(dyn StdError + 'static), config: &Config) -> Self {
+        let mut current = Some(error);
+        while let Some(e) = current {
+            if error_body(e).is_some_and(|body| is_model_not_found(body, config)) {
+                return Self::ModelNotFound {
+                    provider: config.provider,
+                    model: config.model.clone(),
+                };
+            }
+            // 流式请求把 PromptError 装在 Box 中
+            let prompt_error = e
+                .downcast_ref::<PromptError>()
+                .or_else(|| e.downcast_ref::<Box<PromptError>>().map(|b| b.as_ref()));
+            if let Some(PromptError::MaxDepthError { max_depth, .. }) = prompt_error {
+                return Self::TurnLimit {
+                    max_turns: *max_depth,
+                };
+            }
+            if let Some(e) = e.downcast_ref::<ToolSetError>() {
+                return Self::ToolFailed(e.to_string());
+            }
+            if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+                if e.is_connect() {
+                    return Self::unreachable(config);
+                }
+                if e.is_timeout() {
+                    return Self::Timeout { stage };
+                }
+            }
+            current = e.source();
+        }
+
+        let message = error.to_string();
+        let lower = message.to_lowercase();
+        if CONTEXT_OVERFLOW_PATTERNS.iter().any(|p| lower.contains(p)) {
+            return Self::ContextOverflow { message };
+        }
+
+        Self::Llm { stage, message }
+    }
+
+    fn unreachable(config: &Config) -> Self {
+        let url = match config.provider {
+            LlmProvider::Ollama => &config.ollama_host,
+            LlmProvider::OpenAiCompatible => &config.openai_base_url,
+        };
+        Self::LlmUnreachable {
+            provider: config.provider,
+            url: url.clone(),
+        }
+    }
+
+    /// 这个错误会让后续的 LLM 调用同样失败（深度研究据此提前停止）。
+    fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::LlmUnreachable { .. } | Self::ModelNotFound { .. } | Self::Client(_)
+        )
+    }
+
+    /// 给用户的下一步建议（没有具体建议时返回 None）。
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            Self::LlmUnreachable {
+                provider: LlmProvider::Ollama,
+                url,
+            } => format!(
+                "确保 Ollama 正在运行（ollama serve），并且地址 {} 正确（--ollama-host）",
+                url
+            ),
+            Self::LlmUnreachable { url, .. } => format!(
+                "确保 OpenAI 兼容服务器正在运行，并且地址 {} 正确（--openai-base-url）",
+                url
+            ),
+            Self::ModelNotFound {
+                provider: LlmProvider::Ollama,
+                model,
+            } => format!(
+                "先拉取模型：ollama pull {}（或加上 --pull 自动拉取），或用 --model 选择已安装的模型",
+                model
+            ),
+            Self::ModelNotFound { model, .. } => format!(
+                "检查服务器加载的模型名称，并用 --model 指定（当前：{}）",
+                model
+            ),
+            Self::ContextOverflow { .. } => {
+                "增大上下文窗口（--num-ctx），或减少读取的内容（--page-token-budget、--max-search-results）"
+                    .to_string()
+            }
+            Self::Timeout { .. } => {
+                "服务器响应太慢：稍后重试，或增大超时（--search-timeout、--fetch-timeout）".to_string()
+            }
+            Self::TurnLimit { .. } => "增加工具调用轮数（--max-turns），或把问题拆小".to_string(),
+            Self::SearchRateLimited => {
+                "等待几分钟后重试，或改用自托管的 SearXNG（--search-provider searxng）".to_string()
+            }
+            Self::ToolFailed(_) | Self::Search(_) | Self::Client(_) | Self::Llm { .. } => {
+                return None
+            }
+        };
+        Some(hint)
+    }
+}
+
+impl From<SearchError> for AgentError {
+    fn from(error: SearchError) -> Self {
+        match error {
+            SearchError::RateLimited => Self::SearchRateLimited,
+            SearchError::NetworkError(e) if e.is_timeout() => Self::Timeout { stage: "搜索" },
+            other => Self::Search(other),
+        }
+    }
+}
+
+// =============================================================================
+// 对话状态
+// =============================================================================
+/// 多轮研究对话的状态：聊天历史和整个对话中收集到的来源。
+///
+/// 来源登记簿在各轮之间共享，所以第一轮的 `[1]` 在后续追问中仍然指向同一个 URL。
+#[derive(Debug, Default)]
+pub struct Conversation {
+    /// 之前各轮的用户消息和助手回答
+    pub history: Vec<Message>,
+
+    /// 对话中所有工具返回过的来源
+    pub sources: SourceRegistry,
+}
+
 // =============================================================================
 // 研究代理结构体
 // =============================================================================
@@ -53,115 +327,492 @@ pub struct ResearchAgent {
 
     /// 网络搜索工具
     search_tool: WebSearchTool,
+
+    /// 页面获取器（用于快速搜索的预览）
+    fetcher: PageFetcher,
+
+    /// 页面获取工具
+    fetch_tool: FetchPageTool,
+
+    /// LLM 客户端和模型（创建代理时构建一次，所有查询共享）
+    backend: LlmBackend,
 }
 
 impl ResearchAgent {
     /// 使用给定配置创建新的 ResearchAgent。
     ///
+    /// LLM 客户端在这里根据配置显式创建（不读写环境变量），
+    /// 所以同一个进程中可以同时运行多个连接不同服务器的代理。
+    ///
     /// # Rust 概念：构造函数模式
     ///
     /// Rust 没有像 OOP 语言那样的构造函数。
     /// 相反，我们使用关联函数（通常命名为 `new`）。
-    pub fn new(config: Config) -> Self {
-        let search_tool = WebSearchTool::new(config.max_search_results);
+    /// 构造可能失败时返回 `Result`。
+    pub fn new(config: Config) -> Result<Self, AgentError> {
+        let search_tool = WebSearchTool::from_config(&config);
+        let fetcher = PageFetcher::from_config(&config);
+        let fetch_tool = FetchPageTool::new(fetcher.clone());
+        let backend = LlmBackend::from_config(&config)?;
 
-        Self {
+        Ok(Self {
             config,
             search_tool,
-        }
+            fetcher,
+            fetch_tool,
+            backend,
+        })
     }
 
-    /// 研究一个主题并返回全面的摘要。
+    /// 代理使用的配置。
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// 研究一个主题并返回带编号引用的结构化报告。
+    ///
+    /// 研究过程中的工具调用、工具结果和生成的令牌会实时发送到 `events`。
+    /// 问题和回答会追加到 `conversation`，以便保存会话后继续追问。
     ///
     /// # Rust 概念：所有权和借用
     ///
     /// `&self` 表示我们不可变地借用 ResearchAgent。
     /// `&str` 用于查询，借用字符串数据而不复制。
-    pub async fn research(&self, query: &str) -> Result<String> {
+    pub async fn research(
+        &self,
+        query: &str,
+        conversation: &mut Conversation,
+        events: &EventSink,
+    ) -> Result<ResearchReport, AgentError> {
         info!(query = %query, "Starting research task");
 
-        // 步骤 1：使用构建器模式创建 Ollama 客户端
-        // 在 Rig 0.27 中，使用 ollama::Client::from_env()，它读取 OLLAMA_API_BASE_URL
-        // 环境变量，或默认为 http://localhost:11434
-        //
-        // # Rust 概念：环境变量配置
-        // 我们不硬编码值，而是使用环境变量。
-        // 这是 12-factor 应用的配置最佳实践。
-        std::env::set_var("OLLAMA_API_BASE_URL", &self.config.ollama_host);
+        let enhanced_query = format!(
+            "彻底研究以下主题。使用 web_search 工具查找当前信息，\
+             使用 fetch_page 阅读最相关的页面，然后提供用 [n] 标注来源的全面摘要：\n\n{}",
+            query
+        );
 
-        let ollama_client = ollama::Client::from_env();
+        with_llm!(&self.backend, llm => {
+            self.run_research(
+                llm,
+                RESEARCH_SYSTEM_PROMPT,
+                query,
+                &enhanced_query,
+                conversation,
+                events,
+            )
+            .await
+        })
+    }
 
-        debug!(
-            host = %self.config.ollama_host,
-            model = %self.config.model,
-            "Connected to Ollama"
-        );
+    /// 在多轮对话中回答一条消息（新问题或对之前回答的追问）。
+    ///
+    /// 回答之后，消息和回答都会追加到 `conversation.history`。
+    pub async fn follow_up(
+        &self,
+        message: &str,
+        conversation: &mut Conversation,
+        events: &EventSink,
+    ) -> Result<ResearchReport, AgentError> {
+        info!(message = %message, turn = conversation.history.len() / 2 + 1, "Conversation turn");
+
+        with_llm!(&self.backend, llm => {
+            self.run_research(
+                llm,
+                CONVERSATION_SYSTEM_PROMPT,
+                message,
+                message,
+                conversation,
+                events,
+            )
+            .await
+        })
+    }
 
-        // 步骤 2：使用工具构建代理
+    /// 研究和对话共用的流程：构建代理、流式执行、解析报告、核验引用。
+    async fn run_research<M>(
+        &self,
+        llm: &Llm<M>,
+        preamble: &str,
+        query: &str,
+        prompt: &str,
+        conversation: &mut Conversation,
+        events: &EventSink,
+    ) -> Result<ResearchReport, AgentError>
+    where
+        M: CompletionModel + 'static,
+        M::StreamingResponse: GetTokenUsage + Send,
+    {
+        // 步骤 1：使用工具构建代理
         //
         // Rig 的代理构建器让我们可以：
-        // - 设置模型
+        // - 设置模型（`llm` 已经带有模型和采样参数）
         // - 添加系统提示（前导语）
         // - 注册代理可以使用的工具
-        let agent = ollama_client
-            .agent(&self.config.model)
-            .preamble(RESEARCH_SYSTEM_PROMPT)
-            .tool(self.search_tool.clone())
+        //
+        // 两个工具共享对话的来源登记簿
+        let sources = conversation.sources.clone();
+        let agent = llm
+            .agent(preamble)
+            .tool(
+                self.search_tool
+                    .clone()
+                    .with_sources(sources.clone())
+                    .with_events(events.clone()),
+            )
+            .tool(
+                self.fetch_tool
+                    .clone()
+                    .with_sources(sources.clone())
+                    .with_events(events.clone()),
+            )
             .build();
 
         info!("Agent configured, executing research query");
 
-        // 步骤 3：执行研究查询
-        let enhanced_query = format!(
-            "彻底研究以下主题。使用 web_search 工具查找 \
-             当前信息，然后提供包含来源的全面摘要：\n\n{}",
-            query
+        // 步骤 2：执行查询（一次搜索 + 若干次页面获取 + 最终回答）
+        let response = run_streaming(
+            &agent,
+            prompt,
+            conversation.history.clone(),
+            self.config.max_turns,
+            events,
+            true,
+        )
+        .await
+        .map_err(|e| AgentError::from_llm("研究", &*e, &self.config))?;
+
+        conversation.history.push(Message::user(prompt));
+        conversation
+            .history
+            .push(Message::assistant(response.as_str()));
+
+        let mut report = ResearchReport::from_response(query, &response, &sources);
+
+        // 步骤 3：核验引用
+        self.verify(llm, &mut report, &sources, events).await;
+
+        info!(
+            sources = report.sources.len(),
+            uncited_claims = report.uncited_claims(),
+            "Research completed successfully"
         );
 
-        let response = agent
-            .prompt(&enhanced_query)
-            .multi_turn(5) // 允许最多 5 次工具调用迭代
+        Ok(report)
+    }
+
+    /// 深度研究：拆分子问题，逐个研究，最后综合成报告。
+    ///
+    /// 流程：
+    /// 1. 规划：让 LLM 把问题拆分为子问题
+    /// 2. 研究：对每个子问题运行带工具的代理（可以多次搜索），
+    ///    并把回答中报告的待解决问题加入队列
+    /// 3. 当队列为空（覆盖完成）或达到 `deep_max_questions` 预算时停止
+    /// 4. 综合：把所有子问题的笔记交给 LLM 写最终报告
+    ///
+    /// 所有子问题共享对话的来源登记簿，所以笔记中的 `[n]` 在综合阶段仍然有效。
+    /// 子问题的笔记不进入对话历史，只追加原始问题和最终报告。
+    pub async fn deep_research(
+        &self,
+        query: &str,
+        conversation: &mut Conversation,
+        events: &EventSink,
+    ) -> Result<ResearchReport, AgentError> {
+        info!(query = %query, "Starting deep research task");
+
+        with_llm!(&self.backend, llm => self.run_deep_research(llm, query, conversation, events).await)
+    }
+
+    /// 深度研究的具体流程（对每种 LLM 后端各编译一次）。
+    async fn run_deep_research<M>(
+        &self,
+        llm: &Llm<M>,
+        query: &str,
+        conversation: &mut Conversation,
+        events: &EventSink,
+    ) -> Result<ResearchReport, AgentError>
+    where
+        M: CompletionModel + 'static,
+        M::StreamingResponse: GetTokenUsage + Send,
+    {
+        // 步骤 1：规划子问题（不需要工具）
+        events.emit(ResearchEvent::status("正在拆分子问题"));
+        let planner = llm.agent(deep::PLANNER_PROMPT).build();
+        let plan_response = planner
+            .prompt(query)
             .await
-            .map_err(|e| anyhow::anyhow!("Agent execution failed: {}", e))?;
+            .map_err(|e| AgentError::from_llm("规划子问题", &e, &self.config))?;
+        let sub_questions = deep::parse_sub_questions(&plan_response);
+        info!(count = sub_questions.len(), "Planned sub-questions");
+
+        let mut plan = ResearchPlan::new(query, sub_questions, self.config.deep_max_questions);
+        events.emit(ResearchEvent::PlanCreated {
+            questions: plan.unanswered().cloned().collect(),
+        });
+
+        // 步骤 2：逐个研究子问题，直到覆盖完成或预算用尽
+        let sources = conversation.sources.clone();
+        let researcher = llm
+            .agent(deep::SUB_QUESTION_PROMPT)
+            .tool(
+                self.search_tool
+                    .clone()
+                    .with_sources(sources.clone())
+                    .with_events(events.clone()),
+            )
+            .tool(
+                self.fetch_tool
+                    .clone()
+                    .with_sources(sources.clone())
+                    .with_events(events.clone()),
+            )
+            .build();
+
+        while let Some(question) = plan.next_question() {
+            info!(question = %question, "Researching sub-question");
+            events.emit(ResearchEvent::status(format!(
+                "子问题 {}：{}",
+                plan.findings().len() + 1,
+                question
+            )));
+            let prompt = format!("原始研究问题：{}\n\n请回答子问题：{}", query, question);
+
+            // 单个子问题失败时记录下来继续，而不是让整个研究失败；
+            // 但服务器不可达或模型不存在时后面的子问题也会失败，直接停止。
+            // 子问题的回答只是笔记，所以只转发工具事件，不转发令牌
+            let response = match run_streaming(
+                &researcher,
+                &prompt,
+                Vec::new(),
+                self.config.deep_max_turns,
+                events,
+                false,
+            )
+            .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    let e = AgentError::from_llm("研究子问题", &*e, &self.config);
+                    if e.is_fatal() {
+                        return Err(e);
+                    }
+                    warn!(question = %question, error = %e, "Sub-question failed");
+                    format!("（研究失败：{}）", e)
+                }
+            };
+            plan.record(question, &response);
+        }
 
-        info!("Research completed successfully");
+        info!(
+            answered = plan.findings().len(),
+            unanswered = plan.unanswered().count(),
+            budget_exhausted = plan.budget_exhausted(),
+            "Deep research finished, synthesizing report"
+        );
+
+        // 步骤 3：综合最终报告
+        events.emit(ResearchEvent::status("正在撰写最终报告"));
+        let writer = llm.agent(deep::SYNTHESIS_PROMPT).build();
+        let response = run_streaming(
+            &writer,
+            &plan.synthesis_prompt(),
+            Vec::new(),
+            0,
+            events,
+            true,
+        )
+        .await
+        .map_err(|e| AgentError::from_llm("撰写报告", &*e, &self.config))?;
+
+        conversation.history.push(Message::user(query));
+        conversation
+            .history
+            .push(Message::assistant(response.as_str()));
+
+        let mut report = ResearchReport::from_response(query, &response, &sources);
+        self.verify(llm, &mut report, &sources, events).await;
+
+        info!(
+            sources = report.sources.len(),
+            uncited_claims = report.uncited_claims(),
+            "Deep research completed successfully"
+        );
 
-        Ok(response)
+        Ok(report)
+    }
+
+    /// 用 LLM 判断器核验报告中的引用（配置关闭时跳过）。
+    async fn verify<M>(
+        &self,
+        llm: &Llm<M>,
+        report: &mut ResearchReport,
+        sources: &SourceRegistry,
+        events: &EventSink,
+    ) where
+        M: CompletionModel + 'static,
+    {
+        if !self.config.verify_citations {
+            return;
+        }
+
+        info!("Verifying citations against fetched content");
+        events.emit(ResearchEvent::status("正在核验引用"));
+        // 判断需要确定性，所以温度固定为 0（其他采样参数仍然来自配置）
+        let judge = LlmJudge::new(
+            llm.agent(verify::ENTAILMENT_PROMPT)
+                .temperature(0.0)
+                .build(),
+        );
+        verify::verify_report(report, sources, &judge).await;
     }
 
     /// 执行快速搜索，无需完整的代理推理。
     ///
     /// 当你只想要搜索结果而不需要代理合成时，这很有用。
-    pub async fn quick_search(&self, query: &str) -> Result<String> {
+    pub async fn quick_search(&self, query: &str) -> Result<String, AgentError> {
+        let results = self.quick_results(query).await?;
+        Ok(format_quick_results(query, &results))
+    }
+
+    /// 快速搜索的原始结果：每个搜索结果及其页面正文预览。
+    pub async fn quick_results(
+        &self,
+        query: &str,
+    ) -> Result<Vec<(SearchResult, Option<String>)>, AgentError> {
         info!(query = %query, "Performing quick search");
 
-        let results = self
-            .search_tool
-            .search(query)
-            .await
-            .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+        let results = self.search_tool.search(query).await?;
+
+        // 并发获取每个结果页面的正文预览（preview_chars 为 0 时跳过）
+        let previews = if self.config.preview_chars > 0 {
+            join_all(results.iter().map(|r| self.preview(&r.url))).await
+        } else {
+            vec![None; results.len()]
+        };
+
+        Ok(results.into_iter().zip(previews).collect())
+    }
 
-        if results.is_empty() {
-            return Ok(format!("No results found for: {}", query));
+    /// 获取页面并返回正文开头的预览；获取失败时返回 None。
+    async fn preview(&self, url: &str) -> Option<String> {
+        match self.fetcher.fetch(url).await {
+            Ok(page) => Some(truncate_chars(
+                &page.article.text.replace('\n', " "),
+                self.config.preview_chars,
+            ))
+            .filter(|p| !p.is_empty()),
+            Err(e) => {
+                debug!(url = %url, error = %e, "Preview fetch failed");
+                None
+            }
         }
+    }
+}
 
-        // 格式化结果
-        let formatted: String = results
-            .iter()
-            .enumerate()
-            .map(|(i, r)| {
-                format!(
-                    "{}. **{}**\n   {}\n   URL: {}\n",
-                    i + 1,
-                    r.title,
-                    r.snippet,
-                    r.url
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+/// 以流式方式运行代理，把工具调用、工具结果和（可选的）令牌转发为事件，
+/// 并返回最终回答。`history` 是之前各轮的对话（单次研究时为空）。
+///
+/// # Rust 概念：流（Stream）
+///
+/// `Stream` 是异步版本的迭代器：`stream.next().await` 每次产出一个元素，
+/// 直到流结束。Rig 的多轮流式请求会依次产出文本片段、工具调用、
+/// 工具结果，最后是包含完整回答的 `FinalResponse`。
+///
+/// Rig 没有公开流式错误的类型，所以这里把它装箱返回，由调用者用
+/// `AgentError::from_llm` 归类。
+async fn run_streaming<M>(
+    agent: &Agent<M>,
+    prompt: &str,
+    history: Vec<Message>,
+    turns: usize,
+    events: &EventSink,
+    forward_tokens: bool,
+) -> Result<String, Box<dyn StdError + Send + Sync>>
+where
+    M: CompletionModel + 'static,
+    M::StreamingResponse: GetTokenUsage + Send,
+{
+    let mut stream = agent
+        .stream_prompt(prompt)
+        .with_history(history)
+        .multi_turn(turns)
+        .await;
+
+    // 工具结果只带调用 ID，用这个表找回工具名称
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+    let mut text = String::new();
+
+    while let Some(item) = stream.next().await {
+        match item? {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(t)) => {
+                text.push_str(&t.text);
+                if forward_tokens {
+                    events.emit(ResearchEvent::Token { text: t.text });
+                }
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall(call)) => {
+                tool_names.insert(call.id.clone(), call.function.name.clone());
+                events.emit(ResearchEvent::ToolCall {
+                    name: call.function.name,
+                    arguments: call.function.arguments,
+                });
+            }
+            MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult(result)) => {
+                let output: String = result
+                    .content
+                    .iter()
+                    .filter_map(|c| match c {
+                        ToolResultContent::Text(t) => Some(t.text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                let name = tool_names.get(&result.id).cloned().unwrap_or_default();
+                events.emit(ResearchEvent::tool_result(name, &output));
+            }
+            MultiTurnStreamItem::FinalResponse(response) => {
+                return Ok(response.response().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    // 流在没有 FinalResponse 的情况下结束：返回收到的文本
+    Ok(text)
+}
+
+/// 把快速搜索的结果格式化为 Markdown 列表。
+pub fn format_quick_results(query: &str, results: &[(SearchResult, Option<String>)]) -> String {
+    if results.is_empty() {
+        return format!("No results found for: {}", query);
+    }
 
-        Ok(format!("## Search Results\n\n{}", formatted))
+    let formatted: String = results
+        .iter()
+        .enumerate()
+        .map(|(i, (r, preview))| {
+            let mut entry = format!(
+                "{}. **{}**\n   {}\n   URL: {}\n",
+                i + 1,
+                r.title,
+                r.snippet,
+                r.url
+            );
+            if let Some(preview) = preview {
+                entry.push_str(&format!("   Preview: {}\n", preview));
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("## Search Results\n\n{}", formatted)
+}
+
+/// 按字符（而不是字节）截断文本，超出时追加省略号。
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
     }
 }
 
@@ -175,14 +826,254 @@ mod tests {
     #[test]
     fn test_agent_creation() {
         let config = Config::default();
-        let agent = ResearchAgent::new(config);
+        let agent = ResearchAgent::new(config).unwrap();
 
         assert_eq!(agent.config.model, "llama3.2");
     }
 
+    #[tokio::test]
+    async fn test_research_with_openai_compatible_server() {
+        use crate::test_util::{StubResponse, StubServer};
+
+        // 模拟 llama.cpp server / vLLM 的流式 Chat Completions 响应
+        let chunks = [
+            serde_json::json!({ "choices": [{ "delta": { "content": "## 概述\n" }, "finish_reason": null }] }),
+            serde_json::json!({ "choices": [{ "delta": { "content": "Rust 是一门系统编程语言。" }, "finish_reason": "stop" }] }),
+            serde_json::json!({ "choices": [], "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 } }),
+        ];
+        let body: String = chunks
+            .iter()
+            .map(|chunk| format!("data: {}\n\n", chunk))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        let server = StubServer::spawn(move |_| {
+            StubResponse::with_type(200, "text/event-stream", body.clone())
+        })
+        .await;
+
+        let agent = ResearchAgent::new(Config {
+            provider: LlmProvider::OpenAiCompatible,
+            openai_base_url: format!("{}/v1", server.url),
+            model: "local-model".to_string(),
+            seed: Some(7),
+            verify_citations: false,
+            ..Config::default()
+        })
+        .unwrap();
+        let report = agent
+            .research(
+                "什么是 Rust？",
+                &mut Conversation::default(),
+                &EventSink::none(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.claims().count(), 1);
+        assert!(report.to_markdown().contains("Rust 是一门系统编程语言。"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/v1/chat/completions");
+        let request: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(request["model"], "local-model");
+        assert_eq!(request["seed"], 7);
+        assert_eq!(request["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_agents_with_different_ollama_hosts() {
+        use crate::test_util::{StubResponse, StubServer};
+
+        // 每个桩服务器模拟一个 Ollama 实例，流式回答中带上自己的名字
+        async fn ollama_stub(name: &'static str) -> StubServer {
+            let lines = [
+                serde_json::json!({
+                    "model": "llama3.2",
+                    "created_at": "2024-01-01T00:00:00Z",
+                    "message": { "role": "assistant", "content": format!("来自 {} 的回答。", name) },
+                    "done": false
+                }),
+                serde_json::json!({
+                    "model": "llama3.2",
+                    "created_at": "2024-01-01T00:00:01Z",
+                    "message": { "role": "assistant", "content": "" },
+                    "done": true,
+                    "done_reason": "stop",
+                    "prompt_eval_count": 10,
+                    "eval_count": 5
+                }),
+            ];
+            let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+            StubServer::spawn(move |_| {
+                StubResponse::with_type(200, "application/x-ndjson", body.clone())
+            })
+            .await
+        }
+
+        let (first, second) = (ollama_stub("first").await, ollama_stub("second").await);
+        let agent_for = |server: &StubServer| {
+            ResearchAgent::new(Config {
+                ollama_host: server.url.clone(),
+                verify_citations: false,
+                ..Config::default()
+            })
+            .unwrap()
+        };
+        let (agent_a, agent_b) = (agent_for(&first), agent_for(&second));
+
+        // 两个代理并发运行，各自只访问自己的服务器
+        let (mut conv_a, mut conv_b) = (Conversation::default(), Conversation::default());
+        let events = EventSink::none();
+        let (a, b) = tokio::join!(
+            agent_a.research("问题", &mut conv_a, &events),
+            agent_b.research("问题", &mut conv_b, &events),
+        );
+
+        assert!(a.unwrap().to_markdown().contains("来自 first 的回答"));
+        assert!(b.unwrap().to_markdown().contains("来自 second 的回答"));
+        assert_eq!(first.requests().len(), 1);
+        assert_eq!(second.requests().len(), 1);
+        assert_eq!(first.requests()[0].path, "/api/chat");
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_classified() {
+        use crate::test_util::{StubResponse, StubServer};
+
+        async fn research_error(config: Config) -> AgentError {
+            let agent = ResearchAgent::new(Config {
+                verify_citations: false,
+                ..config
+            })
+            .unwrap();
+            agent
+                .research("问题", &mut Conversation::default(), &EventSink::none())
+                .await
+                .unwrap_err()
+        }
+
+        // 没有服务器监听的端口：连接被拒绝
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let error = research_error(Config {
+            ollama_host: url.clone(),
+            ..Config::default()
+        })
+        .await;
+        assert!(
+            matches!(&error, AgentError::LlmUnreachable { url: u, .. } if *u == url),
+            "{:?}",
+            error
+        );
+        assert!(error.hint().unwrap().contains("ollama serve"));
+
+        // Ollama 对不存在的模型返回 404
+        let ollama = StubServer::spawn(|_| {
+            StubResponse::with_type(
+                404,
+                "application/json",
+                r#"{"error":"model 'nope' not found"}"#,
+            )
+        })
+        .await;
+        let error = research_error(Config {
+            ollama_host: ollama.url.clone(),
+            model: "nope".to_string(),
+            ..Config::default()
+        })
+        .await;
+        assert!(
+            matches!(&error, AgentError::ModelNotFound { model, .. } if model == "nope"),
+            "{:?}",
+            error
+        );
+        assert!(error.hint().unwrap().contains("ollama pull nope"));
+
+        // 地址写错时反向代理也会返回 404，但这不表示模型不存在
+        let proxy =
+            StubServer::spawn(|_| StubResponse::with_type(404, "text/plain", "404 page not found"))
+                .await;
+        let error = research_error(Config {
+            ollama_host: proxy.url.clone(),
+            ..Config::default()
+        })
+        .await;
+        assert!(matches!(error, AgentError::Llm { .. }), "{:?}", error);
+
+        // OpenAI 兼容服务器在错误代码中说明模型不存在
+        let openai = StubServer::spawn(|_| {
+            StubResponse::with_type(
+                404,
+                "application/json",
+                r#"{"error":{"message":"The model `gpt-x` does not exist","type":"invalid_request_error","code":"model_not_found"}}"#,
+            )
+        })
+        .await;
+        let error = research_error(Config {
+            provider: LlmProvider::OpenAiCompatible,
+            openai_base_url: format!("{}/v1", openai.url),
+            model: "gpt-x".to_string(),
+            ..Config::default()
+        })
+        .await;
+        assert!(
+            matches!(&error, AgentError::ModelNotFound { model, .. } if model == "gpt-x"),
+            "{:?}",
+            error
+        );
+
+        // llama.cpp server 在提示超出上下文时返回 400
+        let llama_cpp = StubServer::spawn(|_| {
+            StubResponse::with_type(
+                400,
+                "application/json",
+                r#"{"error":{"code":400,"message":"the request exceeds the available context size, try increasing it","type":"exceed_context_size_error"}}"#,
+            )
+        })
+        .await;
+        let error = research_error(Config {
+            provider: LlmProvider::OpenAiCompatible,
+            openai_base_url: format!("{}/v1", llama_cpp.url),
+            ..Config::default()
+        })
+        .await;
+        assert!(
+            matches!(error, AgentError::ContextOverflow { .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_search_errors_are_classified() {
+        assert!(matches!(
+            AgentError::from(SearchError::RateLimited),
+            AgentError::SearchRateLimited
+        ));
+        assert!(matches!(
+            AgentError::from(SearchError::SearchFailed("HTTP 500".to_string())),
+            AgentError::Search(_)
+        ));
+        assert!(AgentError::SearchRateLimited.hint().is_some());
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello world", 5), "hello…");
+        assert_eq!(truncate_chars("短文本", 10), "短文本");
+        assert_eq!(truncate_chars("异步编程", 2), "异步…");
+    }
+
     #[test]
     fn test_system_prompt_not_empty() {
-        assert!(!RESEARCH_SYSTEM_PROMPT.is_empty());
-        assert!(RESEARCH_SYSTEM_PROMPT.contains("research"));
+        // 提示必须告诉模型可用的工具和引用格式
+        for prompt in [RESEARCH_SYSTEM_PROMPT, CONVERSATION_SYSTEM_PROMPT] {
+            assert!(!prompt.is_empty());
+            assert!(prompt.contains("web_search"));
+            assert!(prompt.contains("fetch_page"));
+            assert!(prompt.contains("[1]"));
+        }
     }
 }
@@ -0,0 +1,548 @@
+//! # 参考文献导出模块
+//!
+//! 把一次研究中引用的来源导出为参考文献管理工具可以导入的格式：
+//! - BibTeX（`.bib`）：`@online` 条目，LaTeX（biblatex）直接使用
+//! - CSL-JSON（`.json`）：Zotero、Pandoc 和大多数引用样式处理器使用
+//!
+//! 条目包含标题、URL、网站名称、作者、发布日期（页面提取到时）和访问日期。
+//! 页面中的日期格式五花八门，能解析的转换为年/月/日，不能解析的保留原文。
+//!
+//! 它演示了：
+//! - 为一种数据生成多种文本格式
+//! - 用 `#[serde(rename = "...")]` 匹配外部格式的字段名
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::report::Source;
+
+// =============================================================================
+// 导出目标
+// =============================================================================
+/// 参考文献文件的格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BibFormat {
+    BibTex,
+    CslJson,
+}
+
+impl BibFormat {
+    /// 根据文件扩展名推断格式。
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("bib") => Ok(Self::BibTex),
+            Some("json") => Ok(Self::CslJson),
+            _ => anyhow::bail!(
+                "无法从文件名 {} 推断参考文献格式（支持 .bib、.json）",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// `--bib` 的值：文件路径和从扩展名推断的格式。
+#[derive(Debug, Clone, PartialEq)]
+pub struct BibTarget {
+    pub path: PathBuf,
+    pub format: BibFormat,
+}
+
+impl FromStr for BibTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathBuf::from(s);
+        let format = BibFormat::from_path(&path)?;
+        Ok(Self { path, format })
+    }
+}
+
+impl BibTarget {
+    /// 为来源生成参考文献并写入文件。
+    pub fn write(&self, sources: &[Source]) -> Result<()> {
+        let contents = match self.format {
+            BibFormat::BibTex => bibtex(sources),
+            BibFormat::CslJson => csl_json(sources)?,
+        };
+        std::fs::write(&self.path, contents)
+            .with_context(|| format!("无法写入参考文献 {}", self.path.display()))
+    }
+}
+
+// =============================================================================
+// BibTeX
+// =============================================================================
+/// 生成 BibTeX 条目（每个来源一个 `@online`）。
+pub fn bibtex(sources: &[Source]) -> String {
+    let keys = citation_keys(sources);
+    let mut out = String::new();
+
+    for (source, key) in sources.iter().zip(keys) {
+        let mut fields: Vec<(&str, String)> = Vec::new();
+        fields.push(("title", format!("{{{}}}", escape_bibtex(title(source)))));
+        // 作者名用双层大括号，BibTeX 不会把"张三"或"Rust 团队"拆成姓和名
+        if let Some(author) = &source.author {
+            fields.push(("author", format!("{{{{{}}}}}", escape_bibtex(author))));
+        }
+        if let Some(site) = &source.site_name {
+            fields.push(("organization", format!("{{{}}}", escape_bibtex(site))));
+        }
+        if let Some(published) = &source.published {
+            match PartialDate::parse(published) {
+                Some(date) => {
+                    fields.push(("date", format!("{{{}}}", date.iso())));
+                    fields.push(("year", format!("{{{}}}", date.year)));
+                }
+                None => fields.push(("note", format!("{{{}}}", escape_bibtex(published)))),
+            }
+        }
+        fields.push(("url", format!("{{{}}}", source.url)));
+        fields.push((
+            "urldate",
+            format!("{{{}}}", source.accessed_at.format("%Y-%m-%d")),
+        ));
+
+        out.push_str(&format!("@online{{{},\n", key));
+        for (name, value) in fields {
+            out.push_str(&format!("  {:<12} = {},\n", name, value));
+        }
+        out.push_str("}\n\n");
+    }
+
+    out.trim_end().to_string() + "\n"
+}
+
+/// 转义 BibTeX 中有特殊含义的字符。
+fn escape_bibtex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("\\textbackslash{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// =============================================================================
+// CSL-JSON
+// =============================================================================
+/// CSL-JSON 中的一个条目（只包含我们能填写的字段）。
+#[derive(Debug, Serialize)]
+struct CslItem {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: String,
+    #[serde(rename = "URL")]
+    url: String,
+    #[serde(rename = "container-title", skip_serializing_if = "Option::is_none")]
+    container_title: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    author: Vec<CslName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issued: Option<CslDate>,
+    accessed: CslDate,
+}
+
+/// 作者名。页面中的署名格式不可靠，所以整体作为 `literal` 而不拆分姓和名。
+#[derive(Debug, Serialize)]
+struct CslName {
+    literal: String,
+}
+
+/// CSL 日期：能解析时用 `date-parts`，否则用 `raw` 保留原文。
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CslDate {
+    Parts {
+        #[serde(rename = "date-parts")]
+        date_parts: [Vec<i32>; 1],
+    },
+    Raw {
+        raw: String,
+    },
+}
+
+impl CslDate {
+    fn from_published(published: &str) -> Self {
+        match PartialDate::parse(published) {
+            Some(date) => Self::Parts {
+                date_parts: [date.parts()],
+            },
+            None => Self::Raw {
+                raw: published.to_string(),
+            },
+        }
+    }
+}
+
+/// 生成 CSL-JSON 数组（每个来源一个 `webpage` 条目）。
+pub fn csl_json(sources: &[Source]) -> Result<String> {
+    let items: Vec<CslItem> = sources
+        .iter()
+        .zip(citation_keys(sources))
+        .map(|(source, key)| {
+            let accessed = source.accessed_at.date_naive();
+            CslItem {
+                id: key,
+                kind: "webpage",
+                title: title(source).to_string(),
+                url: source.url.clone(),
+                container_title: source.site_name.clone(),
+                author: source
+                    .author
+                    .iter()
+                    .map(|a| CslName { literal: a.clone() })
+                    .collect(),
+                issued: source.published.as_deref().map(CslDate::from_published),
+                accessed: CslDate::Parts {
+                    date_parts: [vec![
+                        accessed.year(),
+                        accessed.month() as i32,
+                        accessed.day() as i32,
+                    ]],
+                },
+            }
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&items)? + "\n")
+}
+
+// =============================================================================
+// 共用的辅助函数
+// =============================================================================
+/// 来源标题；没有标题时使用 URL。
+fn title(source: &Source) -> &str {
+    if source.title.is_empty() {
+        &source.url
+    } else {
+        &source.title
+    }
+}
+
+/// 为每个来源生成引用键，例如 `tokio2024`、`rustlang`；重复时追加 a、b、c…z、aa、ab…
+///
+/// 键由域名的主体部分（去掉子域名和顶级域名）和发布年份组成，
+/// 只包含 ASCII 字母和数字，可以安全地用于 `\cite{...}`。
+fn citation_keys(sources: &[Source]) -> Vec<String> {
+    let mut used = HashSet::new();
+    sources
+        .iter()
+        .map(|source| {
+            let host = source
+                .url
+                .split("://")
+                .nth(1)
+                .unwrap_or(&source.url)
+                .split(['/', ':', '?', '#'])
+                .next()
+                .unwrap_or_default();
+            let labels: Vec<&str> = host.split('.').filter(|l| !l.is_empty()).collect();
+            // 取顶级域名前面的标签（doc.rust-lang.org → rust-lang），
+            // example.co.uk 这样的二级后缀再往前取一个
+            let mut index = labels.len().saturating_sub(2);
+            if index > 0 && SECOND_LEVEL_SUFFIXES.contains(&labels[index]) {
+                index -= 1;
+            }
+            let label = labels.get(index).copied().unwrap_or("source");
+            let mut base: String = label
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if base.is_empty() {
+                base = format!("source{}", source.id);
+            }
+            if let Some(date) = source.published.as_deref().and_then(PartialDate::parse) {
+                base.push_str(&date.year.to_string());
+            }
+
+            let mut key = base.clone();
+            let mut n = 0;
+            while !used.insert(key.clone()) {
+                key = format!("{}{}", base, letter_suffix(n));
+                n += 1;
+            }
+            key
+        })
+        .collect()
+}
+
+/// 第 `n` 个（从 0 开始）字母后缀：a…z、aa、ab…az、ba…
+fn letter_suffix(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'a' + (n % 26) as u8);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    letters.iter().rev().map(|&b| b as char).collect()
+}
+
+/// 常见的二级域名后缀（例如 example.co.uk、example.com.cn）
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["co", "com", "org", "net", "ac", "gov", "edu"];
+
+/// 可能只有年份或年月的日期。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PartialDate {
+    year: i32,
+    month: Option<u32>,
+    day: Option<u32>,
+}
+
+impl PartialDate {
+    /// 解析页面中常见的日期格式：
+    /// `2024-03-01`、`2024-03-01T08:00:00Z`、`2024/03/01`、`2024年3月1日`、
+    /// `March 1, 2024`、`1 Mar 2024`、`2024-03`、`2024`。
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        // ISO 8601（带或不带时间）以及用 / 或 . 分隔的年月日
+        let head: String = text.chars().take(10).collect();
+        let normalized = head.replace(['/', '.'], "-");
+        if let Ok(date) = NaiveDate::parse_from_str(&normalized, "%Y-%m-%d") {
+            return Some(Self::from_naive(date));
+        }
+
+        // 中文日期：2024年3月1日 / 2024年3月
+        if let Some((year, rest)) = text.split_once('年') {
+            let year = year.trim().parse().ok()?;
+            let (month, rest) = rest.split_once('月').unwrap_or((rest, ""));
+            let month = month.trim().parse().ok().filter(|m| (1..=12).contains(m));
+            let day = rest
+                .trim_end_matches(['日', '号'])
+                .trim()
+                .parse()
+                .ok()
+                .filter(|d| (1..=31).contains(d));
+            return Some(Self {
+                year,
+                month,
+                day: month.and(day),
+            });
+        }
+
+        // 英文月份名称
+        for format in ["%B %d, %Y", "%b %d, %Y", "%d %B %Y", "%d %b %Y"] {
+            if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+                return Some(Self::from_naive(date));
+            }
+        }
+
+        // 只有年月或年份
+        let mut numbers = text.split(['-', '/']).map(str::trim);
+        let year: i32 = numbers.next()?.parse().ok()?;
+        if !(1000..=9999).contains(&year) {
+            return None;
+        }
+        let month = numbers
+            .next()
+            .and_then(|m| m.parse().ok())
+            .filter(|m| (1..=12).contains(m));
+        Some(Self {
+            year,
+            month,
+            day: None,
+        })
+    }
+
+    fn from_naive(date: NaiveDate) -> Self {
+        Self {
+            year: date.year(),
+            month: Some(date.month()),
+            day: Some(date.day()),
+        }
+    }
+
+    /// ISO 8601 格式（biblatex 的 date 字段接受 `2024`、`2024-03`、`2024-03-01`）。
+    fn iso(&self) -> String {
+        match (self.month, self.day) {
+            (Some(month), Some(day)) => format!("{:04}-{:02}-{:02}", self.year, month, day),
+            (Some(month), None) => format!("{:04}-{:02}", self.year, month),
+            _ => format!("{:04}", self.year),
+        }
+    }
+
+    /// CSL 的 date-parts：`[年, 月, 日]`，没有的部分省略。
+    fn parts(&self) -> Vec<i32> {
+        let mut parts = vec![self.year];
+        if let Some(month) = self.month {
+            parts.push(month as i32);
+            if let Some(day) = self.day {
+                parts.push(day as i32);
+            }
+        }
+        parts
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: usize, url: &str, title: &str) -> Source {
+        Source {
+            id,
+            url: url.to_string(),
+            title: title.to_string(),
+            accessed_at: "2026-03-01T08:00:00Z".parse().unwrap(),
+            author: None,
+            site_name: None,
+            published: None,
+        }
+    }
+
+    fn sources() -> Vec<Source> {
+        let mut tokio = source(
+            1,
+            "https://tokio.rs/blog/2024-announce",
+            "Tokio 1.0 & beyond",
+        );
+        tokio.author = Some("Carl Lerche".to_string());
+        tokio.site_name = Some("Tokio".to_string());
+        tokio.published = Some("2024-03-01T08:00:00Z".to_string());
+
+        let mut news = source(2, "https://www.tokio.rs/news", "");
+        news.published = Some("上周".to_string());
+
+        vec![
+            tokio,
+            news,
+            source(3, "https://doc.rust-lang.org/book/", "The Book"),
+        ]
+    }
+
+    #[test]
+    fn test_parse_dates() {
+        let full = |y, m, d| {
+            Some(PartialDate {
+                year: y,
+                month: Some(m),
+                day: Some(d),
+            })
+        };
+        assert_eq!(PartialDate::parse("2024-03-01"), full(2024, 3, 1));
+        assert_eq!(
+            PartialDate::parse("2024-03-01T08:00:00+08:00"),
+            full(2024, 3, 1)
+        );
+        assert_eq!(PartialDate::parse("2024/3/1"), full(2024, 3, 1));
+        assert_eq!(PartialDate::parse("2024年3月1日"), full(2024, 3, 1));
+        assert_eq!(PartialDate::parse("March 1, 2024"), full(2024, 3, 1));
+        assert_eq!(PartialDate::parse("1 Mar 2024"), full(2024, 3, 1));
+        assert_eq!(
+            PartialDate::parse("2024-03"),
+            Some(PartialDate {
+                year: 2024,
+                month: Some(3),
+                day: None
+            })
+        );
+        assert_eq!(PartialDate::parse("2024").unwrap().iso(), "2024");
+        assert_eq!(PartialDate::parse("上周"), None);
+    }
+
+    #[test]
+    fn test_citation_keys() {
+        assert_eq!(
+            citation_keys(&sources()),
+            ["tokio2024", "tokio", "rustlang"]
+        );
+
+        let same = [
+            source(1, "https://a.com/1", ""),
+            source(2, "https://news.a.com/2", ""),
+            source(3, "https://bbc.co.uk/3", ""),
+        ];
+        assert_eq!(citation_keys(&same), ["a", "aa", "bbc"]);
+
+        // 同一网站超过 26 个来源时后缀继续增长，键保持唯一
+        let many: Vec<Source> = (1..=30)
+            .map(|id| source(id, &format!("https://doc.rust-lang.org/{id}"), ""))
+            .collect();
+        let keys = citation_keys(&many);
+        assert_eq!(keys.len(), 30);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 30);
+        assert_eq!(keys[..3], ["rustlang", "rustlanga", "rustlangb"]);
+        assert_eq!(
+            keys[26..],
+            ["rustlangz", "rustlangaa", "rustlangab", "rustlangac"]
+        );
+        assert_eq!(letter_suffix(26 * 27), "aaa");
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let bib = bibtex(&sources());
+        assert!(bib.starts_with(
+            "@online{tokio2024,\n  \
+               title        = {Tokio 1.0 \\& beyond},\n  \
+               author       = {{Carl Lerche}},\n  \
+               organization = {Tokio},\n  \
+               date         = {2024-03-01},\n  \
+               year         = {2024},\n  \
+               url          = {https://tokio.rs/blog/2024-announce},\n  \
+               urldate      = {2026-03-01},\n}\n"
+        ));
+        // 没有标题时使用 URL，无法解析的日期放在 note 中
+        assert!(bib.contains("@online{tokio,\n  title        = {https://www.tokio.rs/news},\n  note         = {上周},"));
+        assert_eq!(bib.matches("@online{").count(), 3);
+    }
+
+    #[test]
+    fn test_csl_json() {
+        let json: serde_json::Value = serde_json::from_str(&csl_json(&sources()).unwrap()).unwrap();
+        let first = &json[0];
+        assert_eq!(first["id"], "tokio2024");
+        assert_eq!(first["type"], "webpage");
+        assert_eq!(first["title"], "Tokio 1.0 & beyond");
+        assert_eq!(first["URL"], "https://tokio.rs/blog/2024-announce");
+        assert_eq!(first["container-title"], "Tokio");
+        assert_eq!(first["author"][0]["literal"], "Carl Lerche");
+        assert_eq!(
+            first["issued"]["date-parts"],
+            serde_json::json!([[2024, 3, 1]])
+        );
+        assert_eq!(
+            first["accessed"]["date-parts"],
+            serde_json::json!([[2026, 3, 1]])
+        );
+
+        assert_eq!(json[1]["issued"]["raw"], "上周");
+        assert!(json[2].get("author").is_none());
+        assert!(json[2].get("issued").is_none());
+    }
+
+    #[test]
+    fn test_bib_target_infers_format() {
+        assert_eq!(
+            "refs.bib".parse::<BibTarget>().unwrap().format,
+            BibFormat::BibTex
+        );
+        assert_eq!(
+            "refs.JSON".parse::<BibTarget>().unwrap().format,
+            BibFormat::CslJson
+        );
+        assert!("refs.txt".parse::<BibTarget>().is_err());
+    }
+}
@@ -1,6 +1,18 @@
 //! # 配置模块
 //!
-//! 本模块处理从环境变量加载和管理配置。
+//! 本模块处理从配置文件和环境变量加载和管理配置。
+//!
+//! 配置按以下顺序合并，后面的来源覆盖前面的：
+//! 1. 默认值
+//! 2. 用户配置文件 `~/.config/ai-research-agent/config.toml`
+//! 3. 项目配置文件 `./ai-research-agent.toml`
+//! 4. 用 `--profile <名称>` 选择的 `[profile.<名称>]` 表
+//! 5. 环境变量（包括 `.env` 文件）
+//! 6. 命令行参数（在 main.rs 中应用）
+//!
+//! 每个字段最终的值来自哪里都记录在 `Config::origins` 中，
+//! 所以验证失败时可以指出是哪个文件、环境变量或参数设置了无效值。
+//!
 //! 它演示了几个重要的 Rust 模式：
 //! - 具有命名字段的结构体
 //! - Default 特征用于合理默认值
@@ -8,7 +20,144 @@
 //! - 字符串所有权与借用
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// =============================================================================
+// 搜索后端
+// =============================================================================
+/// 可用的搜索后端。
+///
+/// # Rust 概念：枚举 + FromStr
+///
+/// 用枚举而不是字符串表示固定的选项集合，可以让编译器检查
+/// 所有地方都处理了每一种后端。实现 `FromStr` 后，
+/// 就可以直接用 `"duckduckgo".parse()` 从环境变量解析。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackend {
+    /// 抓取 DuckDuckGo HTML 页面（免费，无需 API 密钥）
+    #[serde(alias = "ddg")]
+    DuckDuckGo,
+
+    /// 自托管的 SearXNG 实例（JSON API）
+    Searxng,
+}
+
+impl FromStr for SearchBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "duckduckgo" | "ddg" => Ok(Self::DuckDuckGo),
+            "searxng" => Ok(Self::Searxng),
+            other => anyhow::bail!("未知的搜索提供商：{}（可选：duckduckgo、searxng）", other),
+        }
+    }
+}
+
+impl fmt::Display for SearchBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuckDuckGo => write!(f, "duckduckgo"),
+            Self::Searxng => write!(f, "searxng"),
+        }
+    }
+}
+
+// =============================================================================
+// LLM 提供商
+// =============================================================================
+/// 可用的 LLM 提供商。
+///
+/// OpenAI 兼容的 Chat Completions API 被 llama.cpp server、vLLM、LM Studio
+/// 等本地推理服务器广泛实现，所以一个变体就可以覆盖它们。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LlmProvider {
+    /// Ollama 原生 API
+    #[serde(rename = "ollama")]
+    Ollama,
+
+    /// 任何实现 OpenAI `/v1/chat/completions` 的服务器
+    #[serde(rename = "openai-compatible", alias = "openai")]
+    OpenAiCompatible,
+}
+
+impl FromStr for LlmProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ollama" => Ok(Self::Ollama),
+            "openai-compatible" | "openai" => Ok(Self::OpenAiCompatible),
+            other => anyhow::bail!(
+                "未知的 LLM 提供商：{}（可选：ollama、openai-compatible）",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for LlmProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ollama => write!(f, "ollama"),
+            Self::OpenAiCompatible => write!(f, "openai-compatible"),
+        }
+    }
+}
+
+// =============================================================================
+// 配置来源
+// =============================================================================
+/// 一个配置值来自哪里。
+///
+/// 用于在验证错误中指出无效值的出处，例如
+/// "temperature 必须在 0.0 到 2.0 之间，得到：3（来自 环境变量 TEMPERATURE）"。
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    /// 内置默认值
+    Default,
+
+    /// 配置文件；`profile` 不为 None 时来自该文件的 `[profile.<名称>]` 表
+    File {
+        path: PathBuf,
+        profile: Option<String>,
+    },
+
+    /// 环境变量（包括 `.env` 文件中的变量）
+    Env(&'static str),
+
+    /// 命令行参数
+    Cli(&'static str),
+
+    /// HTTP API 请求体中的字段（`serve` 子命令）
+    Request(&'static str),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "默认值"),
+            Self::File {
+                path,
+                profile: None,
+            } => write!(f, "配置文件 {}", path.display()),
+            Self::File {
+                path,
+                profile: Some(profile),
+            } => write!(f, "配置文件 {} 的 [profile.{}]", path.display(), profile),
+            Self::Env(var) => write!(f, "环境变量 {}", var),
+            Self::Cli(flag) => write!(f, "命令行参数 {}", flag),
+            Self::Request(field) => write!(f, "HTTP 请求字段 {}", field),
+        }
+    }
+}
 
 // =============================================================================
 // 配置结构体
@@ -24,23 +173,97 @@ use std::env;
 /// #[derive(...)] 属性自动实现常见特征：
 /// - Debug：允许使用 {:?} 格式打印
 /// - Clone：创建结构体的深拷贝
-#[derive(Debug, Clone)]
+/// - Serialize：保存会话时记录当时使用的配置
+#[derive(Debug, Clone, Serialize)]
 pub struct Config {
-    /// 要使用的 Ollama 模型（例如 "llama3.2"、"deepseek-v3.2"）
+    /// LLM 提供商
+    pub provider: LlmProvider,
+
+    /// 要使用的模型（例如 "llama3.2"、"deepseek-v3.2"）
     pub model: String,
 
     /// Ollama 服务器 URL（默认值：http://localhost:11434）
     pub ollama_host: String,
 
+    /// OpenAI 兼容服务器的基础 URL（包含 `/v1`）
+    pub openai_base_url: String,
+
+    /// OpenAI 兼容服务器的 API 密钥（本地服务器通常不需要）
+    ///
+    /// 不会被序列化，所以不会出现在保存的会话配置快照中。
+    #[serde(skip_serializing)]
+    pub openai_api_key: Option<String>,
+
     /// LLM 响应的温度（0.0 = 确定，1.0 = 创造性）
     /// 较低的值会产生更专注、事实性的响应
     pub temperature: f32,
 
+    /// 核采样阈值（0.0-1.0），None 表示使用模型默认值
+    pub top_p: Option<f32>,
+
+    /// 随机种子。固定种子（并配合固定温度）可以让 Ollama 的输出可复现
+    pub seed: Option<u64>,
+
+    /// 上下文窗口大小（令牌数），None 表示使用模型默认值
+    pub num_ctx: Option<u32>,
+
+    /// 每次回答最多生成的令牌数，None 表示不限制
+    pub max_tokens: Option<u64>,
+
     /// 要分析的最大搜索结果数
     pub max_search_results: usize,
 
+    /// 每次研究中代理最多进行的工具调用轮数（搜索、读取页面）
+    pub max_turns: usize,
+
+    /// 单次网络搜索请求的超时时间（秒）
+    pub search_timeout_secs: u64,
+
+    /// 网络搜索使用的后端
+    pub search_provider: SearchBackend,
+
+    /// SearXNG 实例的 URL（仅在 search_provider 为 searxng 时使用）
+    pub searxng_url: String,
+
+    /// 要查询的 SearXNG 类别（例如 general、news、science）
+    pub searxng_categories: Vec<String>,
+
+    /// 获取页面的超时时间（秒）
+    pub fetch_timeout_secs: u64,
+
+    /// 获取页面时最多下载的字节数
+    pub fetch_max_bytes: usize,
+
+    /// fetch_page 工具接受的 MIME 类型
+    pub fetch_content_types: Vec<String>,
+
+    /// 每个页面返回给 LLM 的近似令牌上限
+    pub page_token_budget: usize,
+
+    /// 是否允许 fetch_page 读取本地文件路径和 file:// URL
+    pub allow_local_files: bool,
+
+    /// 快速搜索中每个结果的正文预览字符数（0 表示不获取预览）
+    pub preview_chars: usize,
+
+    /// 深度研究模式最多研究的子问题数（包括研究中发现的后续问题）
+    pub deep_max_questions: usize,
+
+    /// 深度研究中每个子问题最多进行的工具调用轮数
+    pub deep_max_turns: usize,
+
+    /// 报告完成后是否核验每个引用
+    pub verify_citations: bool,
+
+    /// 保存研究会话的 SQLite 数据库路径
+    pub session_db: PathBuf,
+
     /// 应用程序的日志级别
     pub log_level: String,
+
+    /// 每个字段的值来自哪里（键是字段名，没有记录的字段使用默认值）
+    #[serde(skip)]
+    pub origins: BTreeMap<&'static str, ConfigSource>,
 }
 
 // =============================================================================
@@ -56,20 +279,76 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            // 默认使用本地 Ollama
+            provider: LlmProvider::Ollama,
+
             // 使用常见、强大的模型作为默认值
             model: "llama3.2".to_string(),
 
             // 标准 Ollama 默认端口
             ollama_host: "http://localhost:11434".to_string(),
 
+            // llama.cpp server 的默认端口
+            openai_base_url: "http://localhost:8080/v1".to_string(),
+            openai_api_key: None,
+
             // 中等温度 - 在创造性和专注之间取得平衡
             temperature: 0.7,
 
+            // 其他采样参数默认交给模型决定
+            top_p: None,
+            seed: None,
+            num_ctx: None,
+            max_tokens: None,
+
             // 默认分析前 5 个搜索结果
             max_search_results: 5,
 
+            // 一次搜索加几次页面获取，再留出写最终回答的一轮
+            max_turns: 8,
+
+            search_timeout_secs: 30,
+
+            // 默认使用免费的 DuckDuckGo
+            search_provider: SearchBackend::DuckDuckGo,
+
+            // SearXNG 的默认本地端口
+            searxng_url: "http://localhost:8888".to_string(),
+            searxng_categories: vec!["general".to_string()],
+
+            // 页面获取限制：20 秒、2 MB、约 2000 个令牌
+            fetch_timeout_secs: 20,
+            fetch_max_bytes: 2_000_000,
+            fetch_content_types: vec![
+                "text/html".to_string(),
+                "application/xhtml+xml".to_string(),
+                "text/plain".to_string(),
+                "application/pdf".to_string(),
+            ],
+            page_token_budget: 2000,
+
+            // 出于安全考虑，默认不允许代理读取本地文件
+            allow_local_files: false,
+
+            // 快速搜索显示每个页面开头的 300 个字符
+            preview_chars: 300,
+
+            // 深度研究最多研究 6 个子问题
+            deep_max_questions: 6,
+
+            // 子问题的笔记往往需要多读几个页面，比普通研究多留两轮
+            deep_max_turns: 10,
+
+            // 默认核验引用（每个引用需要一次额外的 LLM 调用）
+            verify_citations: true,
+
+            // 会话保存在用户数据目录（例如 ~/.local/share/ai-research-agent/）
+            session_db: default_session_db(),
+
             // 默认使用 info 级别日志
             log_level: "info".to_string(),
+
+            origins: BTreeMap::new(),
         }
     }
 }
@@ -78,7 +357,10 @@ impl Default for Config {
 // 配置加载
 // =============================================================================
 impl Config {
-    /// 从环境变量加载配置。
+    /// 从配置文件和环境变量加载配置。
+    ///
+    /// `profile` 是 `--profile` 选择的配置档案名称；指定了但在所有配置文件中
+    /// 都找不到对应的 `[profile.<名称>]` 时返回错误。
     ///
     /// # Rust 概念：Result 类型
     ///
@@ -96,78 +378,580 @@ impl Config {
     /// 如果 Result 是 Err，它会提前从函数返回该错误。
     ///
     /// # 示例
-    /// ```
-    /// let config = Config::from_env()?;
+    /// ```ignore
+    /// let config = Config::load(Some("fast"))?;
     /// println!("Using model: {}", config.model);
     /// ```
-    pub fn from_env() -> Result<Self> {
+    pub fn load(profile: Option<&str>) -> Result<Self> {
         // 如果存在则加载 .env 文件（静默忽略如果未找到）
         // 这对于本地开发很有用
         let _ = dotenvy::dotenv();
 
-        // 从默认值开始
+        let mut config = Self::from_files(&config_file_paths(), profile)?;
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// 从默认值开始，依次合并配置文件。
+    ///
+    /// 先合并所有文件的顶层键，再合并所有文件中选中的配置档案，
+    /// 所以配置档案总是优先于任何文件的顶层设置。不存在的文件会被跳过。
+    pub fn from_files(paths: &[PathBuf], profile: Option<&str>) -> Result<Self> {
         let mut config = Config::default();
 
+        let mut files = Vec::new();
+        for path in paths {
+            if let Some(file) = ConfigFile::read(path)? {
+                files.push(file);
+            }
+        }
+
+        for file in &files {
+            file.base.merge_into(
+                &mut config,
+                &ConfigSource::File {
+                    path: file.path.clone(),
+                    profile: None,
+                },
+            );
+        }
+
+        if let Some(name) = profile {
+            let mut found = false;
+            for file in &files {
+                if let Some(values) = file.profile(name)? {
+                    values.merge_into(
+                        &mut config,
+                        &ConfigSource::File {
+                            path: file.path.clone(),
+                            profile: Some(name.to_string()),
+                        },
+                    );
+                    found = true;
+                }
+            }
+            if !found {
+                let searched: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+                anyhow::bail!(
+                    "找不到配置档案 \"{}\"（在 {} 中查找 [profile.{}]）",
+                    name,
+                    searched.join("、"),
+                    name
+                );
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// 用环境变量覆盖当前配置。
+    fn apply_env(&mut self) -> Result<()> {
         // 如果设置了环境变量则覆盖
         //
         // # Rust 概念：if let
         // `if let` 是处理单个模式匹配的简洁方式。
         // 它等价于：
-        //   match env::var("OLLAMA_MODEL") {
-        //       Ok(val) => { config.model = val; }
-        //       Err(_) => { /* 什么都不做 */ }
+        //   match self.env_var("model", "OLLAMA_MODEL") {
+        //       Some(val) => { self.model = val; }
+        //       None => { /* 什么都不做 */ }
         //   }
-        if let Ok(val) = env::var("OLLAMA_MODEL") {
-            config.model = val;
+        if let Some(val) = self.env_var("provider", "LLM_PROVIDER") {
+            self.provider = val.parse().context("LLM_PROVIDER 无效")?;
+        }
+
+        if let Some(val) = self.env_var("model", "OLLAMA_MODEL") {
+            self.model = val;
+        }
+
+        if let Some(val) = self.env_var("ollama_host", "OLLAMA_API_BASE_URL") {
+            self.ollama_host = val;
         }
 
-        if let Ok(val) = env::var("OLLAMA_API_BASE_URL") {
-            config.ollama_host = val;
+        if let Some(val) = self.env_var("openai_base_url", "OPENAI_BASE_URL") {
+            self.openai_base_url = val;
+        }
+
+        if let Some(val) = self.env_var("openai_api_key", "OPENAI_API_KEY") {
+            self.openai_api_key = Some(val).filter(|key| !key.is_empty());
         }
 
         // 将温度从字符串解析为 f32
         // .context() 在失败时添加有用的错误消息
-        if let Ok(val) = env::var("TEMPERATURE") {
-            config.temperature = val
+        if let Some(val) = self.env_var("temperature", "TEMPERATURE") {
+            self.temperature = val
                 .parse()
                 .context("TEMPERATURE 必须是有效的浮点数（例如 0.7）")?;
         }
 
-        if let Ok(val) = env::var("MAX_SEARCH_RESULTS") {
-            config.max_search_results = val
+        if let Some(val) = self.env_var("top_p", "TOP_P") {
+            self.top_p = Some(
+                val.parse()
+                    .context("TOP_P 必须是有效的浮点数（例如 0.9）")?,
+            );
+        }
+
+        if let Some(val) = self.env_var("seed", "SEED") {
+            self.seed = Some(val.parse().context("SEED 必须是有效的非负整数")?);
+        }
+
+        if let Some(val) = self.env_var("num_ctx", "NUM_CTX") {
+            self.num_ctx = Some(val.parse().context("NUM_CTX 必须是有效的正整数")?);
+        }
+
+        if let Some(val) = self.env_var("max_tokens", "MAX_TOKENS") {
+            self.max_tokens = Some(val.parse().context("MAX_TOKENS 必须是有效的正整数")?);
+        }
+
+        if let Some(val) = self.env_var("max_search_results", "MAX_SEARCH_RESULTS") {
+            self.max_search_results = val
                 .parse()
                 .context("MAX_SEARCH_RESULTS 必须是有效的正整数")?;
         }
 
-        if let Ok(val) = env::var("RUST_LOG") {
-            config.log_level = val;
+        if let Some(val) = self.env_var("max_turns", "MAX_TURNS") {
+            self.max_turns = val.parse().context("MAX_TURNS 必须是有效的正整数")?;
         }
 
-        Ok(config)
+        if let Some(val) = self.env_var("search_timeout_secs", "SEARCH_TIMEOUT_SECS") {
+            self.search_timeout_secs = val
+                .parse()
+                .context("SEARCH_TIMEOUT_SECS 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("search_provider", "SEARCH_PROVIDER") {
+            self.search_provider = val.parse().context("SEARCH_PROVIDER 无效")?;
+        }
+
+        if let Some(val) = self.env_var("searxng_url", "SEARXNG_URL") {
+            self.searxng_url = val;
+        }
+
+        // 类别以逗号分隔，例如 "general,science"
+        if let Some(val) = self.env_var("searxng_categories", "SEARXNG_CATEGORIES") {
+            self.searxng_categories = parse_list(&val);
+        }
+
+        if let Some(val) = self.env_var("fetch_timeout_secs", "FETCH_TIMEOUT_SECS") {
+            self.fetch_timeout_secs = val
+                .parse()
+                .context("FETCH_TIMEOUT_SECS 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("fetch_max_bytes", "FETCH_MAX_BYTES") {
+            self.fetch_max_bytes = val.parse().context("FETCH_MAX_BYTES 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("fetch_content_types", "FETCH_CONTENT_TYPES") {
+            self.fetch_content_types = parse_list(&val)
+                .into_iter()
+                .map(|t| t.to_ascii_lowercase())
+                .collect();
+        }
+
+        if let Some(val) = self.env_var("page_token_budget", "PAGE_TOKEN_BUDGET") {
+            self.page_token_budget = val
+                .parse()
+                .context("PAGE_TOKEN_BUDGET 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("allow_local_files", "ALLOW_LOCAL_FILES") {
+            self.allow_local_files = val
+                .parse()
+                .context("ALLOW_LOCAL_FILES 必须是 true 或 false")?;
+        }
+
+        if let Some(val) = self.env_var("preview_chars", "PREVIEW_CHARS") {
+            self.preview_chars = val.parse().context("PREVIEW_CHARS 必须是有效的非负整数")?;
+        }
+
+        if let Some(val) = self.env_var("deep_max_questions", "DEEP_MAX_QUESTIONS") {
+            self.deep_max_questions = val
+                .parse()
+                .context("DEEP_MAX_QUESTIONS 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("deep_max_turns", "DEEP_MAX_TURNS") {
+            self.deep_max_turns = val.parse().context("DEEP_MAX_TURNS 必须是有效的正整数")?;
+        }
+
+        if let Some(val) = self.env_var("verify_citations", "VERIFY_CITATIONS") {
+            self.verify_citations = val
+                .parse()
+                .context("VERIFY_CITATIONS 必须是 true 或 false")?;
+        }
+
+        if let Some(val) = self.env_var("session_db", "SESSION_DB") {
+            self.session_db = PathBuf::from(val);
+        }
+
+        if let Some(val) = self.env_var("log_level", "RUST_LOG") {
+            self.log_level = val;
+        }
+
+        Ok(())
+    }
+
+    /// 以 TOML 格式列出生效的配置，每个值后面注明来源（`config show` 使用）。
+    ///
+    /// 输出可以直接复制到配置文件中。没有设置的可选值以注释列出，
+    /// API 密钥只显示是否设置，不显示内容。
+    pub fn describe(&self) -> Result<String> {
+        let serialized = toml::to_string(self).context("序列化配置失败")?;
+
+        let mut lines: Vec<(String, String)> = serialized
+            .lines()
+            .filter_map(|line| {
+                let (key, _) = line.split_once(" = ")?;
+                Some((line.to_string(), self.origin(key).to_string()))
+            })
+            .collect();
+
+        if self.openai_api_key.is_some() {
+            lines.push((
+                "openai_api_key = \"***\"".to_string(),
+                self.origin("openai_api_key").to_string(),
+            ));
+        }
+
+        // 对齐来源注释，但不让个别很长的值（例如内容类型列表）把所有行撑宽
+        let width = lines
+            .iter()
+            .map(|(line, _)| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(48);
+        let mut output = String::new();
+        for (line, source) in &lines {
+            let padding = width.saturating_sub(line.chars().count());
+            output.push_str(&format!("{}{}  # {}\n", line, " ".repeat(padding), source));
+        }
+
+        // toml 会跳过值为 None 的字段
+        let unset = [
+            ("top_p", self.top_p.is_none()),
+            ("seed", self.seed.is_none()),
+            ("num_ctx", self.num_ctx.is_none()),
+            ("max_tokens", self.max_tokens.is_none()),
+        ];
+        for (key, is_unset) in unset {
+            if is_unset {
+                output.push_str(&format!("# {} 未设置（使用模型默认值）\n", key));
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// 读取环境变量；设置了时记录该字段来自这个变量。
+    fn env_var(&mut self, field: &'static str, var: &'static str) -> Option<String> {
+        let val = env::var(var).ok()?;
+        self.origins.insert(field, ConfigSource::Env(var));
+        Some(val)
+    }
+
+    /// 记录某个字段的值来自哪里（命令行参数覆盖配置时使用）。
+    pub fn set_origin(&mut self, field: &'static str, source: ConfigSource) {
+        self.origins.insert(field, source);
+    }
+
+    /// 某个字段的值来自哪里。
+    pub fn origin(&self, field: &str) -> ConfigSource {
+        self.origins
+            .get(field)
+            .cloned()
+            .unwrap_or(ConfigSource::Default)
     }
 
     /// 验证配置。
     ///
     /// 这确保所有值在代理启动前都在可接受范围内。
     /// 快速失败并给出清晰的错误比以后出现令人困惑的错误更好！
+    /// 错误消息使用配置文件中的键名，并注明无效值来自哪里。
     pub fn validate(&self) -> Result<()> {
         // 温度必须在 0 到 2 之间（OpenAI/Ollama 范围）
-        if !(0.0..=2.0).contains(&self.temperature) {
-            anyhow::bail!("温度必须在 0.0 到 2.0 之间，得到：{}", self.temperature);
+        self.check(
+            (0.0..=2.0).contains(&self.temperature),
+            "temperature",
+            format!("必须在 0.0 到 2.0 之间，得到：{}", self.temperature),
+        )?;
+
+        if let Some(top_p) = self.top_p {
+            self.check(
+                top_p > 0.0 && top_p <= 1.0,
+                "top_p",
+                format!("必须在 0.0（不含）到 1.0 之间，得到：{}", top_p),
+            )?;
         }
 
+        self.check(self.num_ctx != Some(0), "num_ctx", "至少为 1")?;
+        self.check(self.max_tokens != Some(0), "max_tokens", "至少为 1")?;
+
         // 必须至少有 1 个搜索结果
-        if self.max_search_results == 0 {
-            anyhow::bail!("MAX_SEARCH_RESULTS 至少为 1");
-        }
+        self.check(
+            self.max_search_results > 0,
+            "max_search_results",
+            "至少为 1",
+        )?;
+
+        self.check(self.max_turns > 0, "max_turns", "至少为 1")?;
+        self.check(
+            self.search_timeout_secs > 0,
+            "search_timeout_secs",
+            "至少为 1",
+        )?;
+
+        // 页面获取限制必须为正
+        self.check(
+            self.fetch_timeout_secs > 0,
+            "fetch_timeout_secs",
+            "至少为 1",
+        )?;
+        self.check(self.fetch_max_bytes > 0, "fetch_max_bytes", "至少为 1")?;
+        self.check(self.page_token_budget > 0, "page_token_budget", "至少为 1")?;
+        self.check(
+            !self.fetch_content_types.is_empty(),
+            "fetch_content_types",
+            "至少需要一种内容类型",
+        )?;
+
+        // 深度研究至少要研究一个子问题
+        self.check(
+            self.deep_max_questions > 0,
+            "deep_max_questions",
+            "至少为 1",
+        )?;
+        self.check(self.deep_max_turns > 0, "deep_max_turns", "至少为 1")?;
 
         // 模型名称不能为空
-        if self.model.is_empty() {
-            anyhow::bail!("OLLAMA_MODEL 不能为空");
+        self.check(!self.model.is_empty(), "model", "不能为空")?;
+
+        // 日志级别使用 RUST_LOG 的过滤语法
+        self.check(
+            tracing_subscriber::EnvFilter::try_new(&self.log_level).is_ok(),
+            "log_level",
+            format!("不是有效的日志过滤指令，得到：{}", self.log_level),
+        )?;
+
+        // 使用 SearXNG 时必须提供有效的 HTTP(S) URL
+        if self.search_provider == SearchBackend::Searxng {
+            self.check(
+                is_http_url(&self.searxng_url),
+                "searxng_url",
+                format!(
+                    "必须以 http:// 或 https:// 开头，得到：{}",
+                    self.searxng_url
+                ),
+            )?;
+        }
+
+        // 使用 OpenAI 兼容服务器时同样需要有效的 HTTP(S) URL
+        if self.provider == LlmProvider::OpenAiCompatible {
+            self.check(
+                is_http_url(&self.openai_base_url),
+                "openai_base_url",
+                format!(
+                    "必须以 http:// 或 https:// 开头，得到：{}",
+                    self.openai_base_url
+                ),
+            )?;
         }
 
         Ok(())
     }
+
+    /// `ok` 为 false 时返回验证错误，消息中包含字段名和它的来源。
+    fn check(&self, ok: bool, field: &str, message: impl fmt::Display) -> Result<()> {
+        if !ok {
+            anyhow::bail!("{} {}（来自 {}）", field, message, self.origin(field));
+        }
+        Ok(())
+    }
+}
+
+/// 配置文件的搜索路径，后面的文件覆盖前面的：
+/// 用户配置目录（Linux 上是 `~/.config/ai-research-agent/config.toml`）
+/// 和当前目录下的 `ai-research-agent.toml`。
+fn config_file_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = dirs::config_dir() {
+        paths.push(dir.join("ai-research-agent").join("config.toml"));
+    }
+    paths.push(PathBuf::from("ai-research-agent.toml"));
+    paths
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+// =============================================================================
+// 配置文件
+// =============================================================================
+/// 配置文件中的一组设置（顶层或一个 `[profile.<名称>]` 表）。
+///
+/// 键与 `Config` 的字段同名，全部是可选的：没有出现的键保持之前的值。
+///
+/// # Rust 概念：serde 属性
+///
+/// `deny_unknown_fields` 让拼错的键（例如 `temprature`）直接报错，
+/// 而不是被静默忽略。
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileSettings {
+    provider: Option<LlmProvider>,
+    model: Option<String>,
+    ollama_host: Option<String>,
+    openai_base_url: Option<String>,
+    openai_api_key: Option<String>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    seed: Option<u64>,
+    num_ctx: Option<u32>,
+    max_tokens: Option<u64>,
+    max_search_results: Option<usize>,
+    max_turns: Option<usize>,
+    search_timeout_secs: Option<u64>,
+    search_provider: Option<SearchBackend>,
+    searxng_url: Option<String>,
+    searxng_categories: Option<Vec<String>>,
+    fetch_timeout_secs: Option<u64>,
+    fetch_max_bytes: Option<usize>,
+    fetch_content_types: Option<Vec<String>>,
+    page_token_budget: Option<usize>,
+    allow_local_files: Option<bool>,
+    preview_chars: Option<usize>,
+    deep_max_questions: Option<usize>,
+    deep_max_turns: Option<usize>,
+    verify_citations: Option<bool>,
+    session_db: Option<PathBuf>,
+    log_level: Option<String>,
+}
+
+impl FileSettings {
+    /// 把出现的键写入配置，并记录它们的来源。
+    ///
+    /// # Rust 概念：声明宏
+    ///
+    /// 每个字段的合并逻辑都一样，用 `macro_rules!` 展开可以避免
+    /// 二十多段几乎相同的 `if let`；`stringify!` 把字段名变成来源表的键。
+    fn merge_into(&self, config: &mut Config, source: &ConfigSource) {
+        macro_rules! merge {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = &self.$field {
+                    config.$field = value.clone();
+                    config.origins.insert(stringify!($field), source.clone());
+                }
+            )*};
+        }
+        macro_rules! merge_optional {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = &self.$field {
+                    config.$field = Some(value.clone());
+                    config.origins.insert(stringify!($field), source.clone());
+                }
+            )*};
+        }
+
+        merge!(
+            provider,
+            model,
+            ollama_host,
+            openai_base_url,
+            temperature,
+            max_search_results,
+            max_turns,
+            search_timeout_secs,
+            search_provider,
+            searxng_url,
+            searxng_categories,
+            fetch_timeout_secs,
+            fetch_max_bytes,
+            page_token_budget,
+            allow_local_files,
+            preview_chars,
+            deep_max_questions,
+            deep_max_turns,
+            verify_citations,
+            session_db,
+            log_level,
+        );
+        merge_optional!(openai_api_key, top_p, seed, num_ctx, max_tokens);
+
+        // 与环境变量一致，内容类型统一转为小写
+        if let Some(types) = &self.fetch_content_types {
+            config.fetch_content_types = types.iter().map(|t| t.to_ascii_lowercase()).collect();
+            config.origins.insert("fetch_content_types", source.clone());
+        }
+    }
+}
+
+/// 一个已解析的配置文件：顶层设置和 `[profile.*]` 表。
+struct ConfigFile {
+    path: PathBuf,
+    base: FileSettings,
+    profiles: toml::Table,
+}
+
+impl ConfigFile {
+    /// 读取并解析配置文件；文件不存在时返回 None。
+    fn read(path: &Path) -> Result<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("读取配置文件 {} 失败", path.display()))
+            }
+        };
+        let invalid = || format!("配置文件 {} 无效", path.display());
+
+        let mut table: toml::Table = toml::from_str(&text).with_context(invalid)?;
+        let profiles = match table.remove("profile") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => anyhow::bail!("{}：profile 必须是表，例如 [profile.fast]", invalid()),
+            None => toml::Table::new(),
+        };
+        let base = toml::Value::Table(table).try_into().with_context(invalid)?;
+
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            base,
+            profiles,
+        }))
+    }
+
+    /// 解析文件中名为 `name` 的配置档案；文件没有定义它时返回 None。
+    fn profile(&self, name: &str) -> Result<Option<FileSettings>> {
+        let Some(value) = self.profiles.get(name) else {
+            return Ok(None);
+        };
+        let settings = value.clone().try_into().with_context(|| {
+            format!(
+                "配置文件 {} 中的 [profile.{}] 无效",
+                self.path.display(),
+                name
+            )
+        })?;
+        Ok(Some(settings))
+    }
+}
+
+/// 把逗号分隔的字符串拆分为去除空白的非空列表。
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 默认的会话数据库路径：用户数据目录下的 ai-research-agent/sessions.db，
+/// 找不到数据目录时使用当前目录。
+fn default_session_db() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("ai-research-agent"))
+        .unwrap_or_default()
+        .join("sessions.db")
 }
 
 // =============================================================================
@@ -192,6 +976,7 @@ mod tests {
         assert_eq!(config.ollama_host, "http://localhost:11434");
         assert!((config.temperature - 0.7).abs() < f32::EPSILON);
         assert_eq!(config.max_search_results, 5);
+        assert_eq!(config.search_provider, SearchBackend::DuckDuckGo);
     }
 
     #[test]
@@ -202,15 +987,286 @@ mod tests {
 
     #[test]
     fn test_config_validation_invalid_temperature() {
-        let mut config = Config::default();
-        config.temperature = 3.0; // 无效：超过 2.0
+        let config = Config {
+            temperature: 3.0, // 无效：超过 2.0
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_config_validation_sampling_params() {
+        let valid = Config {
+            top_p: Some(0.9),
+            seed: Some(0),
+            num_ctx: Some(8192),
+            max_tokens: Some(512),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        for invalid in [
+            Config {
+                top_p: Some(0.0),
+                ..Default::default()
+            },
+            Config {
+                top_p: Some(1.5),
+                ..Default::default()
+            },
+            Config {
+                num_ctx: Some(0),
+                ..Default::default()
+            },
+            Config {
+                max_tokens: Some(0),
+                ..Default::default()
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+
     #[test]
     fn test_config_validation_invalid_search_results() {
-        let mut config = Config::default();
-        config.max_search_results = 0; // 无效：至少为 1
+        let config = Config {
+            max_search_results: 0, // 无效：至少为 1
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_search_backend_parsing() {
+        assert_eq!(
+            "DuckDuckGo".parse::<SearchBackend>().unwrap(),
+            SearchBackend::DuckDuckGo
+        );
+        assert_eq!(
+            "ddg".parse::<SearchBackend>().unwrap(),
+            SearchBackend::DuckDuckGo
+        );
+        assert_eq!(
+            "searxng".parse::<SearchBackend>().unwrap(),
+            SearchBackend::Searxng
+        );
+        assert!("bing".parse::<SearchBackend>().is_err());
+    }
+
+    #[test]
+    fn test_llm_provider_parsing() {
+        assert_eq!(
+            "ollama".parse::<LlmProvider>().unwrap(),
+            LlmProvider::Ollama
+        );
+        assert_eq!(
+            "OpenAI-Compatible".parse::<LlmProvider>().unwrap(),
+            LlmProvider::OpenAiCompatible
+        );
+        assert_eq!(
+            LlmProvider::OpenAiCompatible.to_string(),
+            "openai-compatible"
+        );
+        assert!("anthropic".parse::<LlmProvider>().is_err());
+
+        let config = Config {
+            provider: LlmProvider::OpenAiCompatible,
+            openai_base_url: "localhost:8080/v1".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_snapshot_hides_api_key() {
+        let config = Config {
+            openai_api_key: Some("sk-secret".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("sk-secret"));
+        assert!(json.contains("\"provider\":\"ollama\""));
+    }
+
+    #[test]
+    fn test_config_validation_invalid_searxng_url() {
+        let config = Config {
+            search_provider: SearchBackend::Searxng,
+            searxng_url: "localhost:8888".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_invalid_fetch_limits() {
+        let config = Config {
+            page_token_budget: 0,
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
+
+        let config = Config {
+            fetch_content_types: Vec::new(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = Config {
+            deep_max_questions: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config {
+            deep_max_turns: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    /// 把配置文件写入临时目录（文件名包含进程号，避免并行测试冲突）
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let path =
+            env::temp_dir().join(format!("ai-research-agent-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_config_files_and_profiles() {
+        let user = write_config(
+            "user.toml",
+            r#"
+model = "llama3.2"
+temperature = 0.5
+search_provider = "ddg"
+
+[profile.fast]
+model = "gemma2:2b"
+verify_citations = false
+"#,
+        );
+        let project = write_config(
+            "project.toml",
+            r#"
+temperature = 0.2
+fetch_content_types = ["TEXT/HTML"]
+
+[profile.fast]
+max_search_results = 3
+"#,
+        );
+        let missing = env::temp_dir().join("ai-research-agent-missing.toml");
+        let paths = [user.clone(), project.clone(), missing];
+
+        // 没有选择配置档案：只合并顶层，后面的文件覆盖前面的
+        let config = Config::from_files(&paths, None).unwrap();
+        assert_eq!(config.model, "llama3.2");
+        assert!((config.temperature - 0.2).abs() < f32::EPSILON);
+        assert_eq!(config.fetch_content_types, vec!["text/html"]);
+        assert!(config.verify_citations);
+        assert_eq!(
+            config.origin("temperature"),
+            ConfigSource::File {
+                path: project.clone(),
+                profile: None
+            }
+        );
+        assert_eq!(config.origin("max_search_results"), ConfigSource::Default);
+
+        // 配置档案优先于所有文件的顶层设置，并且可以分布在多个文件中
+        let config = Config::from_files(&paths, Some("fast")).unwrap();
+        assert_eq!(config.model, "gemma2:2b");
+        assert!(!config.verify_citations);
+        assert_eq!(config.max_search_results, 3);
+        assert_eq!(
+            config.origin("model"),
+            ConfigSource::File {
+                path: user.clone(),
+                profile: Some("fast".to_string())
+            }
+        );
+
+        let err = Config::from_files(&paths, Some("turbo")).unwrap_err();
+        assert!(err.to_string().contains("turbo"));
+
+        std::fs::remove_file(user).unwrap();
+        std::fs::remove_file(project).unwrap();
+    }
+
+    #[test]
+    fn test_config_file_rejects_unknown_keys() {
+        let path = write_config("typo.toml", "temprature = 0.5\n");
+        let err = Config::from_files(std::slice::from_ref(&path), None).unwrap_err();
+        assert!(format!("{:#}", err).contains("temprature"));
+
+        let bad_profile = write_config("bad-profile.toml", "[profile.fast]\nmodle = \"x\"\n");
+        let err = Config::from_files(std::slice::from_ref(&bad_profile), Some("fast")).unwrap_err();
+        assert!(err.to_string().contains("[profile.fast]"));
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(bad_profile).unwrap();
+    }
+
+    #[test]
+    fn test_validation_error_names_source() {
+        let path = write_config("invalid.toml", "[profile.hot]\ntemperature = 3.0\n");
+        let config = Config::from_files(std::slice::from_ref(&path), Some("hot")).unwrap();
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.starts_with("temperature "));
+        assert!(message.contains(&format!("配置文件 {} 的 [profile.hot]", path.display())));
+
+        let mut config = Config {
+            max_search_results: 0,
+            ..Default::default()
+        };
+        config.set_origin(
+            "max_search_results",
+            ConfigSource::Env("MAX_SEARCH_RESULTS"),
+        );
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("环境变量 MAX_SEARCH_RESULTS"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_describe_lists_sources() {
+        let mut config = Config {
+            temperature: 0.2,
+            seed: Some(42),
+            openai_api_key: Some("sk-secret".to_string()),
+            ..Default::default()
+        };
+        config.set_origin("temperature", ConfigSource::Cli("--temperature"));
+        config.set_origin("seed", ConfigSource::Env("SEED"));
+
+        let output = config.describe().unwrap();
+        let line = |key: &str| {
+            output
+                .lines()
+                .find(|l| l.starts_with(&format!("{} = ", key)))
+                .unwrap_or_else(|| panic!("缺少 {}：\n{}", key, output))
+        };
+        assert!(line("temperature").ends_with("# 命令行参数 --temperature"));
+        assert!(line("seed").ends_with("# 环境变量 SEED"));
+        assert!(line("model").contains("\"llama3.2\""));
+        assert!(line("model").ends_with("# 默认值"));
+        assert!(line("openai_api_key").contains("***"));
+        assert!(!output.contains("sk-secret"));
+        assert!(output.contains("# top_p 未设置"));
+        assert!(!output.contains("# seed 未设置"));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list(" general, science ,,"),
+            vec!["general", "science"]
+        );
+        assert!(parse_list("").is_empty());
     }
 }
@@ -0,0 +1,341 @@
+//! # 深度研究模块
+//!
+//! 普通研究模式只搜索一次就写摘要。深度模式把问题拆分为子问题，
+//! 逐个搜索和阅读来源，并在研究过程中记录新发现的"待解决问题"，
+//! 直到所有问题都得到回答（覆盖完成）或达到预算为止，最后再综合成报告。
+//!
+//! 本模块只包含与 LLM 无关的部分：提示词、解析 LLM 输出，以及
+//! 跟踪研究进度的 `ResearchPlan`。实际调用 LLM 的循环在 `agent.rs` 中。
+//! 它演示了：
+//! - 使用 `VecDeque` 实现工作队列
+//! - 容错地解析 LLM 的半结构化输出
+
+use std::collections::VecDeque;
+
+// =============================================================================
+// 提示词
+// =============================================================================
+/// 规划阶段的系统提示：把问题拆分为子问题。
+pub const PLANNER_PROMPT: &str = r#"
+你是一个研究规划助手。把用户的研究问题拆分为 3-5 个可以独立搜索的子问题。
+
+要求：
+1. 每个子问题都应具体、可以通过网络搜索回答
+2. 子问题合起来应覆盖原问题的各个方面
+3. 只输出一个 JSON 字符串数组，不要输出其他内容
+
+示例输出：
+["Rust 异步运行时有哪些主流实现？", "Tokio 和 async-std 的性能差异是什么？"]
+"#;
+
+/// 子问题研究阶段的系统提示：允许多次搜索，并报告待解决问题。
+pub const SUB_QUESTION_PROMPT: &str = r#"
+你是一个研究助手，负责回答一个大型研究项目中的一个子问题。
+
+重要说明：
+1. 使用 web_search 工具查找信息，最多搜索 3 次
+2. 使用 fetch_page 工具阅读最相关的页面
+3. 答案必须基于你阅读的页面内容，每个事实后面用工具给出的编号标注来源，例如 [1]
+4. 回答结束后，另起一行写 "待解决问题:"，然后列出研究中发现但尚未回答的
+   关键问题（每行一个，以 "- " 开头）；如果没有，写 "- 无"
+
+保持回答简洁，只包含与子问题相关的事实。
+"#;
+
+/// 综合阶段的系统提示：把各子问题的发现写成最终报告。
+pub const SYNTHESIS_PROMPT: &str = r#"
+你是一个研究助手。根据提供的子问题研究笔记，为原始问题撰写最终研究报告。
+
+回复格式：
+## 概述
+直接回答原始问题
+## 详细发现
+按主题组织笔记中的关键信息
+## 仍未解决的问题
+列出笔记中标记为未研究的问题（如果有）
+
+只使用笔记中的信息。保留笔记中的 [n] 来源编号，每个事实性陈述后都要标注；
+不要在正文中写 URL，也不要自己写来源列表。
+"#;
+
+/// 子问题回答中分隔待解决问题的标记
+const OPEN_QUESTIONS_MARKER: &str = "待解决问题";
+
+// =============================================================================
+// 研究计划
+// =============================================================================
+/// 一个子问题及其研究结果。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub question: String,
+    pub answer: String,
+}
+
+/// 深度研究的进度：待研究的问题队列和已完成的发现。
+///
+/// # Rust 概念：VecDeque
+///
+/// `VecDeque` 是双端队列，从头部取出、向尾部追加都是 O(1)，
+/// 非常适合"先规划的问题先研究，新问题排在后面"的工作队列。
+#[derive(Debug)]
+pub struct ResearchPlan {
+    /// 原始研究问题
+    pub query: String,
+
+    /// 尚未研究的问题
+    open: VecDeque<String>,
+
+    /// 已研究的子问题及答案
+    findings: Vec<Finding>,
+
+    /// 最多研究的子问题数（包括后续发现的问题）
+    max_questions: usize,
+
+    /// 所有曾经入队的问题（用于去重）
+    seen: Vec<String>,
+}
+
+impl ResearchPlan {
+    /// 用规划得到的子问题创建研究计划。
+    ///
+    /// 如果规划没有得到任何子问题，就把原始问题本身作为唯一的子问题。
+    pub fn new(query: &str, sub_questions: Vec<String>, max_questions: usize) -> Self {
+        let mut plan = Self {
+            query: query.to_string(),
+            open: VecDeque::new(),
+            findings: Vec::new(),
+            max_questions,
+            seen: Vec::new(),
+        };
+
+        if sub_questions.is_empty() {
+            plan.push(query.to_string());
+        }
+        for question in sub_questions {
+            plan.push(question);
+        }
+        plan
+    }
+
+    /// 取出下一个要研究的问题；覆盖完成或预算用尽时返回 None。
+    pub fn next_question(&mut self) -> Option<String> {
+        if self.budget_exhausted() {
+            return None;
+        }
+        self.open.pop_front()
+    }
+
+    /// 记录一个子问题的答案，并把其中的待解决问题加入队列。
+    pub fn record(&mut self, question: String, response: &str) {
+        let (answer, follow_ups) = split_open_questions(response);
+        self.findings.push(Finding { question, answer });
+        for follow_up in follow_ups {
+            self.push(follow_up);
+        }
+    }
+
+    /// 是否已经达到研究预算
+    pub fn budget_exhausted(&self) -> bool {
+        self.findings.len() >= self.max_questions
+    }
+
+    /// 已完成的发现
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// 因预算限制未能研究的问题
+    pub fn unanswered(&self) -> impl Iterator<Item = &String> {
+        self.open.iter()
+    }
+
+    /// 为综合阶段构建提示：原始问题、每个子问题的笔记和未解决问题。
+    pub fn synthesis_prompt(&self) -> String {
+        let mut prompt = format!("原始问题：{}\n\n## 研究笔记\n", self.query);
+        for (i, finding) in self.findings.iter().enumerate() {
+            prompt.push_str(&format!(
+                "\n### 子问题 {}：{}\n{}\n",
+                i + 1,
+                finding.question,
+                finding.answer
+            ));
+        }
+
+        let unanswered: Vec<_> = self.unanswered().collect();
+        if !unanswered.is_empty() {
+            prompt.push_str("\n## 未研究的问题（预算已用尽）\n");
+            for question in unanswered {
+                prompt.push_str(&format!("- {}\n", question));
+            }
+        }
+        prompt
+    }
+
+    /// 去重后把问题加入队列
+    fn push(&mut self, question: String) {
+        let key = normalize_question(&question);
+        if key.is_empty() || self.seen.contains(&key) {
+            return;
+        }
+        self.seen.push(key);
+        self.open.push_back(question);
+    }
+}
+
+// =============================================================================
+// 解析 LLM 输出
+// =============================================================================
+/// 从规划器的回复中解析子问题列表。
+///
+/// 优先解析 JSON 数组（允许被代码块或说明文字包围）；
+/// 小模型经常不遵守格式，所以退而把编号或项目符号列表的每一行当作一个问题。
+pub fn parse_sub_questions(response: &str) -> Vec<String> {
+    if let (Some(start), Some(end)) = (response.find('['), response.rfind(']')) {
+        if start < end {
+            if let Ok(items) = serde_json::from_str::<Vec<String>>(&response[start..=end]) {
+                return items
+                    .into_iter()
+                    .map(|q| q.trim().to_string())
+                    .filter(|q| !q.is_empty())
+                    .collect();
+            }
+        }
+    }
+
+    response
+        .lines()
+        .filter_map(strip_list_marker)
+        .map(str::to_string)
+        .collect()
+}
+
+/// 把子问题的回答拆分为（答案，待解决问题）。
+pub fn split_open_questions(response: &str) -> (String, Vec<String>) {
+    let Some(idx) = response.rfind(OPEN_QUESTIONS_MARKER) else {
+        return (response.trim().to_string(), Vec::new());
+    };
+
+    let answer = response[..idx]
+        .trim_end()
+        .trim_end_matches(['*', '#'])
+        .trim_end()
+        .to_string();
+    let questions = response[idx + OPEN_QUESTIONS_MARKER.len()..]
+        .lines()
+        .filter_map(strip_list_marker)
+        .filter(|q| !matches!(q.trim_end_matches(['。', '.']), "无" | "none" | "None"))
+        .map(str::to_string)
+        .collect();
+
+    (answer, questions)
+}
+
+/// 如果一行是列表项（"- "、"* "、"1. "、"1) "），返回去掉标记后的内容。
+fn strip_list_marker(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let rest = if let Some(rest) = line.strip_prefix("- ").or(line.strip_prefix("* ")) {
+        rest
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        line[digits..]
+            .strip_prefix(". ")
+            .or(line[digits..].strip_prefix(") "))?
+    };
+
+    let rest = rest.trim();
+    (!rest.is_empty()).then_some(rest)
+}
+
+/// 用于去重的问题键：小写并去掉空白和末尾标点
+fn normalize_question(question: &str) -> String {
+    question
+        .trim()
+        .trim_end_matches(['?', '？', '。', '.'])
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sub_questions_json() {
+        let response = "好的，以下是子问题：\n```json\n[\"问题一？\", \" 问题二 \", \"\"]\n```";
+        assert_eq!(parse_sub_questions(response), vec!["问题一？", "问题二"]);
+    }
+
+    #[test]
+    fn test_parse_sub_questions_list_fallback() {
+        let response = "子问题如下：\n1. What is Tokio?\n2) How does async-std differ?\n- Is smol maintained?\n说明文字";
+        assert_eq!(
+            parse_sub_questions(response),
+            vec![
+                "What is Tokio?",
+                "How does async-std differ?",
+                "Is smol maintained?"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_open_questions() {
+        let response = "Tokio 是最流行的运行时 (https://tokio.rs)。\n\n**待解决问题:**\n- Tokio 的调度器如何工作？\n- 无";
+        let (answer, questions) = split_open_questions(response);
+        assert_eq!(answer, "Tokio 是最流行的运行时 (https://tokio.rs)。");
+        assert_eq!(questions, vec!["Tokio 的调度器如何工作？"]);
+
+        let (answer, questions) = split_open_questions("没有标记的回答");
+        assert_eq!(answer, "没有标记的回答");
+        assert!(questions.is_empty());
+    }
+
+    #[test]
+    fn test_plan_stops_when_covered() {
+        let mut plan = ResearchPlan::new("q", vec!["a".into(), "b".into()], 5);
+
+        let first = plan.next_question().unwrap();
+        plan.record(first, "答案 A\n待解决问题:\n- c\n- B?");
+        let second = plan.next_question().unwrap();
+        plan.record(second, "答案 B\n待解决问题:\n- 无");
+        let third = plan.next_question().unwrap();
+        assert_eq!(third, "c");
+        plan.record(third, "答案 C");
+
+        // 所有问题都已回答：覆盖完成
+        assert_eq!(plan.next_question(), None);
+        assert_eq!(plan.findings().len(), 3);
+        assert!(!plan.budget_exhausted());
+    }
+
+    #[test]
+    fn test_plan_stops_at_budget() {
+        let mut plan = ResearchPlan::new("q", vec!["a".into(), "b".into(), "c".into()], 2);
+        assert_eq!(plan.unanswered().count(), 3);
+
+        while let Some(question) = plan.next_question() {
+            plan.record(question, "答案\n待解决问题:\n- d");
+        }
+
+        assert!(plan.budget_exhausted());
+        assert_eq!(plan.findings().len(), 2);
+        let prompt = plan.synthesis_prompt();
+        assert!(prompt.contains("子问题 2：b"));
+        assert!(prompt.contains("未研究的问题"));
+        assert!(prompt.contains("- c\n- d"));
+    }
+
+    #[test]
+    fn test_plan_falls_back_to_query() {
+        let mut plan = ResearchPlan::new("原始问题", Vec::new(), 3);
+        assert_eq!(plan.next_question().as_deref(), Some("原始问题"));
+    }
+}
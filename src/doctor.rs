@@ -0,0 +1,426 @@
+//! # 环境检查模块
+//!
+//! `doctor` 子命令在开始研究之前检查运行环境，把常见问题一次性列出来，
+//! 而不是等研究进行到一半才失败：
+//! - 配置是否有效（复用 `Config::validate`）
+//! - LLM 服务器是否可以连接
+//! - 模型是否已经拉取（Ollama 的 `/api/tags`，OpenAI 兼容服务器的 `/v1/models`）
+//! - 模型是否支持工具调用（Ollama 的 `/api/show`）
+//! - 配置的每个搜索后端能否回答一个探测查询
+//!
+//! 它演示了：
+//! - 用枚举表示检查结果，并据此渲染表格
+//! - 前面的检查失败时跳过依赖它的检查
+
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::config::{Config, ConfigSource, LlmProvider, SearchBackend};
+use crate::models::OllamaModels;
+use crate::search::{self, SearchOptions, SearchProvider, SearxngProvider};
+
+/// 每个 HTTP 探测请求的超时时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 探测搜索后端时使用的查询
+const PROBE_QUERY: &str = "rust programming language";
+
+// =============================================================================
+// 检查结果
+// =============================================================================
+/// 一项检查的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+
+    /// 不影响使用，但可能有问题
+    Warn,
+
+    Fail,
+
+    /// 前置检查失败或不适用，没有执行
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "✅ 通过"),
+            Self::Warn => write!(f, "⚠️ 警告"),
+            Self::Fail => write!(f, "❌ 失败"),
+            Self::Skip => write!(f, "➖ 跳过"),
+        }
+    }
+}
+
+/// 一项检查：名称、结果和说明。
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+// =============================================================================
+// 执行检查
+// =============================================================================
+/// 执行所有检查并返回结果（按执行顺序）。
+pub async fn run_checks(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(match config.validate() {
+        Ok(()) => Check::new("配置", Status::Pass, "所有值都在有效范围内"),
+        Err(e) => Check::new("配置", Status::Fail, e.to_string()),
+    });
+
+    let client = match Client::builder().timeout(PROBE_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(Check::new("HTTP 客户端", Status::Fail, e.to_string()));
+            return checks;
+        }
+    };
+
+    match config.provider {
+        LlmProvider::Ollama => check_ollama(&client, config, &mut checks).await,
+        LlmProvider::OpenAiCompatible => check_openai(&client, config, &mut checks).await,
+    }
+
+    // 正在使用的搜索后端，以及明确配置了地址的 SearXNG 实例
+    checks.push(probe_search(search::provider_from_config(config).as_ref()).await);
+    if config.search_provider != SearchBackend::Searxng
+        && config.origin("searxng_url") != ConfigSource::Default
+    {
+        let searxng = SearxngProvider::new(&config.searxng_url, config.searxng_categories.clone())
+            .with_timeout(PROBE_TIMEOUT);
+        checks.push(probe_search(&searxng).await);
+    }
+
+    checks
+}
+
+/// `/api/show` 的响应中我们关心的部分
+#[derive(Debug, Deserialize)]
+struct OllamaShow {
+    /// 模型能力，例如 ["completion", "tools"]（Ollama 0.6 起提供）
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+
+    /// 提示模板；旧版本没有 capabilities 时，支持工具的模板会引用 `.Tools`
+    #[serde(default)]
+    template: String,
+}
+
+/// 检查 Ollama 服务器、模型和工具调用支持。
+async fn check_ollama(client: &Client, config: &Config, checks: &mut Vec<Check>) {
+    let host = config.ollama_host.trim_end_matches('/');
+
+    // 已拉取的模型列表同时说明服务器可用
+    let pulled = match OllamaModels::new(host) {
+        Ok(models) => models.is_pulled(&config.model).await,
+        Err(e) => Err(e),
+    };
+    let pulled = match pulled {
+        Ok(pulled) => {
+            checks.push(Check::new("Ollama 服务器", Status::Pass, host));
+            pulled
+        }
+        Err(e) => {
+            checks.push(Check::new(
+                "Ollama 服务器",
+                Status::Fail,
+                format!("{}：{}（运行 ollama serve）", host, e),
+            ));
+            checks.push(Check::new("模型", Status::Skip, "服务器不可用"));
+            checks.push(Check::new("工具调用", Status::Skip, "服务器不可用"));
+            return;
+        }
+    };
+
+    if !pulled {
+        checks.push(Check::new(
+            "模型",
+            Status::Fail,
+            format!(
+                "{} 未拉取（运行 ollama pull {}，或研究时加 --pull）",
+                config.model, config.model
+            ),
+        ));
+        checks.push(Check::new("工具调用", Status::Skip, "模型未拉取"));
+        return;
+    }
+    checks.push(Check::new(
+        "模型",
+        Status::Pass,
+        format!("{} 已拉取", config.model),
+    ));
+
+    let request = client
+        .post(format!("{}/api/show", host))
+        .json(&serde_json::json!({ "model": config.model }));
+    checks.push(match get_json::<OllamaShow>(request).await {
+        Ok(show) => tool_support(&config.model, &show),
+        Err(e) => Check::new("工具调用", Status::Warn, format!("无法查询模型信息：{}", e)),
+    });
+}
+
+/// 根据 `/api/show` 的结果判断模型是否支持工具调用。
+fn tool_support(model: &str, show: &OllamaShow) -> Check {
+    let supported = match &show.capabilities {
+        Some(capabilities) => capabilities.iter().any(|c| c == "tools"),
+        None => show.template.contains(".Tools"),
+    };
+    if supported {
+        Check::new("工具调用", Status::Pass, format!("{} 支持工具调用", model))
+    } else if show.capabilities.is_some() {
+        Check::new(
+            "工具调用",
+            Status::Fail,
+            format!(
+                "{} 不支持工具调用，研究需要 web_search 和 fetch_page（换用 llama3.2、qwen2.5 等）",
+                model
+            ),
+        )
+    } else {
+        Check::new(
+            "工具调用",
+            Status::Warn,
+            format!("无法确定 {} 是否支持工具调用（Ollama 版本较旧）", model),
+        )
+    }
+}
+
+/// `/v1/models` 的响应
+#[derive(Debug, Deserialize)]
+struct OpenAiModels {
+    data: Vec<OpenAiModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModel {
+    id: String,
+}
+
+/// 检查 OpenAI 兼容服务器和模型。
+///
+/// Chat Completions API 没有查询模型能力的接口，所以不检查工具调用。
+async fn check_openai(client: &Client, config: &Config, checks: &mut Vec<Check>) {
+    let base = config.openai_base_url.trim_end_matches('/');
+    let mut request = client.get(format!("{}/models", base));
+    if let Some(key) = &config.openai_api_key {
+        request = request.bearer_auth(key);
+    }
+
+    let models: OpenAiModels = match get_json(request).await {
+        Ok(models) => {
+            checks.push(Check::new("OpenAI 兼容服务器", Status::Pass, base));
+            models
+        }
+        Err(e) => {
+            checks.push(Check::new(
+                "OpenAI 兼容服务器",
+                Status::Fail,
+                format!("{}：{}", base, e),
+            ));
+            checks.push(Check::new("模型", Status::Skip, "服务器不可用"));
+            checks.push(Check::new("工具调用", Status::Skip, "服务器不可用"));
+            return;
+        }
+    };
+
+    // llama.cpp 等单模型服务器忽略请求中的模型名称，所以没有列出只是警告
+    checks.push(if models.data.iter().any(|m| m.id == config.model) {
+        Check::new("模型", Status::Pass, format!("{} 可用", config.model))
+    } else {
+        let available: Vec<_> = models.data.iter().map(|m| m.id.as_str()).collect();
+        Check::new(
+            "模型",
+            Status::Warn,
+            format!(
+                "服务器没有列出 {}（可用：{}）",
+                config.model,
+                available.join("、")
+            ),
+        )
+    });
+    checks.push(Check::new(
+        "工具调用",
+        Status::Skip,
+        "OpenAI 兼容 API 无法查询模型能力，请确认服务器启用了工具调用",
+    ));
+}
+
+/// 用探测查询检查搜索后端。
+async fn probe_search(provider: &dyn SearchProvider) -> Check {
+    let name = format!("搜索（{}）", provider.name());
+    match provider
+        .search(PROBE_QUERY, &SearchOptions { max_results: 1 })
+        .await
+    {
+        Ok(results) if results.is_empty() => Check::new(name, Status::Warn, "探测查询没有返回结果"),
+        Ok(results) => Check::new(name, Status::Pass, format!("返回了 {}", results[0].url)),
+        Err(e) => Check::new(name, Status::Fail, e.to_string()),
+    }
+}
+
+/// 发送请求并把成功的 JSON 响应解析为 `T`。
+async fn get_json<T: for<'de> Deserialize<'de>>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, reqwest::Error> {
+    request.send().await?.error_for_status()?.json().await
+}
+
+// =============================================================================
+// 输出
+// =============================================================================
+/// 把检查结果渲染为对齐的表格。
+pub fn render(checks: &[Check]) -> String {
+    let name_width = checks
+        .iter()
+        .map(|c| display_width(&c.name))
+        .max()
+        .unwrap_or(0)
+        .max(display_width("检查"));
+
+    let mut output = format!("{}  {}  详情\n", pad("状态", 8), pad("检查", name_width));
+    for check in checks {
+        output.push_str(&format!(
+            "{}  {}  {}\n",
+            pad(&check.status.to_string(), 8),
+            pad(&check.name, name_width),
+            check.detail
+        ));
+    }
+    output
+}
+
+/// 终端中的显示宽度：中文字符和表情符号占两列。
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .filter(|c| *c != '\u{fe0f}')
+        .map(|c| if c.is_ascii() { 1 } else { 2 })
+        .sum()
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{StubResponse, StubServer};
+
+    /// 模拟 Ollama（/api/tags、/api/show）和 SearXNG（/search）的桩服务器
+    async fn stub(capabilities: &'static str) -> StubServer {
+        StubServer::spawn(move |request| {
+            if request.path == "/api/tags" {
+                StubResponse::json(r#"{"models":[{"name":"llama3.2:latest"},{"name":"gemma:2b"}]}"#)
+            } else if request.path == "/api/show" {
+                StubResponse::json(format!(
+                    r#"{{"capabilities":{},"template":""}}"#,
+                    capabilities
+                ))
+            } else if request.path.starts_with("/search") {
+                StubResponse::json(
+                    r#"{"results":[{"url":"https://www.rust-lang.org/","title":"Rust"}]}"#,
+                )
+            } else {
+                StubResponse::with_type(404, "text/plain", "not found")
+            }
+        })
+        .await
+    }
+
+    fn config_for(server: &StubServer, model: &str) -> Config {
+        Config {
+            ollama_host: server.url.clone(),
+            model: model.to_string(),
+            search_provider: SearchBackend::Searxng,
+            searxng_url: server.url.clone(),
+            ..Config::default()
+        }
+    }
+
+    fn status(checks: &[Check], name: &str) -> Status {
+        checks
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("缺少检查 {}：{:?}", name, checks))
+            .status
+    }
+
+    #[tokio::test]
+    async fn test_all_checks_pass() {
+        let server = stub(r#"["completion","tools"]"#).await;
+        let checks = run_checks(&config_for(&server, "llama3.2")).await;
+
+        assert!(
+            checks.iter().all(|c| c.status == Status::Pass),
+            "{:?}",
+            checks
+        );
+        assert_eq!(status(&checks, "搜索（searxng）"), Status::Pass);
+
+        let show = server
+            .requests()
+            .into_iter()
+            .find(|r| r.path == "/api/show")
+            .unwrap();
+        assert!(show.body.contains("llama3.2"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_model_and_tools() {
+        let server = stub(r#"["completion"]"#).await;
+
+        let checks = run_checks(&config_for(&server, "qwen2.5")).await;
+        assert_eq!(status(&checks, "模型"), Status::Fail);
+        assert_eq!(status(&checks, "工具调用"), Status::Skip);
+
+        let checks = run_checks(&config_for(&server, "gemma:2b")).await;
+        assert_eq!(status(&checks, "模型"), Status::Pass);
+        assert_eq!(status(&checks, "工具调用"), Status::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_skips_dependent_checks() {
+        let server = stub("[]").await;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = Config {
+            ollama_host: format!("http://{}", listener.local_addr().unwrap()),
+            temperature: 5.0,
+            ..config_for(&server, "llama3.2")
+        };
+        drop(listener);
+
+        let checks = run_checks(&config).await;
+        assert_eq!(status(&checks, "配置"), Status::Fail);
+        assert_eq!(status(&checks, "Ollama 服务器"), Status::Fail);
+        assert_eq!(status(&checks, "模型"), Status::Skip);
+        assert_eq!(status(&checks, "搜索（searxng）"), Status::Pass);
+
+        let table = render(&checks);
+        assert!(table.lines().next().unwrap().starts_with("状态"));
+        assert!(table.contains("❌ 失败"));
+    }
+}
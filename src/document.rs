@@ -0,0 +1,398 @@
+//! # 文档模块
+//!
+//! 本模块把不同格式的内容（HTML 正文、PDF、纯文本）统一为按页组织的文档，
+//! 然后切分为带页码的文本块。无论来源是什么格式，后续步骤
+//! （令牌预算、工具输出、引用）都使用同一条分块流水线。
+//!
+//! 它演示了：
+//! - 为同步的 CPU 密集型库（pdf-extract）提供纯函数接口
+//! - 使用 `Option<usize>` 表示"可能没有"的页码
+//! - 基于 Unicode 字符的安全字符串截断
+
+use thiserror::Error;
+
+/// 每个文本块的近似令牌上限
+const CHUNK_TOKENS: usize = 400;
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 解析文档时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum DocumentError {
+    #[error("无法解析 PDF: {0}")]
+    Pdf(String),
+}
+
+// =============================================================================
+// 页和块
+// =============================================================================
+/// 文档中的一页。
+///
+/// HTML 页面和没有分页符的纯文本只有一页，且没有页码（`number` 为 `None`）。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// 从 1 开始的页码
+    pub number: Option<usize>,
+    pub text: String,
+}
+
+/// 分块流水线的输出：一段不超过 CHUNK_TOKENS 的文本及其所在页码。
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Chunk {
+    /// 块所在的页码（如果文档有页码）
+    pub page: Option<usize>,
+    pub text: String,
+}
+
+// =============================================================================
+// 按页提取
+// =============================================================================
+/// 从 PDF 字节中按页提取文本。
+///
+/// 注意：这是同步的 CPU 密集型操作，在异步代码中应通过
+/// `tokio::task::spawn_blocking` 调用。
+pub fn pdf_pages(bytes: &[u8]) -> Result<Vec<Page>, DocumentError> {
+    let pages = pdf_extract::extract_text_from_mem_by_pages(bytes)
+        .map_err(|e| DocumentError::Pdf(e.to_string()))?;
+
+    Ok(pages
+        .iter()
+        .enumerate()
+        .map(|(i, text)| Page {
+            number: Some(i + 1),
+            text: normalize_text(text),
+        })
+        .collect())
+}
+
+/// 把纯文本按换页符（`\x0c`，pdftotext 等工具的输出约定）拆分为页。
+///
+/// 没有换页符的文本作为单个无页码的页面返回。
+pub fn text_pages(text: &str) -> Vec<Page> {
+    let parts: Vec<&str> = text.split('\x0c').collect();
+    if parts.len() == 1 {
+        return vec![Page {
+            number: None,
+            text: normalize_text(text),
+        }];
+    }
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| Page {
+            number: Some(i + 1),
+            text: normalize_text(part),
+        })
+        .collect()
+}
+
+/// 去掉行尾空白，并把连续的空行合并为一个段落分隔。
+fn normalize_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        out.push_str(line.trim_start());
+        blank = false;
+    }
+
+    out
+}
+
+// =============================================================================
+// 分块流水线
+// =============================================================================
+/// 把页面切分为不超过 CHUNK_TOKENS 的块，尽量在段落边界处切分。
+///
+/// 每个块都保留其页码，一个块不会跨越两页。
+pub fn chunk_pages(pages: &[Page]) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+
+    for page in pages {
+        let mut current = String::new();
+
+        for paragraph in page.text.split("\n\n").filter(|p| !p.trim().is_empty()) {
+            let candidate = if current.is_empty() {
+                paragraph.to_string()
+            } else {
+                format!("{}\n\n{}", current, paragraph)
+            };
+
+            if estimate_tokens(&candidate) <= CHUNK_TOKENS {
+                current = candidate;
+                continue;
+            }
+
+            // 当前块已满，先输出它
+            if !current.is_empty() {
+                chunks.push(Chunk {
+                    page: page.number,
+                    text: std::mem::take(&mut current),
+                });
+            }
+
+            // 单个段落本身就超过上限时，继续切分
+            let mut rest = paragraph.to_string();
+            while estimate_tokens(&rest) > CHUNK_TOKENS {
+                let (head, _) = truncate_to_token_budget(&rest, CHUNK_TOKENS);
+                if head.is_empty() {
+                    break;
+                }
+                rest = rest[head.len()..].trim_start().to_string();
+                chunks.push(Chunk {
+                    page: page.number,
+                    text: head,
+                });
+            }
+            current = rest;
+        }
+
+        if !current.is_empty() {
+            chunks.push(Chunk {
+                page: page.number,
+                text: current,
+            });
+        }
+    }
+
+    chunks
+}
+
+/// 按顺序保留块，直到达到令牌预算，返回（块，是否丢弃了内容）。
+///
+/// 如果第一个块就超过预算，会把它截断而不是返回空结果。
+pub fn fit_to_budget(chunks: Vec<Chunk>, budget: usize) -> (Vec<Chunk>, bool) {
+    let total = chunks.len();
+    let mut used = 0;
+    let mut kept = Vec::new();
+
+    for chunk in chunks {
+        let tokens = estimate_tokens(&chunk.text);
+        if used + tokens > budget {
+            if kept.is_empty() {
+                let (text, _) = truncate_to_token_budget(&chunk.text, budget);
+                kept.push(Chunk {
+                    page: chunk.page,
+                    text,
+                });
+            }
+            return (kept, true);
+        }
+        used += tokens;
+        kept.push(chunk);
+    }
+
+    let truncated = kept.len() < total;
+    (kept, truncated)
+}
+
+/// 把块渲染为文本，并在页码变化处插入 `[p. N]` 标记。
+pub fn render_chunks(chunks: &[Chunk]) -> String {
+    let mut out = String::new();
+    let mut last_page = None;
+
+    for chunk in chunks {
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        if chunk.page.is_some() && chunk.page != last_page {
+            out.push_str(&format!("[p. {}]\n", chunk.page.unwrap_or_default()));
+        }
+        last_page = chunk.page;
+        out.push_str(&chunk.text);
+    }
+
+    out
+}
+
+// =============================================================================
+// 令牌预算
+// =============================================================================
+/// 粗略估计文本的令牌数。
+///
+/// 英文大约每 4 个字符一个令牌，而中文等非 ASCII 字符
+/// 通常每个字符就是一个令牌。这个估计不精确，但足以控制上下文长度。
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+        if c.is_ascii() {
+            (a + 1, o)
+        } else {
+            (a, o + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 把文本截断到近似的令牌预算内，返回（文本，是否截断）。
+///
+/// # Rust 概念：字符边界
+///
+/// Rust 字符串是 UTF-8 编码的，按字节切片可能切在多字节字符中间并导致 panic。
+/// 所以我们按 `char_indices()` 逐字符前进，只在字符边界处切分。
+pub fn truncate_to_token_budget(text: &str, budget: usize) -> (String, bool) {
+    let mut ascii: usize = 0;
+    let mut other: usize = 0;
+
+    for (idx, c) in text.char_indices() {
+        if c.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+        if ascii.div_ceil(4) + other > budget {
+            // 尽量在最后一个空白处切分，避免截断单词
+            let cut = text[..idx]
+                .rfind(char::is_whitespace)
+                .filter(|&ws| ws > idx / 2)
+                .unwrap_or(idx);
+            return (text[..cut].trim_end().to_string(), true);
+        }
+    }
+
+    (text.to_string(), false)
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT_PDF: &[u8] = include_bytes!("../tests/fixtures/documents/report.pdf");
+    const NOTES_TXT: &str = include_str!("../tests/fixtures/documents/notes.txt");
+
+    #[test]
+    fn test_pdf_pages_preserve_page_numbers() {
+        let pages = pdf_pages(REPORT_PDF).unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].number, Some(1));
+        assert!(pages[0].text.contains("Rust Memory Safety Report"));
+        assert_eq!(pages[1].number, Some(2));
+        assert!(pages[1].text.contains("borrow checker"));
+        assert_eq!(pages[2].number, Some(3));
+        assert!(pages[2].text.contains("eliminates data races"));
+    }
+
+    #[test]
+    fn test_pdf_pages_rejects_garbage() {
+        assert!(pdf_pages(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn test_text_pages() {
+        let pages = text_pages(NOTES_TXT);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(
+            pages[0],
+            Page {
+                number: Some(1),
+                text: "Chapter one of the plain text notes.\nOwnership rules apply.".to_string(),
+            }
+        );
+        assert_eq!(pages[2].number, Some(3));
+
+        let single = text_pages("  line one  \n\n\n\nline two\n");
+        assert_eq!(
+            single,
+            vec![Page {
+                number: None,
+                text: "line one\n\nline two".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_chunk_pages_respects_limit_and_pages() {
+        let paragraph = "word ".repeat(300); // 约 375 个令牌
+        let pages = vec![
+            Page {
+                number: Some(1),
+                text: format!("{}\n\n{}", paragraph.trim(), paragraph.trim()),
+            },
+            Page {
+                number: Some(2),
+                text: "short".to_string(),
+            },
+        ];
+
+        let chunks = chunk_pages(&pages);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks
+            .iter()
+            .all(|c| estimate_tokens(&c.text) <= CHUNK_TOKENS));
+        assert_eq!(chunks[0].page, Some(1));
+        assert_eq!(chunks[1].page, Some(1));
+        assert_eq!(chunks[2].page, Some(2));
+
+        // 超长段落被继续切分
+        let long = vec![Page {
+            number: None,
+            text: "word ".repeat(1000),
+        }];
+        let chunks = chunk_pages(&long);
+        assert!(chunks.len() >= 3);
+        assert!(chunks
+            .iter()
+            .all(|c| estimate_tokens(&c.text) <= CHUNK_TOKENS));
+    }
+
+    #[test]
+    fn test_fit_to_budget_and_render() {
+        let chunks = vec![
+            Chunk {
+                page: Some(1),
+                text: "alpha beta".to_string(),
+            },
+            Chunk {
+                page: Some(2),
+                text: "gamma delta".to_string(),
+            },
+            Chunk {
+                page: Some(2),
+                text: "epsilon".to_string(),
+            },
+        ];
+
+        let (kept, truncated) = fit_to_budget(chunks.clone(), 100);
+        assert_eq!(kept.len(), 3);
+        assert!(!truncated);
+        assert_eq!(
+            render_chunks(&kept),
+            "[p. 1]\nalpha beta\n\n[p. 2]\ngamma delta\n\nepsilon"
+        );
+
+        let (kept, truncated) = fit_to_budget(chunks, 3);
+        assert_eq!(kept.len(), 1);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_truncate_to_token_budget() {
+        let (text, truncated) = truncate_to_token_budget("short text", 100);
+        assert_eq!(text, "short text");
+        assert!(!truncated);
+
+        // 40 个 ASCII 字符约 10 个令牌，预算为 5 时大约保留一半
+        let long = "word ".repeat(8);
+        let (text, truncated) = truncate_to_token_budget(&long, 5);
+        assert!(truncated);
+        assert!(text.len() <= 20);
+        assert!(text.ends_with("word"));
+
+        // 多字节字符不会被切断
+        let (text, truncated) = truncate_to_token_budget("异步编程很有趣", 3);
+        assert_eq!(text, "异步编");
+        assert!(truncated);
+    }
+}
@@ -0,0 +1,288 @@
+//! # 研究事件模块
+//!
+//! 本地模型完成一次研究可能需要几分钟。为了让用户看到进度，代理在运行时
+//! 发出 `ResearchEvent`：状态更新、研究计划、搜索、读取的页面和 LLM 生成的令牌。
+//! CLI 把它们实时打印到终端；`serve` 子命令把同一个事件流通过 SSE 推送给网页等前端。
+//!
+//! 它演示了：
+//! - 使用 tokio 的 mpsc 通道在异步任务之间传递消息
+//! - 使用带标签的 serde 枚举表示不同种类的事件
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tokio::sync::mpsc;
+
+use crate::search::SearchResult;
+
+/// 工具结果摘要的最大字符数
+const RESULT_SUMMARY_CHARS: usize = 80;
+
+// =============================================================================
+// 事件类型
+// =============================================================================
+/// 研究过程中发生的一件事。
+///
+/// # Rust 概念：带数据的枚举
+///
+/// Rust 枚举的每个变体都可以携带不同的数据，非常适合表示"几种不同的事件"。
+/// `#[serde(tag = "type")]` 把变体名作为 JSON 中的 `type` 字段序列化。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResearchEvent {
+    /// 研究阶段的变化（规划、研究子问题、核验等）
+    Status { message: String },
+
+    /// 深度研究拆分出的子问题（研究过程中还可能加入新的待解决问题）
+    PlanCreated { questions: Vec<String> },
+
+    /// LLM 请求调用工具
+    ToolCall {
+        name: String,
+        arguments: serde_json::Value,
+    },
+
+    /// 工具返回了结果（只保留简短摘要）
+    ToolResult { name: String, summary: String },
+
+    /// web_search 工具开始一次搜索
+    SearchIssued { query: String },
+
+    /// web_search 工具得到的完整搜索结果（供 JSON 输出等前端使用）
+    SearchResults {
+        query: String,
+        results: Vec<SearchResult>,
+    },
+
+    /// fetch_page 工具读取了一个页面
+    PageFetched {
+        url: String,
+        title: Option<String>,
+
+        /// 来源编号（报告中的 `[n]`）
+        source_id: usize,
+
+        /// 交给 LLM 的正文字符数
+        chars: usize,
+    },
+
+    /// LLM 生成的一段文本
+    Token { text: String },
+}
+
+impl ResearchEvent {
+    /// 创建状态事件的便捷函数。
+    pub fn status(message: impl Into<String>) -> Self {
+        Self::Status {
+            message: message.into(),
+        }
+    }
+
+    /// 根据工具的完整输出创建工具结果事件（只保留第一行的摘要）。
+    pub fn tool_result(name: impl Into<String>, output: &str) -> Self {
+        let first_line = output
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or_default();
+        let mut summary: String = first_line.chars().take(RESULT_SUMMARY_CHARS).collect();
+        if first_line.chars().count() > RESULT_SUMMARY_CHARS {
+            summary.push('…');
+        }
+        summary.push_str(&format!(" ({} 字符)", output.chars().count()));
+
+        Self::ToolResult {
+            name: name.into(),
+            summary,
+        }
+    }
+
+    /// 渲染为终端输出。令牌原样输出，其他事件各占一行。
+    pub fn to_terminal(&self) -> String {
+        match self {
+            Self::Status { message } => format!("\n▶ {}\n", message),
+            Self::PlanCreated { questions } => questions
+                .iter()
+                .enumerate()
+                .map(|(i, q)| format!("   {}. {}\n", i + 1, q))
+                .collect(),
+            Self::ToolCall { name, arguments } => {
+                let arg = |key: &str| {
+                    arguments
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                match name.as_str() {
+                    "web_search" => format!("\n🔎 searching: {}\n", arg("query")),
+                    "fetch_page" => format!("\n📄 reading: {}\n", arg("url")),
+                    _ => format!("\n🛠 {}: {}\n", name, arguments),
+                }
+            }
+            Self::ToolResult { summary, .. } => format!("   ↳ {}\n", summary),
+            // 终端上已经由对应的工具调用和工具结果摘要表示
+            Self::SearchIssued { .. } | Self::SearchResults { .. } | Self::PageFetched { .. } => {
+                String::new()
+            }
+            Self::Token { text } => text.clone(),
+        }
+    }
+}
+
+// =============================================================================
+// 事件接收端
+// =============================================================================
+/// 代理发出事件的目的地。
+///
+/// 不需要事件时使用 `EventSink::none()`，发出事件就是空操作。
+///
+/// # Rust 概念：无界通道
+///
+/// `mpsc::unbounded_channel` 的发送端 `send` 是同步的、不会阻塞，
+/// 所以代理可以在任何地方发出事件，而消费者在另一个任务中按自己的节奏读取。
+#[derive(Debug, Clone, Default)]
+pub struct EventSink {
+    sender: Option<mpsc::UnboundedSender<ResearchEvent>>,
+}
+
+impl EventSink {
+    /// 丢弃所有事件的接收端。
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// 创建一个通道，返回（接收端，事件接收器）。
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<ResearchEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                sender: Some(sender),
+            },
+            receiver,
+        )
+    }
+
+    /// 发出一个事件。消费者已经退出时静默忽略。
+    pub fn emit(&self, event: ResearchEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// 接收事件直到通道关闭，返回除令牌以外的所有事件（用于保存会话）。
+///
+/// `print` 为 true 时同时把每个事件实时打印到标准输出。
+/// 令牌不带换行，所以每次打印后都要手动刷新 stdout，否则输出会被行缓冲卡住。
+pub async fn collect(
+    mut receiver: mpsc::UnboundedReceiver<ResearchEvent>,
+    print: bool,
+) -> Vec<ResearchEvent> {
+    let mut stdout = std::io::stdout();
+    let mut log = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        if print {
+            let _ = write!(stdout, "{}", event.to_terminal());
+            let _ = stdout.flush();
+        }
+        // 生成的文本已经保存在报告中，不需要逐个令牌保存
+        if !matches!(event, ResearchEvent::Token { .. }) {
+            log.push(event);
+        }
+    }
+    log
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_result_summary() {
+        let event = ResearchEvent::tool_result("web_search", "\n## 搜索结果: rust\n\n[1] ...");
+        assert_eq!(
+            event,
+            ResearchEvent::ToolResult {
+                name: "web_search".to_string(),
+                summary: "## 搜索结果: rust (23 字符)".to_string(),
+            }
+        );
+
+        let long = "x".repeat(200);
+        let ResearchEvent::ToolResult { summary, .. } = ResearchEvent::tool_result("t", &long)
+        else {
+            unreachable!()
+        };
+        assert!(summary.starts_with(&format!("{}…", "x".repeat(RESULT_SUMMARY_CHARS))));
+    }
+
+    #[test]
+    fn test_to_terminal() {
+        let call = ResearchEvent::ToolCall {
+            name: "web_search".to_string(),
+            arguments: serde_json::json!({ "query": "rust async" }),
+        };
+        assert_eq!(call.to_terminal(), "\n🔎 searching: rust async\n");
+
+        let call = ResearchEvent::ToolCall {
+            name: "fetch_page".to_string(),
+            arguments: serde_json::json!({ "url": "https://tokio.rs" }),
+        };
+        assert_eq!(call.to_terminal(), "\n📄 reading: https://tokio.rs\n");
+
+        let plan = ResearchEvent::PlanCreated {
+            questions: vec!["Tokio 是什么？".to_string(), "smol 是什么？".to_string()],
+        };
+        assert_eq!(
+            plan.to_terminal(),
+            "   1. Tokio 是什么？\n   2. smol 是什么？\n"
+        );
+
+        let token = ResearchEvent::Token {
+            text: "Tok".to_string(),
+        };
+        assert_eq!(token.to_terminal(), "Tok");
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let json = serde_json::to_value(ResearchEvent::status("规划")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "status", "message": "规划" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_sink() {
+        // 没有接收器时发出事件不会出错
+        EventSink::none().emit(ResearchEvent::status("ignored"));
+
+        let (sink, mut receiver) = EventSink::channel();
+        sink.clone().emit(ResearchEvent::status("a"));
+        sink.emit(ResearchEvent::status("b"));
+        drop(sink);
+
+        assert_eq!(receiver.recv().await, Some(ResearchEvent::status("a")));
+        assert_eq!(receiver.recv().await, Some(ResearchEvent::status("b")));
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_collect_skips_tokens() {
+        let (sink, receiver) = EventSink::channel();
+        sink.emit(ResearchEvent::status("a"));
+        sink.emit(ResearchEvent::Token {
+            text: "tok".to_string(),
+        });
+        sink.emit(ResearchEvent::tool_result("web_search", "结果"));
+        drop(sink);
+
+        let log = collect(receiver, false).await;
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0], ResearchEvent::status("a"));
+    }
+}
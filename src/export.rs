@@ -0,0 +1,253 @@
+//! # 报告导出模块
+//!
+//! `--output <路径>` 把研究报告写入文件，格式由扩展名决定：
+//! - `.md` / `.markdown`：带 YAML 前置元数据（查询、模型、日期、来源）的 Markdown
+//! - `.html` / `.htm`：带内联样式、可以直接在浏览器中打开的独立 HTML 页面
+//!
+//! 两种格式都是自包含的：不引用外部样式表或脚本。
+//!
+//! 它演示了：
+//! - 为自定义类型实现 `FromStr`，让 clap 在解析参数时就完成校验
+//! - 用 JSON 字符串作为 YAML 字符串（JSON 是 YAML 的子集）
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::report::{escape_html, ResearchReport};
+
+// =============================================================================
+// 导出目标
+// =============================================================================
+/// 导出文件的格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    /// 根据文件扩展名推断格式。
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("md" | "markdown") => Ok(Self::Markdown),
+            Some("html" | "htm") => Ok(Self::Html),
+            _ => anyhow::bail!(
+                "无法从文件名 {} 推断导出格式（支持 .md、.markdown、.html、.htm）",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// `--output` 的值：文件路径和从扩展名推断的格式。
+///
+/// # Rust 概念：FromStr 和 clap
+///
+/// clap 可以解析任何实现了 `FromStr` 的类型。在这里推断格式，
+/// 扩展名不受支持时在开始研究之前就报告参数错误，而不是在几分钟后。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTarget {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+}
+
+impl FromStr for ExportTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathBuf::from(s);
+        let format = ExportFormat::from_path(&path)?;
+        Ok(Self { path, format })
+    }
+}
+
+impl ExportTarget {
+    /// 渲染报告并写入文件。
+    pub fn write(
+        &self,
+        report: &ResearchReport,
+        config: &Config,
+        date: DateTime<Utc>,
+    ) -> Result<()> {
+        let contents = match self.format {
+            ExportFormat::Markdown => markdown_document(report, config, date),
+            ExportFormat::Html => html_document(report, config, date),
+        };
+        std::fs::write(&self.path, contents)
+            .with_context(|| format!("无法写入报告 {}", self.path.display()))
+    }
+}
+
+// =============================================================================
+// 文档渲染
+// =============================================================================
+/// 带 YAML 前置元数据的 Markdown 文档。
+pub fn markdown_document(report: &ResearchReport, config: &Config, date: DateTime<Utc>) -> String {
+    let mut out = String::from("---\n");
+    let _ = writeln!(out, "query: {}", yaml_string(&report.query));
+    let _ = writeln!(out, "model: {}", yaml_string(&config.model));
+    let _ = writeln!(out, "provider: {}", config.provider);
+    let _ = writeln!(out, "date: {}", date.to_rfc3339());
+    if report.sources.is_empty() {
+        out.push_str("sources: []\n");
+    } else {
+        out.push_str("sources:\n");
+        for source in &report.sources {
+            let _ = writeln!(out, "  - id: {}", source.id);
+            let _ = writeln!(out, "    title: {}", yaml_string(&source.title));
+            let _ = writeln!(out, "    url: {}", yaml_string(&source.url));
+            let _ = writeln!(
+                out,
+                "    accessed: {}",
+                source.accessed_at.format("%Y-%m-%d")
+            );
+        }
+    }
+    out.push_str("---\n\n");
+
+    let _ = writeln!(out, "# {}\n", report.query);
+    out.push_str(&report.to_markdown());
+    out.push('\n');
+    out
+}
+
+/// 带内联样式的独立 HTML 页面。
+pub fn html_document(report: &ResearchReport, config: &Config, date: DateTime<Utc>) -> String {
+    let title = escape_html(&report.query);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="generator" content="ai-research-agent">
+<title>{title}</title>
+<style>
+{style}
+</style>
+</head>
+<body>
+<header>
+<h1>{title}</h1>
+<p class="meta">{model}（{provider}）· {date}</p>
+</header>
+<main>
+{body}</main>
+</body>
+</html>
+"#,
+        title = title,
+        style = STYLE.trim(),
+        model = escape_html(&config.model),
+        provider = config.provider,
+        date = date.format("%Y-%m-%d %H:%M UTC"),
+        body = report.to_html(),
+    )
+}
+
+/// HTML 报告的内联样式
+const STYLE: &str = r#"
+body { max-width: 46rem; margin: 2rem auto; padding: 0 1rem; font: 16px/1.7 system-ui, -apple-system, "PingFang SC", "Microsoft YaHei", sans-serif; color: #222; }
+header { border-bottom: 1px solid #ddd; margin-bottom: 1.5rem; }
+h1 { font-size: 1.6rem; margin-bottom: 0.2rem; }
+h2 { font-size: 1.25rem; margin-top: 2rem; }
+.meta { color: #666; font-size: 0.9rem; }
+a { color: #0b62c4; text-decoration: none; }
+a:hover { text-decoration: underline; }
+code { background: #f3f3f3; padding: 0 0.25rem; border-radius: 3px; }
+.checks { color: #666; font-size: 0.85rem; }
+.supported { color: #1a7f37; }
+.weak { color: #9a6700; }
+.unsupported { color: #cf222e; }
+.verification { border-left: 3px solid #ddd; padding-left: 0.8rem; color: #555; }
+.references li { margin-bottom: 0.4rem; }
+.references li:target { background: #fff8c5; }
+.references .url, .references .accessed { display: block; color: #666; font-size: 0.85rem; word-break: break-all; }
+"#;
+
+/// YAML 字符串字面量。
+///
+/// JSON 字符串也是合法的 YAML 双引号字符串，所以直接用 serde_json 转义，
+/// 标题中的冒号、引号和换行都不会破坏前置元数据。
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SourceRegistry;
+
+    fn report() -> ResearchReport {
+        let registry = SourceRegistry::new();
+        registry.register("https://tokio.rs", "Tokio: an \"async\" runtime");
+        registry.register("https://smol.rs", "smol");
+        let response = "\
+## 概述
+Tokio 是最流行的 **异步运行时** [1]。
+
+## 对比
+- smol 很轻量 [2]
+- 两者都支持 `async fn` [1, 2]
+";
+        ResearchReport::from_response("Rust <异步> 运行时", response, &registry)
+    }
+
+    #[test]
+    fn test_export_target_infers_format() {
+        let target: ExportTarget = "out/report.md".parse().unwrap();
+        assert_eq!(target.format, ExportFormat::Markdown);
+        assert_eq!(target.path, PathBuf::from("out/report.md"));
+        assert_eq!(
+            "Report.HTML".parse::<ExportTarget>().unwrap().format,
+            ExportFormat::Html
+        );
+        assert!("report.pdf".parse::<ExportTarget>().is_err());
+        assert!("report".parse::<ExportTarget>().is_err());
+    }
+
+    #[test]
+    fn test_markdown_document_front_matter() {
+        let date = "2026-03-01T08:00:00Z".parse().unwrap();
+        let doc = markdown_document(&report(), &Config::default(), date);
+
+        assert!(doc.starts_with("---\nquery: \"Rust <异步> 运行时\"\nmodel: \"llama3.2\"\n"));
+        assert!(doc.contains("date: 2026-03-01T08:00:00+00:00\n"));
+        assert!(doc.contains(
+            "  - id: 1\n    title: \"Tokio: an \\\"async\\\" runtime\"\n    url: \"https://tokio.rs\"\n"
+        ));
+        assert!(doc.contains("---\n\n# Rust <异步> 运行时\n\n## 概述\n"));
+        assert!(doc.contains("## 参考文献"));
+    }
+
+    #[test]
+    fn test_html_document() {
+        let date = "2026-03-01T08:00:00Z".parse().unwrap();
+        let doc = html_document(&report(), &Config::default(), date);
+
+        assert!(doc.starts_with("<!DOCTYPE html>"));
+        assert!(doc.contains("<title>Rust &lt;异步&gt; 运行时</title>"));
+        assert!(doc.contains(
+            "<p>Tokio 是最流行的 <strong>异步运行时</strong> [<a href=\"#ref-1\">1</a>]。</p>"
+        ));
+        assert!(doc.contains(
+            "<ul>\n<li>smol 很轻量 [<a href=\"#ref-2\">2</a>]</li>\n\
+             <li>两者都支持 <code>async fn</code> [<a href=\"#ref-1\">1</a>, <a href=\"#ref-2\">2</a>]</li>\n</ul>"
+        ));
+        assert!(doc.contains(
+            "<li id=\"ref-1\" value=\"1\"><a href=\"https://tokio.rs\">Tokio: an &quot;async&quot; runtime</a>"
+        ));
+        assert!(!doc.contains("<link") && !doc.contains("<script"));
+    }
+}
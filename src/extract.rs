@@ -0,0 +1,945 @@
+//! # 正文提取模块
+//!
+//! 本模块把原始 HTML 转换为干净的文章文本，并提取标题、作者、
+//! 发布日期、小标题和外部链接。
+//!
+//! 算法参考了 Mozilla Readability 的思路：
+//! 1. 移除明显不是正文的元素（导航、侧边栏、评论、广告……）
+//! 2. 为每个段落打分，并把分数累加到它的父节点和祖父节点
+//! 3. 选出得分最高的节点，再合并与它相关的兄弟节点
+//! 4. 把选中的节点渲染为带段落和小标题的纯文本
+//!
+//! 它演示了：
+//! - 使用 `HashMap<NodeId, f64>` 在不可变 DOM 树上保存额外数据
+//! - 递归遍历树结构
+//! - 使用 `Option` 组合子链式回退
+
+use ego_tree::NodeId;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// =============================================================================
+// 提取结果
+// =============================================================================
+/// 从页面中提取的文章。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Article {
+    /// 文章标题
+    pub title: Option<String>,
+
+    /// 作者
+    pub byline: Option<String>,
+
+    /// 发布日期（保留页面中的原始格式）
+    pub published: Option<String>,
+
+    /// 网站名称
+    pub site_name: Option<String>,
+
+    /// 正文中的小标题，按出现顺序
+    pub headings: Vec<Heading>,
+
+    /// 正文中的 http(s) 链接（已解析为绝对 URL 并去重）。
+    /// 同一网站内的链接也保留（文档的其他章节常常有用），用 `is_external` 区分出站链接
+    pub links: Vec<Link>,
+
+    /// 干净的正文文本，段落之间用空行分隔，小标题以 `#` 开头
+    pub text: String,
+}
+
+/// 正文中的一个小标题。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heading {
+    /// 级别 1-6（对应 h1-h6）
+    pub level: u8,
+    pub text: String,
+}
+
+/// 正文中的一个链接。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub text: String,
+    pub url: String,
+
+    /// 是否指向其他网站（主机名与页面不同；页面没有 URL 时都视为外部链接）
+    #[serde(default)]
+    pub is_external: bool,
+}
+
+// =============================================================================
+// 启发式规则
+// =============================================================================
+/// 永远不属于正文的标签
+const BOILERPLATE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "footer", "aside", "form", "iframe", "svg", "button",
+    "template", "select", "input", "textarea", "object", "embed",
+];
+
+/// class/id 中出现这些词的元素很可能不是正文
+const UNLIKELY_WORDS: &[&str] = &[
+    "banner",
+    "breadcrumb",
+    "breadcrumbs",
+    "combx",
+    "comment",
+    "community",
+    "copyright",
+    "cookie",
+    "disqus",
+    "footer",
+    "gdpr",
+    "header",
+    "masthead",
+    "menu",
+    "nav",
+    "newsletter",
+    "pager",
+    "pagination",
+    "popup",
+    "related",
+    "remark",
+    "replies",
+    "rss",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "social",
+    "sponsor",
+    "supplemental",
+    "toc",
+    "ad",
+    "ads",
+    "advert",
+    "advertisement",
+];
+
+/// 即使匹配了 UNLIKELY_WORDS，出现这些词的元素也要保留
+const MAYBE_WORDS: &[&str] = &[
+    "and", "article", "body", "column", "content", "main", "shadow",
+];
+
+/// 提高节点得分的 class/id 词
+const POSITIVE_WORDS: &[&str] = &[
+    "article", "blog", "body", "content", "entry", "hentry", "main", "page", "post", "story",
+    "text",
+];
+
+/// 降低节点得分的 class/id 词
+const NEGATIVE_WORDS: &[&str] = &[
+    "author",
+    "byline",
+    "comment",
+    "contact",
+    "foot",
+    "footer",
+    "footnote",
+    "hidden",
+    "masthead",
+    "media",
+    "meta",
+    "outbrain",
+    "promo",
+    "related",
+    "scroll",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "sponsor",
+    "shopping",
+    "tags",
+    "tool",
+    "widget",
+];
+
+/// 不属于正文的 ARIA 角色
+const UNLIKELY_ROLES: &[&str] = &[
+    "navigation",
+    "banner",
+    "complementary",
+    "contentinfo",
+    "dialog",
+    "menu",
+];
+
+/// 作为段落参与打分的标签
+const SCORE_TAGS: &[&str] = &["p", "pre", "td", "blockquote"];
+
+/// 块级元素（渲染时另起段落，且不会被当作"叶子 div"）
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "header",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tbody",
+    "thead",
+    "tr",
+    "ul",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+
+/// 段落至少需要这么多字符才参与打分
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+// =============================================================================
+// 公共入口
+// =============================================================================
+/// 从 HTML 中提取文章。
+///
+/// `base_url` 用于把相对链接解析为绝对 URL；为 `None` 时只保留绝对链接。
+///
+/// # 示例
+/// ```ignore
+/// let article = extract(&html, Some("https://example.com/post"));
+/// println!("{}", article.text);
+/// ```
+pub fn extract(html: &str, base_url: Option<&str>) -> Article {
+    let document = Html::parse_document(html);
+    let base = base_url.and_then(|u| Url::parse(u).ok());
+
+    let removed = find_removed(&document);
+    let scores = score_candidates(&document, &removed);
+    let content = select_content(&document, &scores, &removed);
+
+    let mut renderer = Renderer::new(&removed, base.as_ref());
+    for node in content {
+        renderer.render(node);
+    }
+    renderer.flush();
+
+    Article {
+        title: find_title(&document),
+        byline: find_byline(&document),
+        published: find_published(&document),
+        site_name: meta_content(
+            &document,
+            &[
+                "meta[property='og:site_name']",
+                "meta[name='application-name']",
+            ],
+        ),
+        headings: renderer.headings,
+        links: renderer.links,
+        text: renderer.blocks.join("\n\n"),
+    }
+}
+
+// =============================================================================
+// 步骤 1：移除不可能是正文的元素
+// =============================================================================
+/// 返回所有应被跳过（连同子节点）的元素 ID。
+fn find_removed(document: &Html) -> HashSet<NodeId> {
+    let mut removed = HashSet::new();
+
+    for el in document.root_element().descendent_elements() {
+        let tag = el.value().name();
+
+        let boilerplate = BOILERPLATE_TAGS.contains(&tag)
+            // <header> 在 <article> 内部时通常包含文章标题，保留它
+            || (tag == "header" && !has_ancestor_tag(el, "article"));
+
+        let hidden = el.attr("hidden").is_some()
+            || el.attr("aria-hidden") == Some("true")
+            || el
+                .attr("style")
+                .is_some_and(|s| s.replace(' ', "").contains("display:none"))
+            || el.attr("role").is_some_and(|r| UNLIKELY_ROLES.contains(&r));
+
+        let unlikely = !matches!(tag, "html" | "body" | "article" | "main")
+            && matches_words(el, UNLIKELY_WORDS)
+            && !matches_words_exact(el, MAYBE_WORDS);
+
+        if boilerplate || hidden || unlikely {
+            removed.insert(el.id());
+        }
+    }
+
+    removed
+}
+
+/// 元素自身或任一祖先是否被移除
+fn is_removed(el: ElementRef, removed: &HashSet<NodeId>) -> bool {
+    removed.contains(&el.id()) || el.ancestors().any(|a| removed.contains(&a.id()))
+}
+
+fn has_ancestor_tag(el: ElementRef, tag: &str) -> bool {
+    el.ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|a| a.value().name() == tag)
+}
+
+/// 把 class 和 id 拆分为小写的单词
+fn class_id_words(el: ElementRef) -> Vec<String> {
+    let mut raw = String::new();
+    if let Some(class) = el.attr("class") {
+        raw.push_str(class);
+    }
+    raw.push(' ');
+    if let Some(id) = el.attr("id") {
+        raw.push_str(id);
+    }
+    raw.to_ascii_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// class/id 中是否有单词等于（或以其开头，仅限 5 个字符以上的词）给定词之一。
+///
+/// 例如 "comments" 匹配 "comment"，但 "address" 不会匹配 "ad"。
+fn matches_words(el: ElementRef, words: &[&str]) -> bool {
+    class_id_words(el).iter().any(|w| {
+        words
+            .iter()
+            .any(|k| w == k || (k.len() >= 5 && w.starts_with(k)))
+    })
+}
+
+/// class/id 中是否有单词完全等于给定词之一
+fn matches_words_exact(el: ElementRef, words: &[&str]) -> bool {
+    class_id_words(el)
+        .iter()
+        .any(|w| words.contains(&w.as_str()))
+}
+
+// =============================================================================
+// 步骤 2：为候选节点打分
+// =============================================================================
+/// 为包含段落的节点打分。
+///
+/// # Rust 概念：在不可变树上附加数据
+///
+/// scraper 的 DOM 是不可变的，所以我们不能像 JavaScript 那样在节点上
+/// 写入 `readability.contentScore`。相反，我们用节点 ID 作为键，
+/// 把分数保存在单独的 `HashMap` 中。
+fn score_candidates(document: &Html, removed: &HashSet<NodeId>) -> HashMap<NodeId, f64> {
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+    for el in document.root_element().descendent_elements() {
+        let tag = el.value().name();
+        let scorable = SCORE_TAGS.contains(&tag) || (tag == "div" && is_leaf_div(el));
+        if !scorable || is_removed(el, removed) {
+            continue;
+        }
+
+        let text = collapse_whitespace(&el.text().collect::<String>());
+        let len = text.chars().count();
+        if len < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+
+        // 基础分 1，每个逗号加 1，每 100 个字符加 1（最多 3）
+        let commas = text
+            .chars()
+            .filter(|c| matches!(c, ',' | '，' | '、'))
+            .count();
+        let score = 1.0 + commas as f64 + (len / 100).min(3) as f64;
+
+        // 父节点得全部分数，祖父节点得一半，曾祖父节点得六分之一
+        let ancestors = el.ancestors().filter_map(ElementRef::wrap).take(3);
+        for (level, ancestor) in ancestors.enumerate() {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                n => n as f64 * 3.0,
+            };
+            *scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor)) += score / divider;
+        }
+    }
+
+    // 链接密度高的节点（例如链接列表）更可能是导航
+    for (id, score) in scores.iter_mut() {
+        if let Some(el) = document.tree.get(*id).and_then(ElementRef::wrap) {
+            *score *= 1.0 - link_density(el);
+        }
+    }
+
+    scores
+}
+
+/// 没有块级子元素的 div 被当作段落处理
+fn is_leaf_div(el: ElementRef) -> bool {
+    !el.child_elements()
+        .any(|c| BLOCK_TAGS.contains(&c.value().name()))
+}
+
+/// 节点第一次成为候选时的初始分数
+fn initial_score(el: ElementRef) -> f64 {
+    let tag_score = match el.value().name() {
+        "div" | "article" | "main" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(el)
+}
+
+/// 根据 class/id 调整分数
+fn class_weight(el: ElementRef) -> f64 {
+    let mut weight = 0.0;
+    if matches_words(el, POSITIVE_WORDS) {
+        weight += 25.0;
+    }
+    if matches_words(el, NEGATIVE_WORDS) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// 链接文本占全部文本的比例（0.0 - 1.0）
+fn link_density(el: ElementRef) -> f64 {
+    let total = el.text().map(|t| t.chars().count()).sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let links = Selector::parse("a").expect("valid selector");
+    let link_chars: usize = el
+        .select(&links)
+        .flat_map(|a| a.text())
+        .map(|t| t.chars().count())
+        .sum();
+
+    (link_chars as f64 / total as f64).min(1.0)
+}
+
+// =============================================================================
+// 步骤 3：选择正文节点
+// =============================================================================
+/// 选出得分最高的节点，并合并相关的兄弟节点。
+///
+/// 没有任何候选时回退到 `<body>`。
+fn select_content<'a>(
+    document: &'a Html,
+    scores: &HashMap<NodeId, f64>,
+    removed: &HashSet<NodeId>,
+) -> Vec<ElementRef<'a>> {
+    let top = scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .and_then(|(id, score)| {
+            document
+                .tree
+                .get(*id)
+                .and_then(ElementRef::wrap)
+                .map(|el| (el, *score))
+        });
+
+    let Some((top, top_score)) = top else {
+        let body = Selector::parse("body").expect("valid selector");
+        return document.select(&body).take(1).collect();
+    };
+
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return vec![top];
+    };
+
+    let threshold = (top_score * 0.2).max(10.0);
+    let top_class = top.attr("class").unwrap_or_default();
+
+    parent
+        .child_elements()
+        .filter(|sibling| {
+            if sibling.id() == top.id() {
+                return true;
+            }
+            if is_removed(*sibling, removed) {
+                return false;
+            }
+
+            // 与最佳节点 class 相同的兄弟节点获得奖励
+            let bonus = if !top_class.is_empty() && sibling.attr("class") == Some(top_class) {
+                top_score * 0.2
+            } else {
+                0.0
+            };
+            if scores
+                .get(&sibling.id())
+                .is_some_and(|s| s + bonus >= threshold)
+            {
+                return true;
+            }
+
+            // 不在候选中但看起来像正文段落的兄弟 <p>
+            if sibling.value().name() == "p" {
+                let text = collapse_whitespace(&sibling.text().collect::<String>());
+                let len = text.chars().count();
+                let density = link_density(*sibling);
+                return (len > 80 && density < 0.25)
+                    || (len > 0
+                        && density == 0.0
+                        && (text.contains(". ") || text.ends_with('.') || text.ends_with('。')));
+            }
+
+            false
+        })
+        .collect()
+}
+
+// =============================================================================
+// 步骤 4：渲染为文本
+// =============================================================================
+/// 把选中的节点渲染为段落文本，同时收集小标题和链接。
+struct Renderer<'r> {
+    removed: &'r HashSet<NodeId>,
+    base: Option<&'r Url>,
+    blocks: Vec<String>,
+    buf: String,
+    headings: Vec<Heading>,
+    links: Vec<Link>,
+    seen_links: HashSet<String>,
+}
+
+impl<'r> Renderer<'r> {
+    fn new(removed: &'r HashSet<NodeId>, base: Option<&'r Url>) -> Self {
+        Self {
+            removed,
+            base,
+            blocks: Vec::new(),
+            buf: String::new(),
+            headings: Vec::new(),
+            links: Vec::new(),
+            seen_links: HashSet::new(),
+        }
+    }
+
+    /// 把当前缓冲区作为一个段落输出
+    fn flush(&mut self) {
+        let text = collapse_whitespace(&self.buf);
+        // 只有列表符号的空列表项不输出
+        if !text.is_empty() && text != "-" {
+            self.blocks.push(text);
+        }
+        self.buf.clear();
+    }
+
+    fn render(&mut self, el: ElementRef) {
+        if self.removed.contains(&el.id()) {
+            return;
+        }
+
+        let tag = el.value().name();
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                let text = collapse_whitespace(&el.text().collect::<String>());
+                if !text.is_empty() {
+                    let level = tag[1..].parse().unwrap_or(1);
+                    self.blocks
+                        .push(format!("{} {}", "#".repeat(level as usize), text));
+                    self.headings.push(Heading { level, text });
+                }
+            }
+            "pre" => {
+                // 代码块保留原始换行
+                self.flush();
+                let text = el.text().collect::<String>();
+                let text = text.trim_matches('\n').trim_end();
+                if !text.is_empty() {
+                    self.blocks.push(text.to_string());
+                }
+            }
+            "br" => self.flush(),
+            "li" => {
+                self.flush();
+                self.buf.push_str("- ");
+                self.render_children(el);
+                self.flush();
+            }
+            "td" | "th" => {
+                self.render_children(el);
+                self.buf.push(' ');
+            }
+            "a" => {
+                self.record_link(el);
+                self.render_children(el);
+            }
+            _ if BLOCK_TAGS.contains(&tag) => {
+                self.flush();
+                self.render_children(el);
+                self.flush();
+            }
+            _ => self.render_children(el),
+        }
+    }
+
+    fn render_children(&mut self, el: ElementRef) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(text) => self.buf.push_str(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.render(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn record_link(&mut self, a: ElementRef) {
+        let Some(url) = a.attr("href").and_then(|h| resolve_link(h, self.base)) else {
+            return;
+        };
+        let is_external = self
+            .base
+            .is_none_or(|base| base.host_str() != url.host_str());
+        let url = url.to_string();
+        if !self.seen_links.insert(url.clone()) {
+            return;
+        }
+
+        let text = collapse_whitespace(&a.text().collect::<String>());
+        self.links.push(Link {
+            text: if text.is_empty() { url.clone() } else { text },
+            url,
+            is_external,
+        });
+    }
+}
+
+/// 把链接解析为绝对 http(s) URL；锚点、javascript: 和 mailto: 链接返回 None
+fn resolve_link(href: &str, base: Option<&Url>) -> Option<Url> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') {
+        return None;
+    }
+
+    let url = match base {
+        Some(base) => base.join(href).ok()?,
+        None => Url::parse(href).ok()?,
+    };
+
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+// =============================================================================
+// 元数据
+// =============================================================================
+/// 按顺序尝试选择器，返回第一个非空的 `content` 属性
+fn meta_content(document: &Html, selectors: &[&str]) -> Option<String> {
+    selectors
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+        .find_map(|s| {
+            document
+                .select(&s)
+                .filter_map(|el| el.attr("content"))
+                .map(collapse_whitespace)
+                .find(|c| !c.is_empty())
+        })
+}
+
+/// 按顺序尝试选择器，返回第一个非空的元素文本
+fn first_text(document: &Html, selectors: &[&str]) -> Option<String> {
+    selectors
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+        .find_map(|s| {
+            document
+                .select(&s)
+                .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+                .find(|t| !t.is_empty())
+        })
+}
+
+fn find_title(document: &Html) -> Option<String> {
+    meta_content(
+        document,
+        &["meta[property='og:title']", "meta[name='twitter:title']"],
+    )
+    .or_else(|| first_text(document, &["title", "h1"]))
+}
+
+fn find_byline(document: &Html) -> Option<String> {
+    // meta author 有时是作者主页的 URL，这种情况跳过
+    let from_meta = meta_content(
+        document,
+        &["meta[name='author']", "meta[property='article:author']"],
+    )
+    .filter(|a| !a.starts_with("http"));
+
+    from_meta
+        .or_else(|| {
+            first_text(
+                document,
+                &[
+                    "[rel='author']",
+                    "[itemprop='author']",
+                    ".byline",
+                    ".author",
+                ],
+            )
+        })
+        .map(|b| clean_byline(&b))
+        .filter(|b| !b.is_empty() && b.chars().count() <= 100)
+}
+
+/// 去掉常见的 "By " / "作者：" 前缀
+fn clean_byline(byline: &str) -> String {
+    let trimmed = byline.trim();
+    for prefix in ["By ", "by ", "BY ", "作者：", "作者:", "作者 "] {
+        if let Some(rest) = trimmed.strip_prefix(prefix) {
+            return rest.trim().to_string();
+        }
+    }
+    trimmed.to_string()
+}
+
+fn find_published(document: &Html) -> Option<String> {
+    meta_content(
+        document,
+        &[
+            "meta[property='article:published_time']",
+            "meta[name='date']",
+            "meta[name='pubdate']",
+            "meta[name='publish-date']",
+            "meta[itemprop='datePublished']",
+        ],
+    )
+    .or_else(|| {
+        ["[itemprop='datePublished'][datetime]", "time[datetime]"]
+            .iter()
+            .filter_map(|s| Selector::parse(s).ok())
+            .find_map(|s| {
+                document
+                    .select(&s)
+                    .filter_map(|el| el.attr("datetime"))
+                    .map(str::trim)
+                    .find(|d| !d.is_empty())
+                    .map(str::to_string)
+            })
+    })
+}
+
+/// 把连续的空白折叠为单个空格
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+/// 使用 `tests/fixtures/pages/` 中保存的页面进行测试。
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOG_POST: &str = include_str!("../tests/fixtures/pages/blog_post.html");
+    const NEWS_ARTICLE: &str = include_str!("../tests/fixtures/pages/news_article.html");
+    const DOCS_PAGE: &str = include_str!("../tests/fixtures/pages/docs_page.html");
+    const CHINESE_ARTICLE: &str = include_str!("../tests/fixtures/pages/chinese_article.html");
+    const MINIMAL: &str = include_str!("../tests/fixtures/pages/minimal.html");
+
+    #[test]
+    fn test_blog_post() {
+        let article = extract(BLOG_POST, Some("https://ferris.example/posts/pin"));
+
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Understanding Pin in Rust | Ferris Writes")
+        );
+        assert_eq!(article.byline.as_deref(), Some("Jane Ferris"));
+        assert_eq!(article.published.as_deref(), Some("2024-05-14T09:30:00Z"));
+        assert_eq!(article.site_name.as_deref(), Some("Ferris Writes"));
+
+        assert!(article
+            .text
+            .starts_with("Pinning is one of the most confusing parts of async Rust"));
+        assert!(article.text.contains("## What Pin guarantees"));
+        assert!(article
+            .text
+            .contains("let fut = pin!(async { 42 });\nlet value = fut.await;"));
+
+        // 侧边栏、评论、分享按钮和页脚都被移除
+        for boilerplate in [
+            "Subscribe to the newsletter",
+            "Great explanation",
+            "Share on",
+            "All rights reserved",
+            "Archive",
+        ] {
+            assert!(
+                !article.text.contains(boilerplate),
+                "unexpected: {}",
+                boilerplate
+            );
+        }
+
+        assert_eq!(
+            article.headings,
+            vec![
+                Heading {
+                    level: 2,
+                    text: "What Pin guarantees".to_string()
+                },
+                Heading {
+                    level: 2,
+                    text: "Pinning in practice".to_string()
+                },
+            ]
+        );
+
+        let urls: Vec<_> = article.links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://doc.rust-lang.org/std/pin/index.html",
+                "https://tokio.rs/",
+                "https://ferris.example/posts/pin-project",
+            ]
+        );
+        assert_eq!(article.links[1].text, "Tokio");
+        let outbound: Vec<_> = article
+            .links
+            .iter()
+            .filter(|l| l.is_external)
+            .map(|l| l.url.as_str())
+            .collect();
+        assert_eq!(
+            outbound,
+            [
+                "https://doc.rust-lang.org/std/pin/index.html",
+                "https://tokio.rs/"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_news_article() {
+        let article = extract(
+            NEWS_ARTICLE,
+            Some("https://techdaily.example/ai/laptop-llm"),
+        );
+
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Open-source language model now runs on a laptop")
+        );
+        // meta author 是 URL，回退到 rel=author
+        assert_eq!(article.byline.as_deref(), Some("Sam Lee"));
+        assert_eq!(article.published.as_deref(), Some("2024-11-02"));
+
+        assert!(article.text.contains("seven billion parameters"));
+        assert!(article.text.contains("## Why it matters"));
+        for boilerplate in ["cookies", "ADVERTISEMENT", "GPU shortage", "Privacy policy"] {
+            assert!(
+                !article.text.contains(boilerplate),
+                "unexpected: {}",
+                boilerplate
+            );
+        }
+
+        assert_eq!(
+            article.links,
+            vec![Link {
+                text: "published benchmark report".to_string(),
+                url: "https://benchmarks.example.org/report".to_string(),
+                is_external: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_docs_page() {
+        let article = extract(DOCS_PAGE, Some("https://docs.example.com/docs/channels"));
+
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Channels - Async Toolkit Docs")
+        );
+        assert_eq!(article.site_name.as_deref(), Some("Async Toolkit Docs"));
+        assert!(article.byline.is_none());
+
+        let headings: Vec<_> = article
+            .headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                (1, "Channels"),
+                (2, "Multi-producer, single-consumer"),
+                (3, "Example"),
+                (2, "Broadcast"),
+                (2, "One-shot"),
+            ]
+        );
+
+        // 目录侧边栏不算正文
+        assert!(!article.text.contains("Synchronisation"));
+        assert!(!article.text.contains("Previous: Tasks"));
+        assert_eq!(article.links.len(), 1);
+        assert_eq!(article.links[0].url, "https://docs.example.com/select");
+        // 同一网站内的链接不是出站链接
+        assert!(!article.links[0].is_external);
+        assert!(!article.links.iter().any(|l| l.is_external));
+    }
+
+    #[test]
+    fn test_chinese_article() {
+        let article = extract(CHINESE_ARTICLE, None);
+
+        assert_eq!(article.title.as_deref(), Some("Rust 所有权入门 - 码农周刊"));
+        assert_eq!(article.byline.as_deref(), Some("李明"));
+        assert_eq!(article.published.as_deref(), Some("2023-08-20"));
+        assert_eq!(article.site_name.as_deref(), Some("码农周刊"));
+
+        assert!(article.text.starts_with("所有权是 Rust 最独特的特性"));
+        assert!(article.text.contains("## 借用与引用"));
+        assert!(!article.text.contains("网友评论"));
+        assert!(!article.text.contains("版权所有"));
+    }
+
+    #[test]
+    fn test_minimal_page() {
+        let article = extract(MINIMAL, None);
+
+        assert_eq!(article.title.as_deref(), Some("Plain notes"));
+        assert!(article.byline.is_none());
+        assert!(article.published.is_none());
+
+        let paragraphs: Vec<_> = article.text.split("\n\n").collect();
+        assert_eq!(paragraphs.len(), 2);
+        assert!(paragraphs[0].starts_with("This page has no semantic markup"));
+        assert!(paragraphs[1].starts_with("Extraction should still return"));
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let base = Url::parse("https://example.com/a/b").unwrap();
+        assert_eq!(
+            resolve_link("../c", Some(&base)).map(String::from),
+            Some("https://example.com/c".to_string())
+        );
+        assert_eq!(resolve_link("#top", Some(&base)), None);
+        assert_eq!(resolve_link("mailto:me@example.com", Some(&base)), None);
+        assert_eq!(resolve_link("/relative", None), None);
+    }
+}
@@ -0,0 +1,476 @@
+//! # 页面获取模块
+//!
+//! 本模块下载网页（或读取本地文件）并把它们转换为 LLM 可以阅读的纯文本。
+//! HTML 正文提取由 `extract` 模块完成，PDF 和纯文本由 `document` 模块按页拆分，
+//! 这里负责网络、本地文件和各种限制。
+//! 它演示了：
+//! - 使用 reqwest 按块读取响应体（限制下载大小）
+//! - 使用 `spawn_blocking` 在异步代码中运行 CPU 密集型任务
+
+use reqwest::Url;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use tracing::{debug, info};
+
+use crate::config::Config;
+use crate::document::{self, Chunk, DocumentError, Page};
+use crate::extract::{self, Article};
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 获取页面时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("无效的 URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("不允许读取本地文件（设置 ALLOW_LOCAL_FILES=true 以启用）: {0}")]
+    LocalFilesDisabled(String),
+
+    #[error("服务器返回 HTTP {0}")]
+    HttpStatus(u16),
+
+    #[error("不支持的内容类型: {0}")]
+    UnsupportedContentType(String),
+
+    #[error("文档超过 {0} 字节的大小限制，无法完整解析")]
+    TooLarge(usize),
+
+    #[error("网络错误: {0}")]
+    NetworkError(#[from] reqwest::Error),
+
+    #[error("读取文件失败: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Document(#[from] DocumentError),
+}
+
+// =============================================================================
+// 获取结果
+// =============================================================================
+/// 一个已下载并提取正文的页面或文档。
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchedPage {
+    /// 最终 URL（跟随重定向之后；本地文件为 `file://` URL）
+    pub url: String,
+
+    /// 内容的 MIME 类型，例如 "text/html"、"application/pdf"
+    pub content_type: String,
+
+    /// 提取出的文章元数据和完整正文
+    pub article: Article,
+
+    /// 令牌预算内的正文块（PDF 和分页文本带页码）
+    pub chunks: Vec<Chunk>,
+
+    /// 正文是否因下载大小或令牌预算而被截断
+    pub truncated: bool,
+}
+
+// =============================================================================
+// 页面获取器
+// =============================================================================
+/// 根据配置的限制下载网页或读取本地文件。
+///
+/// # Rust 概念：Clone 与廉价复制
+///
+/// 所有字段都是小值或 `Vec<String>`，所以克隆 PageFetcher 很便宜，
+/// 可以放心地把它放进每个代理的工具里。
+#[derive(Debug, Clone)]
+pub struct PageFetcher {
+    /// 单个请求的超时时间
+    timeout: Duration,
+
+    /// 最多下载的字节数
+    max_bytes: usize,
+
+    /// 允许的 MIME 类型
+    allowed_content_types: Vec<String>,
+
+    /// 返回文本的近似令牌上限
+    token_budget: usize,
+
+    /// 是否允许读取本地文件路径
+    allow_local_files: bool,
+}
+
+impl PageFetcher {
+    /// 从配置创建页面获取器。
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.fetch_timeout_secs),
+            max_bytes: config.fetch_max_bytes,
+            allowed_content_types: config.fetch_content_types.clone(),
+            token_budget: config.page_token_budget,
+            allow_local_files: config.allow_local_files,
+        }
+    }
+
+    /// 获取 URL 或本地路径并返回可读的正文。
+    ///
+    /// `location` 可以是 http(s) URL、`file://` URL 或文件路径
+    /// （后两者需要启用 `allow_local_files`）。
+    pub async fn fetch(&self, location: &str) -> Result<FetchedPage, FetchError> {
+        let location = location.trim();
+        if location.starts_with("http://") || location.starts_with("https://") {
+            self.fetch_http(location).await
+        } else if location.contains("://") && !location.starts_with("file://") {
+            Err(FetchError::InvalidUrl(location.to_string()))
+        } else if self.allow_local_files {
+            self.fetch_local(location.strip_prefix("file://").unwrap_or(location))
+                .await
+        } else {
+            Err(FetchError::LocalFilesDisabled(location.to_string()))
+        }
+    }
+
+    /// 通过 HTTP 下载页面
+    async fn fetch_http(&self, url: &str) -> Result<FetchedPage, FetchError> {
+        info!(url = %url, "Fetching page");
+
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+            .build()?;
+
+        let mut response = client.get(url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(FetchError::HttpStatus(response.status().as_u16()));
+        }
+
+        let final_url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(mime_type)
+            .unwrap_or_else(|| "text/html".to_string());
+
+        self.check_content_type(&content_type)?;
+
+        // 按块读取，超过 max_bytes 就停止，避免下载巨大的文件
+        let mut body = Vec::new();
+        let mut over_limit = false;
+        while let Some(chunk) = response.chunk().await? {
+            let remaining = self.max_bytes - body.len();
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                over_limit = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        debug!(url = %final_url, bytes = body.len(), over_limit, "Downloaded page");
+
+        self.build_page(final_url, content_type, body, over_limit)
+            .await
+    }
+
+    /// 读取本地文件，内容类型由扩展名决定
+    async fn fetch_local(&self, path: &str) -> Result<FetchedPage, FetchError> {
+        info!(path = %path, "Reading local document");
+
+        let path = tokio::fs::canonicalize(path).await?;
+        let content_type = mime_from_extension(&path)
+            .ok_or_else(|| FetchError::UnsupportedContentType(path.display().to_string()))?
+            .to_string();
+        self.check_content_type(&content_type)?;
+
+        // 与下载一样最多读取 max_bytes（多读一个字节用于发现读取期间变大的文件），
+        // 不会把巨大的文件整个读入内存
+        let file = tokio::fs::File::open(&path).await?;
+        let size = file.metadata().await?.len();
+        let mut body = Vec::new();
+        file.take(self.max_bytes as u64 + 1)
+            .read_to_end(&mut body)
+            .await?;
+        let over_limit = size > self.max_bytes as u64 || body.len() > self.max_bytes;
+        body.truncate(self.max_bytes);
+
+        let url = Url::from_file_path(&path)
+            .map(|u| u.to_string())
+            .unwrap_or_else(|_| path.display().to_string());
+
+        self.build_page(url, content_type, body, over_limit).await
+    }
+
+    fn check_content_type(&self, content_type: &str) -> Result<(), FetchError> {
+        if self
+            .allowed_content_types
+            .iter()
+            .any(|allowed| allowed == content_type)
+        {
+            Ok(())
+        } else {
+            Err(FetchError::UnsupportedContentType(content_type.to_string()))
+        }
+    }
+
+    /// 把原始字节转换为页面：提取正文、按页拆分、分块并应用令牌预算。
+    async fn build_page(
+        &self,
+        url: String,
+        content_type: String,
+        body: Vec<u8>,
+        over_limit: bool,
+    ) -> Result<FetchedPage, FetchError> {
+        let (article, pages) = match content_type.as_str() {
+            "application/pdf" => {
+                // 截断的 PDF 无法解析，所以直接报错
+                if over_limit {
+                    return Err(FetchError::TooLarge(self.max_bytes));
+                }
+
+                // pdf-extract 是同步的且可能很慢，放到阻塞线程池中运行
+                let pages = tokio::task::spawn_blocking(move || document::pdf_pages(&body))
+                    .await
+                    .map_err(|e| DocumentError::Pdf(e.to_string()))??;
+                (article_from_pages(&pages), pages)
+            }
+            "text/plain" => {
+                let pages = document::text_pages(&String::from_utf8_lossy(&body));
+                (article_from_pages(&pages), pages)
+            }
+            _ => {
+                let article = extract::extract(&String::from_utf8_lossy(&body), Some(&url));
+                let pages = vec![Page {
+                    number: None,
+                    text: article.text.clone(),
+                }];
+                (article, pages)
+            }
+        };
+
+        let (chunks, over_budget) =
+            document::fit_to_budget(document::chunk_pages(&pages), self.token_budget);
+
+        Ok(FetchedPage {
+            url,
+            content_type,
+            article,
+            chunks,
+            truncated: over_limit || over_budget,
+        })
+    }
+}
+
+/// 没有 HTML 元数据的文档只有正文
+fn article_from_pages(pages: &[Page]) -> Article {
+    Article {
+        text: pages
+            .iter()
+            .map(|p| p.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        ..Default::default()
+    }
+}
+
+/// 从 Content-Type 头中提取小写的 MIME 类型（去掉 charset 等参数）。
+fn mime_type(header: &str) -> String {
+    header
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// 根据文件扩展名推断本地文件的 MIME 类型
+fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some("application/pdf"),
+        "txt" | "text" | "md" | "markdown" | "log" | "csv" => Some("text/plain"),
+        "html" | "htm" => Some("text/html"),
+        "xhtml" => Some("application/xhtml+xml"),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{StubResponse, StubServer};
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title> Async Rust Explained </title><style>body { color: red; }</style></head>
+<body>
+  <nav><a href="/">Home</a> | <a href="/blog">Blog</a></nav>
+  <header><h1>Site Name</h1></header>
+  <article>
+    <h1>Async Rust Explained</h1>
+    <p>Futures are lazy and do   nothing unless polled.</p>
+    <script>trackVisitor();</script>
+    <p>An executor such as <b>Tokio</b> drives them to completion.</p>
+  </article>
+  <footer>Copyright 2024</footer>
+</body>
+</html>"#;
+
+    const REPORT_PDF: &[u8] = include_bytes!("../tests/fixtures/documents/report.pdf");
+
+    fn fetcher(config: Config) -> PageFetcher {
+        PageFetcher::from_config(&config)
+    }
+
+    #[test]
+    fn test_mime_type() {
+        assert_eq!(mime_type("Text/HTML; charset=utf-8"), "text/html");
+        assert_eq!(mime_type("application/pdf"), "application/pdf");
+        assert_eq!(
+            mime_from_extension(Path::new("paper.PDF")),
+            Some("application/pdf")
+        );
+        assert_eq!(mime_from_extension(Path::new("archive.zip")), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_html_page() {
+        let server =
+            StubServer::spawn(|_| StubResponse::with_type(200, "text/html; charset=utf-8", PAGE))
+                .await;
+
+        let page = fetcher(Config::default())
+            .fetch(&format!("{}/article", server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(page.url, format!("{}/article", server.url));
+        assert_eq!(page.content_type, "text/html");
+        assert_eq!(page.article.title.as_deref(), Some("Async Rust Explained"));
+        assert!(page.article.text.contains("Futures are lazy"));
+        assert!(!page.article.text.contains("trackVisitor"));
+        assert!(!page.article.text.contains("Copyright"));
+        assert_eq!(page.chunks.len(), 1);
+        assert_eq!(page.chunks[0].page, None);
+        assert!(!page.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_disallowed_content_type() {
+        let server =
+            StubServer::spawn(|_| StubResponse::with_type(200, "image/png", "\u{89}PNG")).await;
+
+        let err = fetcher(Config::default())
+            .fetch(&server.url)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FetchError::UnsupportedContentType(t) if t == "image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_limits_bytes_and_reports_status() {
+        let server = StubServer::spawn(|req| {
+            if req.path == "/missing" {
+                StubResponse::with_type(404, "text/html", "not found")
+            } else {
+                StubResponse::with_type(200, "text/plain", "a".repeat(10_000))
+            }
+        })
+        .await;
+
+        let config = Config {
+            fetch_max_bytes: 100,
+            ..Default::default()
+        };
+        let page = fetcher(config.clone()).fetch(&server.url).await.unwrap();
+        assert_eq!(page.article.text.len(), 100);
+        assert_eq!(page.chunks[0].text.len(), 100);
+        assert!(page.truncated);
+
+        let err = fetcher(config)
+            .fetch(&format!("{}/missing", server.url))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FetchError::HttpStatus(404)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pdf_keeps_page_numbers() {
+        let server = StubServer::spawn(|_| {
+            StubResponse::with_type(200, "application/pdf", REPORT_PDF.to_vec())
+        })
+        .await;
+
+        let page = fetcher(Config::default())
+            .fetch(&format!("{}/report.pdf", server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(page.content_type, "application/pdf");
+        let pages: Vec<_> = page.chunks.iter().map(|c| c.page).collect();
+        assert_eq!(pages, vec![Some(1), Some(2), Some(3)]);
+        assert!(page.chunks[1].text.contains("borrow checker"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_truncated_pdf_is_rejected() {
+        let server = StubServer::spawn(|_| {
+            StubResponse::with_type(200, "application/pdf", REPORT_PDF.to_vec())
+        })
+        .await;
+
+        let config = Config {
+            fetch_max_bytes: 200,
+            ..Default::default()
+        };
+        let err = fetcher(config).fetch(&server.url).await.unwrap_err();
+        assert!(matches!(err, FetchError::TooLarge(200)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_local_files() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/documents/notes.txt"
+        );
+
+        // 默认禁止读取本地文件
+        let err = fetcher(Config::default()).fetch(path).await.unwrap_err();
+        assert!(matches!(err, FetchError::LocalFilesDisabled(_)));
+
+        let config = Config {
+            allow_local_files: true,
+            ..Default::default()
+        };
+        let page = fetcher(config.clone())
+            .fetch(&format!("file://{}", path))
+            .await
+            .unwrap();
+        assert!(page.url.starts_with("file:///"));
+        assert_eq!(page.content_type, "text/plain");
+        let pages: Vec<_> = page.chunks.iter().map(|c| c.page).collect();
+        assert_eq!(pages, vec![Some(1), Some(2), Some(3)]);
+
+        let pdf = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/documents/report.pdf"
+        );
+        let page = fetcher(config.clone()).fetch(pdf).await.unwrap();
+        assert_eq!(page.content_type, "application/pdf");
+        assert_eq!(page.chunks.len(), 3);
+
+        // 本地文件同样只读取 fetch_max_bytes
+        let page = fetcher(Config {
+            fetch_max_bytes: 100,
+            ..config
+        })
+        .fetch(path)
+        .await
+        .unwrap();
+        assert!(page.truncated);
+        assert!(page.article.text.len() <= 100);
+    }
+}
@@ -0,0 +1,204 @@
+//! # LLM 后端模块
+//!
+//! 研究代理可以使用 Ollama，也可以使用任何 OpenAI 兼容的服务器
+//! （llama.cpp server、vLLM、LM Studio 等）。本模块根据 `Config::provider`
+//! 创建对应的客户端，并把采样参数转换为各提供商的格式。
+//!
+//! 它演示了：
+//! - 用枚举包装不同类型的泛型值（"枚举分派"）
+//! - 用 `macro_rules!` 消除对每个变体重复的 `match` 分支
+
+use rig::agent::AgentBuilder;
+use rig::client::{CompletionClient, Nothing};
+use rig::completion::CompletionModel;
+use rig::providers::{ollama, openai};
+use thiserror::Error;
+use tracing::debug;
+
+use crate::config::{Config, LlmProvider};
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 创建 LLM 客户端时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[error("创建 {provider} 客户端失败: {message}")]
+    Client {
+        provider: LlmProvider,
+        message: String,
+    },
+}
+
+// =============================================================================
+// 模型
+// =============================================================================
+/// 一个已配置好的模型：创建的每个代理都带有相同的采样参数。
+///
+/// # Rust 概念：泛型结构体
+///
+/// `M` 是具体的补全模型类型（Ollama 或 OpenAI）。Rig 的代理也是泛型的，
+/// 所以 `Llm<M>` 创建的代理在编译期就确定了要调用哪个提供商。
+pub struct Llm<M> {
+    model: M,
+    temperature: f64,
+    params: Option<serde_json::Value>,
+}
+
+impl<M: CompletionModel> Llm<M> {
+    /// 创建代理构建器：设置系统提示和配置中的采样参数。
+    pub fn agent(&self, preamble: &str) -> AgentBuilder<M> {
+        let builder = AgentBuilder::new(self.model.clone())
+            .preamble(preamble)
+            .temperature(self.temperature);
+
+        match &self.params {
+            Some(params) => builder.additional_params(params.clone()),
+            None => builder,
+        }
+    }
+}
+
+/// 根据配置选择的 LLM 后端。
+pub enum LlmBackend {
+    Ollama(Llm<ollama::CompletionModel>),
+    OpenAiCompatible(Llm<openai::CompletionModel>),
+}
+
+impl LlmBackend {
+    /// 根据配置创建客户端和模型。
+    pub fn from_config(config: &Config) -> Result<Self, LlmError> {
+        let temperature = config.temperature as f64;
+
+        debug!(
+            provider = %config.provider,
+            model = %config.model,
+            "Creating LLM client"
+        );
+
+        let backend = match config.provider {
+            LlmProvider::Ollama => {
+                // 直接用配置中的地址构建客户端，而不是通过 OLLAMA_API_BASE_URL 环境变量：
+                // 在多线程运行时中修改环境变量是不安全的，也会让所有代理共用同一个地址
+                let client = ollama::Client::builder()
+                    .api_key(Nothing)
+                    .base_url(&config.ollama_host)
+                    .build()
+                    .map_err(|e| LlmError::Client {
+                        provider: config.provider,
+                        message: e.to_string(),
+                    })?;
+                Self::Ollama(Llm {
+                    model: client.completion_model(&config.model),
+                    temperature,
+                    params: ollama_options(config),
+                })
+            }
+            LlmProvider::OpenAiCompatible => {
+                // 本地服务器通常不检查密钥，但请求仍然需要一个 Authorization 头
+                let api_key = config.openai_api_key.clone().unwrap_or_default();
+                let client = openai::CompletionsClient::builder()
+                    .api_key(api_key)
+                    .base_url(&config.openai_base_url)
+                    .build()
+                    .map_err(|e| LlmError::Client {
+                        provider: config.provider,
+                        message: e.to_string(),
+                    })?;
+                Self::OpenAiCompatible(Llm {
+                    model: client.completion_model(&config.model),
+                    temperature,
+                    params: openai_params(config),
+                })
+            }
+        };
+        Ok(backend)
+    }
+}
+
+/// 对后端中的 `Llm<M>` 执行同一段泛型代码。
+///
+/// ```ignore
+/// with_llm!(&backend, llm => self.run(llm).await)
+/// ```
+///
+/// 展开为对每个变体的 `match`，每个分支中 `llm` 的具体类型不同，
+/// 所以 `$body` 会针对每个提供商各编译一次。
+macro_rules! with_llm {
+    ($backend:expr, $llm:ident => $body:expr) => {
+        match $backend {
+            $crate::llm::LlmBackend::Ollama($llm) => $body,
+            $crate::llm::LlmBackend::OpenAiCompatible($llm) => $body,
+        }
+    };
+}
+pub(crate) use with_llm;
+
+// =============================================================================
+// 采样参数
+// =============================================================================
+/// 把配置中的采样参数转换为 Ollama 的 `options`（都没有设置时返回 None）。
+///
+/// 温度由 Rig 单独传递；最大输出令牌数在 Ollama 中叫 `num_predict`。
+fn ollama_options(config: &Config) -> Option<serde_json::Value> {
+    let mut options = serde_json::Map::new();
+    if let Some(top_p) = config.top_p {
+        options.insert("top_p".into(), top_p.into());
+    }
+    if let Some(seed) = config.seed {
+        options.insert("seed".into(), seed.into());
+    }
+    if let Some(num_ctx) = config.num_ctx {
+        options.insert("num_ctx".into(), num_ctx.into());
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        options.insert("num_predict".into(), max_tokens.into());
+    }
+    (!options.is_empty()).then_some(serde_json::Value::Object(options))
+}
+
+/// 把采样参数转换为 OpenAI 请求体中的顶层字段（都没有设置时返回 None）。
+///
+/// Chat Completions API 没有上下文窗口参数（由服务器启动时决定），所以忽略 `num_ctx`。
+fn openai_params(config: &Config) -> Option<serde_json::Value> {
+    let mut params = serde_json::Map::new();
+    if let Some(top_p) = config.top_p {
+        params.insert("top_p".into(), top_p.into());
+    }
+    if let Some(seed) = config.seed {
+        params.insert("seed".into(), seed.into());
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        params.insert("max_tokens".into(), max_tokens.into());
+    }
+    (!params.is_empty()).then_some(serde_json::Value::Object(params))
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_params() {
+        assert_eq!(ollama_options(&Config::default()), None);
+        assert_eq!(openai_params(&Config::default()), None);
+
+        let config = Config {
+            seed: Some(42),
+            num_ctx: Some(8192),
+            max_tokens: Some(1024),
+            ..Config::default()
+        };
+        assert_eq!(
+            ollama_options(&config),
+            Some(serde_json::json!({ "seed": 42, "num_ctx": 8192, "num_predict": 1024 }))
+        );
+        assert_eq!(
+            openai_params(&config),
+            Some(serde_json::json!({ "seed": 42, "max_tokens": 1024 }))
+        );
+    }
+}
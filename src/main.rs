@@ -27,19 +27,84 @@ mod config;
 /// 研究代理实现
 mod agent;
 
+/// LLM 后端（Ollama 和 OpenAI 兼容服务器）
+mod llm;
+
+/// 可插拔的搜索后端
+mod search;
+
+/// 网页获取
+mod fetch;
+
+/// Readability 风格的正文提取
+mod extract;
+
+/// PDF 和纯文本文档的按页分块
+mod document;
+
 /// 网络搜索和其他工具
 mod tools;
 
+/// 深度研究：子问题拆分和覆盖跟踪
+mod deep;
+
+/// 结构化研究报告和来源引用
+mod report;
+
+/// 引用核验（字符串重叠 + LLM 蕴含判断）
+mod verify;
+
+/// 研究过程中的实时事件（工具调用、令牌）
+mod events;
+
+/// 交互式研究会话（REPL）
+mod repl;
+
+/// SQLite 会话存储
+mod store;
+
+/// 运行环境检查（doctor 子命令）
+mod doctor;
+
+/// Ollama 模型管理：检查并拉取缺少的模型
+mod models;
+
+/// 输出格式（文本或 JSON）
+mod output;
+
+/// 报告导出（Markdown、HTML 文件）
+mod export;
+
+/// 参考文献导出（BibTeX、CSL-JSON）
+mod bib;
+
+/// HTTP API 服务器（serve 子命令）
+mod server;
+
+/// 测试辅助工具（仅测试时编译）
+#[cfg(test)]
+mod test_util;
+
 // =============================================================================
 // 导入
 // =============================================================================
 use anyhow::Result;
-use clap::Parser;
-use tracing::{error, info, Level};
-use tracing_subscriber::FmtSubscriber;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
-use crate::agent::ResearchAgent;
-use crate::config::Config;
+use crate::agent::{AgentError, Conversation, ResearchAgent};
+use crate::bib::BibTarget;
+use crate::config::{Config, ConfigSource, LlmProvider, SearchBackend};
+use crate::events::EventSink;
+use crate::export::ExportTarget;
+use crate::output::{OutputFormat, RunOutput};
+use crate::report::ResearchReport;
+use crate::store::SessionStore;
 
 // =============================================================================
 // CLI 参数
@@ -68,7 +133,7 @@ AI 研究代理 - 您的智能研究伙伴！
 
 前置条件：
   1. 安装 Ollama：https://ollama.ai
-  2. 拉取模型：ollama pull llama3.2
+  2. 拉取模型：ollama pull llama3.2（或运行时加 --pull 自动拉取）
   3. 启动 Ollama：ollama serve
 
 示例：
@@ -78,23 +143,45 @@ AI 研究代理 - 您的智能研究伙伴！
   # 快速搜索而不综合
   ai-research-agent --quick "2024 年 Rust Web 框架"
 
+  # 深度研究：拆分子问题并逐个研究
+  ai-research-agent --deep "Rust 和 Go 在云原生领域的采用情况对比"
+
   # 使用特定模型
   ai-research-agent --model deepseek-v3.2 "Rust 中的机器学习"
-"#
+
+  # 交互模式：保留对话和来源，可以连续追问
+  ai-research-agent interactive
+
+  # 检查 Ollama、模型和搜索后端是否就绪
+  ai-research-agent doctor
+
+  # 查看和恢复保存的研究会话
+  ai-research-agent sessions list
+  ai-research-agent sessions resume 3
+"#,
+    subcommand_negates_reqs = true
 )]
 struct Args {
+    /// 子命令（不指定时研究 QUERY 后退出）
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// 要研究的主题或问题
-    #[arg(help = "要研究的主题", value_name = "QUERY")]
-    query: String,
+    #[arg(help = "要研究的主题", value_name = "QUERY", required = true)]
+    query: Option<String>,
 
-    /// 要使用的 Ollama 模型（覆盖 OLLAMA_MODEL 环境变量）
+    /// 配置档案：使用配置文件中 `[profile.<名称>]` 表的设置
     #[arg(
-        short = 'm',
-        long = "model",
-        help = "要使用的 Ollama 模型",
-        env = "OLLAMA_MODEL"
+        long = "profile",
+        help = "使用配置文件中的命名配置档案（例如 fast、deep）",
+        value_name = "NAME",
+        global = true
     )]
-    model: Option<String>,
+    profile: Option<String>,
+
+    /// 覆盖配置文件和环境变量的参数
+    #[command(flatten)]
+    overrides: ConfigOverrides,
 
     /// 快速搜索模式 - 只搜索，不综合
     #[arg(
@@ -105,16 +192,374 @@ struct Args {
     )]
     quick: bool,
 
+    /// 深度研究模式 - 拆分子问题，逐个搜索和阅读后综合
+    #[arg(
+        short = 'd',
+        long = "deep",
+        help = "深度研究模式（拆分子问题，多轮搜索）",
+        default_value = "false",
+        conflicts_with = "quick"
+    )]
+    deep: bool,
+
+    /// 关闭流式输出，只在最后打印完整结果（适合脚本）
+    #[arg(
+        long = "no-stream",
+        help = "关闭流式输出，只打印最终结果",
+        default_value = "false",
+        global = true
+    )]
+    no_stream: bool,
+
+    /// 结果的输出格式：text（默认）或 json（机器可读，隐含 --no-stream）
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        help = "输出格式：text 或 json",
+        default_value = "text"
+    )]
+    format: OutputFormat,
+
+    /// 把报告写入文件，格式由扩展名决定（.md 或 .html）
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "PATH",
+        help = "把报告写入文件（.md 或 .html）",
+        conflicts_with = "quick"
+    )]
+    output: Option<ExportTarget>,
+
+    /// 把报告引用的来源写入参考文献文件，格式由扩展名决定（.bib 或 .json）
+    #[arg(
+        long = "bib",
+        value_name = "PATH",
+        help = "把引用的来源写入 BibTeX（.bib）或 CSL-JSON（.json）文件",
+        conflicts_with = "quick"
+    )]
+    bib: Option<BibTarget>,
+
+    /// 不把本次研究保存到会话数据库
+    #[arg(
+        long = "no-save",
+        help = "不保存研究会话",
+        default_value = "false",
+        global = true
+    )]
+    no_save: bool,
+
+    /// 配置的模型不在 Ollama 上时直接拉取，不再询问
+    #[arg(
+        long = "pull",
+        help = "模型不在 Ollama 上时自动拉取（显示进度）",
+        default_value = "false",
+        global = true
+    )]
+    pull: bool,
+
     /// 详细输出（调试日志）
     #[arg(
         short = 'v',
         long = "verbose",
         help = "启用详细/调试日志",
-        default_value = "false"
+        default_value = "false",
+        global = true
     )]
     verbose: bool,
 }
 
+/// 覆盖配置的命令行参数，优先级最高（高于配置文件和环境变量）。
+///
+/// 字段名与 `Config` 的字段相同，所以可以用一个宏统一应用并记录来源。
+/// 没有提供 API 密钥参数：命令行参数会出现在 shell 历史和进程列表中，
+/// 密钥请使用 OPENAI_API_KEY 环境变量或配置文件。
+///
+/// # Rust 概念：flatten
+///
+/// `#[command(flatten)]` 把另一个实现了 `clap::Args` 的结构体的参数
+/// 合并到父命令中，这样几十个配置参数不会挤在主 `Args` 结构体里。
+#[derive(clap::Args, Debug, Default)]
+#[command(next_help_heading = "配置（覆盖配置文件和环境变量）")]
+struct ConfigOverrides {
+    /// LLM 提供商（LLM_PROVIDER）
+    #[arg(long, help = "LLM 提供商：ollama 或 openai-compatible", global = true)]
+    provider: Option<LlmProvider>,
+
+    /// 要使用的模型（OLLAMA_MODEL）
+    #[arg(short = 'm', long, help = "要使用的模型", global = true)]
+    model: Option<String>,
+
+    /// Ollama 服务器 URL（OLLAMA_API_BASE_URL）
+    #[arg(long, value_name = "URL", help = "Ollama 服务器 URL", global = true)]
+    ollama_host: Option<String>,
+
+    /// OpenAI 兼容服务器的基础 URL（OPENAI_BASE_URL）
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "OpenAI 兼容服务器的基础 URL（包含 /v1）",
+        global = true
+    )]
+    openai_base_url: Option<String>,
+
+    /// 采样温度（TEMPERATURE）
+    #[arg(long, help = "采样温度（0.0-2.0）", global = true)]
+    temperature: Option<f32>,
+
+    /// 核采样阈值（TOP_P）
+    #[arg(long, help = "核采样阈值（0.0-1.0）", global = true)]
+    top_p: Option<f32>,
+
+    /// 随机种子（SEED），固定种子可以得到可复现的结果
+    #[arg(long, help = "随机种子（用于可复现的运行）", global = true)]
+    seed: Option<u64>,
+
+    /// 上下文窗口大小（NUM_CTX）
+    #[arg(long, help = "上下文窗口大小（令牌）", global = true)]
+    num_ctx: Option<u32>,
+
+    /// 每次回答最多生成的令牌数（MAX_TOKENS）
+    #[arg(long, help = "最多生成的令牌数", global = true)]
+    max_tokens: Option<u64>,
+
+    /// 代理最多进行的工具调用轮数（MAX_TURNS）
+    #[arg(long, help = "每次研究最多的工具调用轮数", global = true)]
+    max_turns: Option<usize>,
+
+    /// 要分析的搜索结果数（MAX_SEARCH_RESULTS）
+    #[arg(long, help = "每次搜索返回的结果数", global = true)]
+    max_search_results: Option<usize>,
+
+    /// 搜索后端（SEARCH_PROVIDER）
+    #[arg(long, help = "搜索后端：duckduckgo 或 searxng", global = true)]
+    search_provider: Option<SearchBackend>,
+
+    /// SearXNG 实例 URL（SEARXNG_URL）
+    #[arg(long, value_name = "URL", help = "SearXNG 实例 URL", global = true)]
+    searxng_url: Option<String>,
+
+    /// SearXNG 类别（SEARXNG_CATEGORIES）
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        help = "SearXNG 类别，以逗号分隔",
+        global = true
+    )]
+    searxng_categories: Option<Vec<String>>,
+
+    /// 搜索请求超时（SEARCH_TIMEOUT_SECS）
+    #[arg(
+        long = "search-timeout",
+        value_name = "SECS",
+        help = "搜索请求超时（秒）",
+        global = true
+    )]
+    search_timeout_secs: Option<u64>,
+
+    /// 页面获取超时（FETCH_TIMEOUT_SECS）
+    #[arg(
+        long = "fetch-timeout",
+        value_name = "SECS",
+        help = "页面获取超时（秒）",
+        global = true
+    )]
+    fetch_timeout_secs: Option<u64>,
+
+    /// 每个页面最多下载的字节数（FETCH_MAX_BYTES）
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "每个页面最多下载的字节数",
+        global = true
+    )]
+    fetch_max_bytes: Option<usize>,
+
+    /// fetch_page 接受的 MIME 类型（FETCH_CONTENT_TYPES）
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        help = "接受的内容类型，以逗号分隔",
+        global = true
+    )]
+    fetch_content_types: Option<Vec<String>>,
+
+    /// 每个页面返回给 LLM 的令牌上限（PAGE_TOKEN_BUDGET）
+    #[arg(long, help = "每个页面返回给模型的令牌上限", global = true)]
+    page_token_budget: Option<usize>,
+
+    /// 快速搜索的正文预览字符数（PREVIEW_CHARS）
+    #[arg(long, help = "快速搜索的正文预览字符数（0 表示不预览）", global = true)]
+    preview_chars: Option<usize>,
+
+    /// 深度研究最多研究的子问题数（DEEP_MAX_QUESTIONS）
+    #[arg(long, help = "深度研究最多研究的子问题数", global = true)]
+    deep_max_questions: Option<usize>,
+
+    /// 深度研究中每个子问题的工具调用轮数上限（DEEP_MAX_TURNS）
+    #[arg(long, help = "深度研究中每个子问题的最大工具调用轮数", global = true)]
+    deep_max_turns: Option<usize>,
+
+    /// 会话数据库路径（SESSION_DB）
+    #[arg(long, value_name = "PATH", help = "会话数据库路径", global = true)]
+    session_db: Option<PathBuf>,
+
+    /// 日志级别或过滤指令（RUST_LOG），例如 debug、ai_research_agent=trace
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        help = "日志级别（例如 info、debug）",
+        global = true
+    )]
+    log_level: Option<String>,
+
+    /// 允许 fetch_page 读取本地文件（ALLOW_LOCAL_FILES=true）
+    #[arg(long, help = "允许读取本地 PDF/文本文件", global = true)]
+    allow_local_files: bool,
+
+    /// 跳过引用核验（VERIFY_CITATIONS=false），更快，但不标记引用是否被来源支持
+    #[arg(long, help = "跳过引用核验", global = true)]
+    no_verify: bool,
+}
+
+impl ConfigOverrides {
+    /// 把提供了的参数写入配置，并记录它们来自命令行。
+    fn apply_to(self, config: &mut Config) {
+        macro_rules! apply {
+            ($($field:ident => $flag:literal),* $(,)?) => {$(
+                if let Some(value) = self.$field {
+                    config.$field = value;
+                    config.set_origin(stringify!($field), ConfigSource::Cli($flag));
+                }
+            )*};
+        }
+        macro_rules! apply_optional {
+            ($($field:ident => $flag:literal),* $(,)?) => {$(
+                if self.$field.is_some() {
+                    config.$field = self.$field;
+                    config.set_origin(stringify!($field), ConfigSource::Cli($flag));
+                }
+            )*};
+        }
+
+        apply!(
+            provider => "--provider",
+            model => "--model",
+            ollama_host => "--ollama-host",
+            openai_base_url => "--openai-base-url",
+            temperature => "--temperature",
+            max_turns => "--max-turns",
+            max_search_results => "--max-search-results",
+            search_provider => "--search-provider",
+            searxng_url => "--searxng-url",
+            searxng_categories => "--searxng-categories",
+            search_timeout_secs => "--search-timeout",
+            fetch_timeout_secs => "--fetch-timeout",
+            fetch_max_bytes => "--fetch-max-bytes",
+            page_token_budget => "--page-token-budget",
+            preview_chars => "--preview-chars",
+            deep_max_questions => "--deep-max-questions",
+            deep_max_turns => "--deep-max-turns",
+            session_db => "--session-db",
+            log_level => "--log-level",
+        );
+        apply_optional!(
+            top_p => "--top-p",
+            seed => "--seed",
+            num_ctx => "--num-ctx",
+            max_tokens => "--max-tokens",
+        );
+
+        // 与配置文件和环境变量一致，内容类型统一转为小写
+        if let Some(types) = self.fetch_content_types {
+            config.fetch_content_types = types.iter().map(|t| t.to_ascii_lowercase()).collect();
+            config.set_origin(
+                "fetch_content_types",
+                ConfigSource::Cli("--fetch-content-types"),
+            );
+        }
+        if self.allow_local_files {
+            config.allow_local_files = true;
+            config.set_origin(
+                "allow_local_files",
+                ConfigSource::Cli("--allow-local-files"),
+            );
+        }
+        if self.no_verify {
+            config.verify_citations = false;
+            config.set_origin("verify_citations", ConfigSource::Cli("--no-verify"));
+        }
+    }
+}
+
+/// # Rust 概念：子命令枚举
+///
+/// `#[derive(Subcommand)]` 把枚举的每个变体变成一个子命令，
+/// 变体名自动转换为 kebab-case（`Interactive` → `interactive`）。
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 交互模式：保留聊天历史和来源，支持追问和斜杠命令
+    #[command(visible_alias = "repl")]
+    Interactive,
+
+    /// 管理保存的研究会话（只保存成功完成的研究；快速搜索和失败的研究不保存）
+    Sessions {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// 检查运行环境：配置、LLM 服务器、模型、工具调用支持和搜索后端
+    Doctor,
+
+    /// 以 HTTP API 的形式提供研究代理（POST /research、POST /search、GET /jobs/{id}）
+    Serve {
+        /// 监听地址。API 没有身份验证，默认只监听本机
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        listen: SocketAddr,
+    },
+
+    /// 查看配置
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+/// `config` 的子命令。
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// 显示合并后生效的配置，以及每个值来自哪里
+    Show,
+}
+
+/// `sessions` 的子命令。
+#[derive(Subcommand, Debug)]
+enum SessionAction {
+    /// 列出保存的会话
+    #[command(visible_alias = "ls")]
+    List,
+
+    /// 显示会话的工具调用、报告和配置快照
+    Show {
+        /// 会话编号（见 `sessions list`）
+        id: i64,
+    },
+
+    /// 在交互模式中继续会话
+    Resume {
+        /// 会话编号
+        id: i64,
+    },
+
+    /// 删除会话
+    #[command(visible_alias = "rm")]
+    Delete {
+        /// 会话编号
+        id: i64,
+    },
+}
+
 // =============================================================================
 // 主函数
 // =============================================================================
@@ -126,61 +571,212 @@ struct Args {
 /// 2. 在其中运行我们的异步 main
 ///
 /// 这等价于：
-/// ```
-/// fn main() {
-///     let rt = tokio::runtime::Runtime::new().unwrap();
-///     rt.block_on(async { /* 我们的代码 */ });
-/// }
+/// ```ignore
+/// let rt = tokio::runtime::Runtime::new().unwrap();
+/// rt.block_on(async { /* 我们的代码 */ });
 /// ```
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // 打印用户友好的错误消息，并根据错误类型给出建议
+            eprintln!("\n❌ {:#}", e);
+            if let Some(hint) = e.downcast_ref::<AgentError>().and_then(AgentError::hint) {
+                eprintln!("\n💡 提示：{}", hint);
+            }
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// 应用程序主体。返回的错误由 `main` 打印，并转换为退出码。
+async fn run() -> Result<()> {
     // 解析命令行参数
     // Clap 自动处理 --help、--version 和错误消息
     let args = Args::parse();
 
-    // 初始化日志
-    init_logging(args.verbose)?;
+    // 从配置文件、环境变量和 .env 文件加载配置
+    let mut config = Config::load(args.profile.as_deref()).map_err(ConfigError)?;
+
+    // 命令行参数优先于配置文件和环境变量，并记录来源（用于验证错误和 config show）
+    args.overrides.apply_to(&mut config);
+    if args.verbose {
+        config.log_level = "debug".to_string();
+        config.set_origin("log_level", ConfigSource::Cli("--verbose"));
+    }
+
+    // 初始化日志（需要先知道配置中的日志级别）
+    init_logging(&config.log_level)?;
 
     info!("AI 研究代理正在启动...");
 
-    // 从环境变量/.env 文件加载配置
-    let mut config = Config::from_env()?;
+    // 恢复会话时，只有明确指定的模型（环境变量或 --model）才替换会话原来的模型
+    let model_override = matches!(
+        config.origin("model"),
+        ConfigSource::Env(_) | ConfigSource::Cli(_)
+    );
 
-    // 如果在命令行上指定了模型则覆盖
-    //
-    // # Rust 概念：Option 类型
-    // Option<T> 要么是 Some(value)，要么是 None。
-    // if let Some(x) = option { } 是处理此问题的简洁方式。
-    if let Some(model) = args.model {
-        info!(model = %model, "使用命令行中的模型");
-        config.model = model;
+    // config show 在验证之前打印，这样也能用来排查无效的配置
+    if let Some(Command::Config {
+        action: ConfigAction::Show,
+    }) = args.command
+    {
+        print!("{}", config.describe()?);
+        config.validate().map_err(ConfigError)?;
+        return Ok(());
+    }
+
+    // doctor 自己报告配置问题，和其他检查一起列在表格中
+    if let Some(Command::Doctor) = args.command {
+        let checks = doctor::run_checks(&config).await;
+        print!("{}", doctor::render(&checks));
+        let failed = checks
+            .iter()
+            .filter(|c| c.status == doctor::Status::Fail)
+            .count();
+        if failed > 0 {
+            anyhow::bail!("{} 项检查失败", failed);
+        }
+        return Ok(());
     }
 
     // 验证配置
-    config.validate()?;
+    config.validate().map_err(ConfigError)?;
 
     info!(
+        provider = %config.provider,
         model = %config.model,
         host = %config.ollama_host,
+        search = %config.search_provider,
         "配置已加载"
     );
 
+    match args.command {
+        // 交互模式：进入 REPL，直到用户退出
+        Some(Command::Interactive) => {
+            models::ensure_model(&config, args.pull).await?;
+            let store = open_store(&config, args.no_save);
+            return repl::run(config, !args.no_stream, args.pull, store, None).await;
+        }
+        Some(Command::Sessions { action }) => {
+            let stream = !args.no_stream;
+            return run_sessions(
+                action,
+                config,
+                model_override,
+                stream,
+                args.no_save,
+                args.pull,
+            )
+            .await;
+        }
+        Some(Command::Serve { listen }) => {
+            models::ensure_model(&config, args.pull).await?;
+            let store = open_store(&config, args.no_save);
+            return server::serve(config, store, listen).await;
+        }
+        Some(Command::Doctor | Command::Config { .. }) | None => {}
+    }
+
+    // clap 保证没有子命令时一定提供了查询
+    let query = args.query.unwrap_or_default();
+
+    // 快速模式不调用 LLM，不需要模型
+    if !args.quick {
+        models::ensure_model(&config, args.pull).await?;
+    }
+
     // 创建研究代理
-    let agent = ResearchAgent::new(config);
+    let agent = ResearchAgent::new(config)?;
+
+    // JSON 模式下标准输出只留给最终的 JSON 文档，所以不流式打印
+    let json = args.format == OutputFormat::Json;
+
+    // 在后台任务中收集代理发出的事件（用于保存会话）；
+    // 流式模式下同时实时打印（快速模式不调用 LLM，没有可以流式输出的内容）
+    let stream = !args.no_stream && !args.quick && !json;
+    let (events, receiver) = EventSink::channel();
+    let collector = tokio::spawn(events::collect(receiver, stream));
+
+    let mode = if args.quick {
+        "quick"
+    } else if args.deep {
+        "deep"
+    } else {
+        "research"
+    };
+    let mut output = RunOutput::new(&query, mode, agent.config(), Utc::now());
 
     // 执行查询
+    let mut exported = Ok(());
     let result = if args.quick {
-        // 快速模式：只搜索，不综合
+        // 快速模式：只搜索，不综合，也不保存
         info!("正在运行快速搜索模式");
-        agent.quick_search(&args.query).await
+        agent.quick_results(&query).await.map(|results| {
+            let body = agent::format_quick_results(&query, &results);
+            output.add_search_results(results.into_iter().map(|(result, _)| result));
+            body
+        })
     } else {
-        // 完整模式：搜索 + AI 综合
-        info!("正在运行完整研究模式");
-        agent.research(&args.query).await
+        let mut conversation = Conversation::default();
+        let report = if args.deep {
+            // 深度模式：拆分子问题 + 多轮研究 + 综合
+            info!("正在运行深度研究模式");
+            agent
+                .deep_research(&query, &mut conversation, &events)
+                .await
+        } else {
+            // 完整模式：搜索 + AI 综合
+            info!("正在运行完整研究模式");
+            agent.research(&query, &mut conversation, &events).await
+        };
+
+        // 关闭事件通道并等待收集任务处理剩余的事件
+        drop(events);
+        let log = collector.await.unwrap_or_default();
+        output.record_events(&log);
+
+        if let (Ok(report), Some(mut store)) = (&report, open_store(agent.config(), args.no_save)) {
+            let saved = store.create(mode, &query, agent.config()).and_then(|id| {
+                store.record_turn(id, &query, report, &log, &conversation)?;
+                Ok(id)
+            });
+            match saved {
+                Ok(id) => info!(session = id, "研究会话已保存"),
+                Err(e) => warn!(error = %e, "保存研究会话失败"),
+            }
+        }
+
+        // 写入失败时仍然先打印结果，再报告错误，这样研究结果不会丢失
+        if let Ok(report) = &report {
+            let date = output.timings.started_at;
+            exported = write_files(report, agent.config(), date, &args.output, &args.bib);
+        }
+
+        // 流式模式下正文已经打印过了，只需要补上核验结果和参考文献
+        report.map(|report| {
+            let body = if stream {
+                report.stream_footer()
+            } else {
+                report.to_markdown()
+            };
+            output.report = Some(report);
+            body
+        })
     };
 
     // 处理结果
     match result {
+        Ok(response) if json => {
+            output.body = response;
+            println!("{}", serde_json::to_string_pretty(&output.finish())?);
+        }
+        Ok(response) if stream => {
+            println!("\n\n{}", "=".repeat(60));
+            println!("{}", response);
+            println!("{}", "=".repeat(60));
+        }
         Ok(response) => {
             // 打印结果到 stdout
             println!("\n{}", "=".repeat(60));
@@ -190,26 +786,142 @@ async fn main() -> Result<()> {
             println!("\n{}", "=".repeat(60));
         }
         Err(e) => {
-            // 打印用户友好的错误消息
             error!(error = %e, "研究失败");
 
-            // 根据常见错误给出有用的建议
-            eprintln!("\n❌ 研究失败: {}", e);
+            // 提示和退出码由 main 根据错误类型决定
+            return Err(anyhow::Error::new(e).context("研究失败"));
+        }
+    }
 
-            if e.to_string().contains("connection refused") {
-                eprintln!("\n💡 提示：确保 Ollama 正在运行：");
-                eprintln!("   ollama serve");
-            } else if e.to_string().contains("model") {
-                eprintln!("\n💡 提示：确保模型已安装：");
-                eprintln!("   ollama pull llama3.2");
-            }
+    // 结果已经打印，再报告写入文件的错误
+    exported?;
 
-            // 返回错误以设置非零退出代码
-            return Err(e);
+    info!("研究成功完成");
+    Ok(())
+}
+
+/// 把报告写入 `--output` 和 `--bib` 指定的文件。
+fn write_files(
+    report: &ResearchReport,
+    config: &Config,
+    date: DateTime<Utc>,
+    output: &Option<ExportTarget>,
+    bib: &Option<BibTarget>,
+) -> Result<()> {
+    if let Some(target) = output {
+        target.write(report, config, date)?;
+        info!(path = %target.path.display(), "报告已写入文件");
+    }
+    if let Some(target) = bib {
+        target.write(&report.sources)?;
+        info!(path = %target.path.display(), sources = report.sources.len(), "参考文献已写入文件");
+    }
+    Ok(())
+}
+
+// =============================================================================
+// 错误和退出码
+// =============================================================================
+/// 配置无效（配置文件、环境变量或命令行参数）。
+///
+/// 只用于让 `exit_code` 区分配置错误和运行时错误，消息保持不变。
+#[derive(Debug, thiserror::Error)]
+#[error("{0:#}")]
+struct ConfigError(anyhow::Error);
+
+/// 把错误转换为进程退出码，脚本可以据此区分失败原因（完整列表见 README）。
+///
+/// 2 是 clap 的参数错误退出码，这里不使用。
+///
+/// # Rust 概念：downcast_ref
+///
+/// `anyhow::Error` 擦除了具体的错误类型，但仍然记得它。
+/// `downcast_ref::<T>()` 在错误（或它的上下文链中）是 `T` 时返回 `Some(&T)`。
+fn exit_code(error: &anyhow::Error) -> u8 {
+    if error.downcast_ref::<ConfigError>().is_some() {
+        return 3;
+    }
+    match error.downcast_ref::<AgentError>() {
+        Some(AgentError::LlmUnreachable { .. }) => 10,
+        Some(AgentError::ModelNotFound { .. }) => 11,
+        Some(AgentError::ContextOverflow { .. }) => 12,
+        Some(AgentError::Timeout { .. }) => 13,
+        Some(AgentError::TurnLimit { .. }) => 14,
+        Some(AgentError::Client(_) | AgentError::Llm { .. }) => 15,
+        Some(AgentError::SearchRateLimited) => 20,
+        Some(AgentError::Search(_)) => 21,
+        Some(AgentError::ToolFailed(_)) => 22,
+        None => 1,
+    }
+}
+
+// =============================================================================
+// 会话
+// =============================================================================
+/// 打开会话数据库；`--no-save` 或打开失败时返回 None（研究照常进行，只是不保存）。
+fn open_store(config: &Config, no_save: bool) -> Option<SessionStore> {
+    if no_save {
+        return None;
+    }
+    match SessionStore::open(&config.session_db) {
+        Ok(store) => Some(store),
+        Err(e) => {
+            warn!(
+                error = %e,
+                path = %config.session_db.display(),
+                "无法打开会话数据库，本次研究不会保存"
+            );
+            None
         }
     }
+}
 
-    info!("研究成功完成");
+/// 执行 `sessions` 子命令。
+///
+/// 恢复会话时默认使用会话原来的模型，除非用 `--model` 或 OLLAMA_MODEL 指定了模型。
+async fn run_sessions(
+    action: SessionAction,
+    mut config: Config,
+    model_override: bool,
+    stream: bool,
+    no_save: bool,
+    pull: bool,
+) -> Result<()> {
+    let store = SessionStore::open(&config.session_db)?;
+
+    match action {
+        SessionAction::List => {
+            let sessions = store.list()?;
+            if sessions.is_empty() {
+                println!("还没有保存的会话（{}）", config.session_db.display());
+            }
+            for session in sessions {
+                println!(
+                    "#{:<4} {}  {:<11} {:<16} {:>2} 轮  {}",
+                    session.id,
+                    session.updated_at.format("%Y-%m-%d %H:%M"),
+                    session.mode,
+                    session.model,
+                    session.turns,
+                    session.query
+                );
+            }
+        }
+        SessionAction::Show { id } => println!("{}", store.load(id)?.render()),
+        SessionAction::Resume { id } => {
+            let session = store.load(id)?;
+            if !model_override {
+                config.model = session.summary.model.clone();
+            }
+            models::ensure_model(&config, pull).await?;
+            let store = (!no_save).then_some(store);
+            repl::run(config, stream, pull, store, Some(session)).await?;
+        }
+        SessionAction::Delete { id } => {
+            store.delete(id)?;
+            println!("已删除会话 #{}", id);
+        }
+    }
     Ok(())
 }
 
@@ -222,9 +934,10 @@ async fn main() -> Result<()> {
 ///
 /// `?` 操作符在出错时从函数早期返回。
 /// 这在应该中止的初始化代码中很常见。
-fn init_logging(verbose: bool) -> Result<()> {
-    // 根据详细标志设置日志级别
-    let level = if verbose { Level::DEBUG } else { Level::INFO };
+fn init_logging(log_level: &str) -> Result<()> {
+    // 日志级别使用 RUST_LOG 的过滤语法，例如 "info" 或 "ai_research_agent=debug"；
+    // 无效的值由 Config::validate 报告，这里先退回到 info
+    let filter = EnvFilter::try_new(log_level).unwrap_or_else(|_| EnvFilter::new("info"));
 
     // 构建订阅服务器
     //
@@ -232,11 +945,12 @@ fn init_logging(verbose: bool) -> Result<()> {
     // 许多 Rust 库使用构建器进行配置。
     // 每个方法修改构建器并返回它以进行链式调用。
     let subscriber = FmtSubscriber::builder()
-        .with_max_level(level)
+        .with_env_filter(filter)
         .with_target(true) // 显示记录日志的模块
         .with_thread_names(false)
         .with_file(false)
         .with_line_number(false)
+        .with_writer(std::io::stderr) // 日志写到 stderr，stdout 只留给研究结果和流式输出
         .finish();
 
     // 设置为全局默认
@@ -261,25 +975,219 @@ mod tests {
     fn test_args_parsing() {
         // 测试 CLI 参数是否正确解析
         let args = Args::parse_from(["test", "什么是 Rust？"]);
-        assert_eq!(args.query, "什么是 Rust？");
+        assert_eq!(args.query.as_deref(), Some("什么是 Rust？"));
+        assert!(args.command.is_none());
         assert!(!args.quick);
+        assert!(!args.deep);
+        assert!(!args.no_stream);
+        assert!(!args.pull);
         assert!(!args.verbose);
     }
 
+    #[test]
+    fn test_args_deep_conflicts_with_quick() {
+        let args = Args::parse_from(["test", "--deep", "测试查询"]);
+        assert!(args.deep);
+
+        assert!(Args::try_parse_from(["test", "--deep", "--quick", "测试查询"]).is_err());
+    }
+
     #[test]
     fn test_args_with_flags() {
         let args = Args::parse_from([
             "test",
             "--quick",
             "--verbose",
+            "--no-stream",
             "--model",
             "llama3.2",
             "测试查询",
         ]);
 
-        assert_eq!(args.query, "测试查询");
+        assert_eq!(args.query.as_deref(), Some("测试查询"));
         assert!(args.quick);
         assert!(args.verbose);
-        assert_eq!(args.model, Some("llama3.2".to_string()));
+        assert!(args.no_stream);
+        assert_eq!(args.overrides.model, Some("llama3.2".to_string()));
+        assert_eq!(args.format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_args_format() {
+        let args = Args::parse_from(["test", "--format", "json", "测试查询"]);
+        assert_eq!(args.format, OutputFormat::Json);
+
+        assert!(Args::try_parse_from(["test", "--format", "yaml", "测试查询"]).is_err());
+    }
+
+    #[test]
+    fn test_args_output() {
+        let args = Args::parse_from(["test", "-o", "report.html", "测试查询"]);
+        let target = args.output.unwrap();
+        assert_eq!(target.path, PathBuf::from("report.html"));
+        assert_eq!(target.format, export::ExportFormat::Html);
+
+        // 不支持的扩展名在参数解析时就报错；快速搜索没有报告可以导出
+        assert!(Args::try_parse_from(["test", "-o", "report.txt", "测试查询"]).is_err());
+        assert!(Args::try_parse_from(["test", "--quick", "-o", "r.md", "测试查询"]).is_err());
+
+        let args = Args::parse_from(["test", "--bib", "refs.bib", "测试查询"]);
+        assert_eq!(args.bib.unwrap().format, bib::BibFormat::BibTex);
+        assert!(Args::try_parse_from(["test", "--bib", "refs.ris", "测试查询"]).is_err());
+    }
+
+    #[test]
+    fn test_args_sampling_params() {
+        let args = Args::parse_from([
+            "test",
+            "--temperature",
+            "0.2",
+            "--top-p",
+            "0.9",
+            "--seed",
+            "42",
+            "--num-ctx",
+            "8192",
+            "--max-tokens",
+            "1024",
+            "测试查询",
+        ]);
+
+        assert_eq!(args.overrides.temperature, Some(0.2));
+        assert_eq!(args.overrides.top_p, Some(0.9));
+        assert_eq!(args.overrides.seed, Some(42));
+        assert_eq!(args.overrides.num_ctx, Some(8192));
+        assert_eq!(args.overrides.max_tokens, Some(1024));
+    }
+
+    #[test]
+    fn test_args_provider() {
+        let args = Args::parse_from(["test", "--provider", "openai-compatible", "测试查询"]);
+        assert_eq!(args.overrides.provider, Some(LlmProvider::OpenAiCompatible));
+
+        assert!(Args::try_parse_from(["test", "--provider", "bogus", "测试查询"]).is_err());
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let args = Args::parse_from([
+            "test",
+            "--ollama-host",
+            "http://gpu-box:11434",
+            "--max-search-results",
+            "8",
+            "--max-turns",
+            "4",
+            "--search-timeout",
+            "10",
+            "--searxng-categories",
+            "news,science",
+            "--log-level",
+            "debug",
+            "--no-verify",
+            "测试查询",
+        ]);
+
+        let mut config = Config::default();
+        args.overrides.apply_to(&mut config);
+        assert_eq!(config.ollama_host, "http://gpu-box:11434");
+        assert_eq!(config.max_search_results, 8);
+        assert_eq!(config.max_turns, 4);
+        assert_eq!(config.search_timeout_secs, 10);
+        assert_eq!(config.searxng_categories, vec!["news", "science"]);
+        assert_eq!(config.log_level, "debug");
+        assert!(!config.verify_citations);
+        assert_eq!(config.origin("max_turns"), ConfigSource::Cli("--max-turns"));
+        assert_eq!(config.origin("temperature"), ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_args_doctor_subcommand() {
+        let args = Args::parse_from(["test", "doctor", "--provider", "openai-compatible"]);
+        assert!(matches!(args.command, Some(Command::Doctor)));
+        assert_eq!(args.overrides.provider, Some(LlmProvider::OpenAiCompatible));
+    }
+
+    #[test]
+    fn test_args_serve_subcommand() {
+        let args = Args::parse_from(["test", "serve"]);
+        let Some(Command::Serve { listen }) = args.command else {
+            panic!("expected serve subcommand");
+        };
+        assert_eq!(listen, "127.0.0.1:8787".parse().unwrap());
+
+        let args = Args::parse_from(["test", "serve", "--listen", "0.0.0.0:9000", "--no-save"]);
+        assert!(matches!(args.command, Some(Command::Serve { listen }) if listen.port() == 9000));
+        assert!(args.no_save);
+    }
+
+    #[test]
+    fn test_args_config_show_subcommand() {
+        let args = Args::parse_from(["test", "config", "show", "--profile", "fast"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Config {
+                action: ConfigAction::Show
+            })
+        ));
+        assert_eq!(args.profile.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let config_error = anyhow::Error::new(ConfigError(anyhow::anyhow!("temperature 无效")));
+        assert_eq!(exit_code(&config_error), 3);
+
+        // 加上上下文之后仍然可以识别出 AgentError
+        let agent_error = anyhow::Error::new(AgentError::ModelNotFound {
+            provider: LlmProvider::Ollama,
+            model: "llama3.2".to_string(),
+        })
+        .context("研究失败");
+        assert_eq!(exit_code(&agent_error), 11);
+        assert_eq!(
+            exit_code(&anyhow::Error::new(AgentError::SearchRateLimited)),
+            20
+        );
+
+        assert_eq!(exit_code(&anyhow::anyhow!("其他错误")), 1);
+    }
+
+    #[test]
+    fn test_args_sessions_subcommand() {
+        let args = Args::parse_from(["test", "sessions", "resume", "3", "--no-save", "--pull"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Sessions {
+                action: SessionAction::Resume { id: 3 }
+            })
+        ));
+        assert!(args.no_save);
+        assert!(args.pull);
+
+        let args = Args::parse_from(["test", "sessions", "ls"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Sessions {
+                action: SessionAction::List
+            })
+        ));
+
+        assert!(Args::try_parse_from(["test", "sessions", "show", "abc"]).is_err());
+    }
+
+    #[test]
+    fn test_args_interactive_subcommand() {
+        // 交互模式不需要查询，全局参数可以写在子命令之后
+        let args = Args::parse_from(["test", "interactive", "--model", "qwen2.5"]);
+        assert!(matches!(args.command, Some(Command::Interactive)));
+        assert_eq!(args.query, None);
+        assert_eq!(args.overrides.model, Some("qwen2.5".to_string()));
+
+        let args = Args::parse_from(["test", "repl"]);
+        assert!(matches!(args.command, Some(Command::Interactive)));
+
+        // 没有子命令时必须提供查询
+        assert!(Args::try_parse_from(["test"]).is_err());
     }
 }
@@ -0,0 +1,345 @@
+//! # Ollama 模型管理模块
+//!
+//! 研究开始之前确认配置的模型已经在 Ollama 上：没有时询问用户
+//! （或在 `--pull` 时直接）通过 `/api/pull` 拉取，并在终端显示进度，
+//! 拉取完成后研究照常继续。
+//!
+//! 它演示了：
+//! - 逐块读取 HTTP 响应并按行解析 NDJSON 流
+//! - 用闭包参数（`FnMut`）把进度回调交给调用者
+
+use anyhow::Result;
+use reqwest::Client;
+use serde::Deserialize;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info};
+
+use crate::agent::AgentError;
+use crate::config::{Config, LlmProvider};
+
+/// 查询模型列表的超时时间（拉取本身可能需要很久，不设超时）
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 查询或拉取模型时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("请求 Ollama 失败: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("拉取模型失败: {0}")]
+    Pull(String),
+}
+
+// =============================================================================
+// Ollama 模型 API
+// =============================================================================
+/// 拉取过程中的一条进度消息。
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PullProgress {
+    /// 当前阶段，例如 "pulling manifest"、"pulling 6a0746a1ec1a"、"success"
+    #[serde(default)]
+    pub status: String,
+
+    /// 正在下载的层的总字节数和已完成字节数（只在下载阶段出现）
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+
+    /// 失败时 Ollama 在流中返回 `{"error": "..."}`
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl PullProgress {
+    /// 下载进度百分比（没有大小信息时返回 None）。
+    pub fn percent(&self) -> Option<u64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => Some(completed * 100 / total),
+            _ => None,
+        }
+    }
+}
+
+/// `/api/tags` 的响应
+#[derive(Debug, Deserialize)]
+struct Tags {
+    models: Vec<TagModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagModel {
+    name: String,
+}
+
+/// 访问 Ollama 模型管理 API 的客户端。
+pub struct OllamaModels {
+    client: Client,
+    host: String,
+}
+
+impl OllamaModels {
+    /// 为给定的 Ollama 地址创建客户端。
+    pub fn new(host: &str) -> Result<Self, ModelError> {
+        let client = Client::builder().connect_timeout(LIST_TIMEOUT).build()?;
+        Ok(Self {
+            client,
+            host: host.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// 列出已拉取的模型名称（例如 "llama3.2:latest"）。
+    pub async fn list(&self) -> Result<Vec<String>, ModelError> {
+        let tags: Tags = self
+            .client
+            .get(format!("{}/api/tags", self.host))
+            .timeout(LIST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+
+    /// 模型是否已经拉取。
+    pub async fn is_pulled(&self, model: &str) -> Result<bool, ModelError> {
+        Ok(self
+            .list()
+            .await?
+            .iter()
+            .any(|name| same_model(name, model)))
+    }
+
+    /// 拉取模型，每收到一条进度消息就调用一次 `on_progress`。
+    ///
+    /// # Rust 概念：FnMut
+    ///
+    /// `impl FnMut(&PullProgress)` 接受任何可以多次调用、并且可以修改
+    /// 自身捕获状态的闭包，例如记录上一次打印的阶段以决定是否换行。
+    pub async fn pull(
+        &self,
+        model: &str,
+        mut on_progress: impl FnMut(&PullProgress),
+    ) -> Result<(), ModelError> {
+        let mut response = self
+            .client
+            .post(format!("{}/api/pull", self.host))
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // 响应是 NDJSON：每行一个 JSON 对象，但一行可能跨越多个数据块
+        let mut buffer = Vec::new();
+        let mut succeeded = false;
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                succeeded |= handle_line(&line, &mut on_progress)?;
+            }
+        }
+        succeeded |= handle_line(&buffer, &mut on_progress)?;
+
+        if !succeeded {
+            return Err(ModelError::Pull("连接在拉取完成前中断".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 解析一行进度消息，返回这一行是否表示拉取成功。
+fn handle_line(
+    line: &[u8],
+    on_progress: &mut impl FnMut(&PullProgress),
+) -> Result<bool, ModelError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(false);
+    }
+    let progress: PullProgress = serde_json::from_str(line)
+        .map_err(|e| ModelError::Pull(format!("无法解析进度消息（{}）：{}", e, line)))?;
+    if let Some(error) = progress.error {
+        return Err(ModelError::Pull(error));
+    }
+    on_progress(&progress);
+    Ok(progress.status == "success")
+}
+
+/// Ollama 把没有写标签的模型名称当作 `:latest`。
+pub fn same_model(pulled: &str, requested: &str) -> bool {
+    pulled == requested || pulled.strip_suffix(":latest") == Some(requested)
+}
+
+// =============================================================================
+// 研究前的检查
+// =============================================================================
+/// 确认配置的 Ollama 模型已经拉取；没有时拉取它。
+///
+/// - `auto_pull` 为 true（`--pull`）时直接拉取
+/// - 否则在终端中询问用户；不在终端中运行（脚本、管道）时不询问，
+///   返回 `AgentError::ModelNotFound`，提示中会给出拉取的方法
+///
+/// 无法连接到 Ollama 时不在这里报错，而是交给研究过程报告
+/// `LlmUnreachable`（那里有完整的提示和退出码）。
+pub async fn ensure_model(config: &Config, auto_pull: bool) -> Result<()> {
+    if config.provider != LlmProvider::Ollama {
+        return Ok(());
+    }
+
+    let models = OllamaModels::new(&config.ollama_host)?;
+    match models.is_pulled(&config.model).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => {
+            debug!(error = %e, "无法查询 Ollama 模型列表，跳过检查");
+            return Ok(());
+        }
+    }
+
+    let confirmed = auto_pull
+        || (std::io::stdin().is_terminal() && confirm_pull(&config.model, &config.ollama_host)?);
+    if !confirmed {
+        return Err(AgentError::ModelNotFound {
+            provider: config.provider,
+            model: config.model.clone(),
+        }
+        .into());
+    }
+
+    info!(model = %config.model, "正在拉取模型");
+    let mut printer = ProgressPrinter::default();
+    models
+        .pull(&config.model, |progress| printer.print(progress))
+        .await?;
+    printer.finish();
+    eprintln!("✅ 已拉取 {}", config.model);
+    Ok(())
+}
+
+/// 询问用户是否拉取模型（直接回车表示同意）。
+fn confirm_pull(model: &str, host: &str) -> Result<bool> {
+    eprint!("模型 {} 不在 {} 上，现在拉取吗？[Y/n] ", model, host);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "" | "y" | "yes" | "是"
+    ))
+}
+
+/// 在标准错误上显示拉取进度：同一阶段的进度在一行内刷新，阶段变化时换行。
+#[derive(Default)]
+struct ProgressPrinter {
+    status: String,
+}
+
+impl ProgressPrinter {
+    fn print(&mut self, progress: &PullProgress) {
+        if progress.status != self.status {
+            if !self.status.is_empty() {
+                eprintln!();
+            }
+            self.status = progress.status.clone();
+        }
+        match (progress.percent(), progress.total) {
+            (Some(percent), Some(total)) => eprint!(
+                "\r{}  {:>3}%  ({} / {} MB)",
+                progress.status,
+                percent,
+                progress.completed.unwrap_or(0) / 1_000_000,
+                total / 1_000_000
+            ),
+            _ => eprint!("\r{}", progress.status),
+        }
+        let _ = std::io::stderr().flush();
+    }
+
+    fn finish(&self) {
+        if !self.status.is_empty() {
+            eprintln!();
+        }
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{StubResponse, StubServer};
+
+    #[test]
+    fn test_same_model() {
+        assert!(same_model("llama3.2:latest", "llama3.2"));
+        assert!(same_model("qwen2.5:7b", "qwen2.5:7b"));
+        assert!(!same_model("qwen2.5:7b", "qwen2.5"));
+        assert!(!same_model("llama3.2:latest", "llama3"));
+    }
+
+    #[tokio::test]
+    async fn test_pull_streams_progress() {
+        let server = StubServer::spawn(|request| {
+            if request.path == "/api/tags" {
+                return StubResponse::json(r#"{"models":[{"name":"llama3.2:latest"}]}"#);
+            }
+            StubResponse::with_type(
+                200,
+                "application/x-ndjson",
+                concat!(
+                    "{\"status\":\"pulling manifest\"}\n",
+                    "{\"status\":\"pulling 6a0746a1ec1a\",\"digest\":\"sha256:6a07\",\"total\":2000,\"completed\":500}\n",
+                    "{\"status\":\"pulling 6a0746a1ec1a\",\"digest\":\"sha256:6a07\",\"total\":2000,\"completed\":2000}\n",
+                    "{\"status\":\"verifying sha256 digest\"}\n",
+                    "{\"status\":\"success\"}\n",
+                ),
+            )
+        })
+        .await;
+        let models = OllamaModels::new(&server.url).unwrap();
+
+        assert!(models.is_pulled("llama3.2").await.unwrap());
+        assert!(!models.is_pulled("qwen2.5").await.unwrap());
+
+        let mut seen = Vec::new();
+        models
+            .pull("qwen2.5", |p| seen.push((p.status.clone(), p.percent())))
+            .await
+            .unwrap();
+        assert_eq!(seen.len(), 5);
+        assert_eq!(seen[1], ("pulling 6a0746a1ec1a".to_string(), Some(25)));
+        assert_eq!(seen[4].0, "success");
+
+        let pull = server
+            .requests()
+            .into_iter()
+            .find(|r| r.path == "/api/pull")
+            .unwrap();
+        assert_eq!(pull.method, "POST");
+        assert!(pull.body.contains("\"qwen2.5\""));
+    }
+
+    #[tokio::test]
+    async fn test_pull_reports_errors() {
+        let server = StubServer::spawn(|_| {
+            StubResponse::with_type(
+                200,
+                "application/x-ndjson",
+                "{\"status\":\"pulling manifest\"}\n{\"error\":\"pull model manifest: file does not exist\"}\n",
+            )
+        })
+        .await;
+        let models = OllamaModels::new(&server.url).unwrap();
+
+        let err = models.pull("no-such-model", |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("file does not exist"));
+    }
+}
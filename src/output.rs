@@ -0,0 +1,238 @@
+//! # 输出格式模块
+//!
+//! 默认情况下 CLI 把结果打印在 `=====` 横幅之间，方便人阅读。
+//! 脚本和流水线使用 `--format json` 得到一个机器可读的 JSON 文档：
+//! 查询、模型、耗时、工具调用、搜索结果和报告正文。
+//!
+//! 它演示了：
+//! - 为输出专门定义可序列化的结构体（而不是拼接字符串）
+//! - 从事件日志中提取需要的信息
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, LlmProvider};
+use crate::events::ResearchEvent;
+use crate::report::ResearchReport;
+use crate::search::SearchResult;
+
+// =============================================================================
+// 输出格式
+// =============================================================================
+/// 结果打印到标准输出时使用的格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 给人阅读的文本（横幅 + Markdown 报告）
+    #[default]
+    Text,
+
+    /// 机器可读的 JSON 文档（`RunOutput`）
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => anyhow::bail!("未知的输出格式：{}（可选：text、json）", other),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+// =============================================================================
+// JSON 文档
+// =============================================================================
+/// 一次运行（快速搜索、研究或深度研究）的机器可读结果。
+///
+/// 字段只增不改，这样解析它的流水线不会因为升级而失效。
+#[derive(Debug, Serialize)]
+pub struct RunOutput {
+    /// 原始查询
+    pub query: String,
+
+    /// 运行模式："quick"、"research" 或 "deep"
+    pub mode: &'static str,
+
+    pub provider: LlmProvider,
+    pub model: String,
+    pub timings: Timings,
+
+    /// LLM 发起的工具调用（按发生顺序；快速搜索为空）
+    pub tool_calls: Vec<ToolCall>,
+
+    /// 搜索返回的所有结果（按 URL 去重，保留第一次出现的顺序）
+    pub search_results: Vec<SearchResult>,
+
+    /// 结构化报告（章节、论断、引用核验和来源；快速搜索没有报告）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<ResearchReport>,
+
+    /// Markdown 格式的正文，与文本模式打印的内容相同
+    pub body: String,
+}
+
+/// 运行的起止时间。
+#[derive(Debug, Serialize)]
+pub struct Timings {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub elapsed_ms: i64,
+}
+
+/// 一次工具调用。
+#[derive(Debug, Serialize)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+impl RunOutput {
+    /// 开始记录一次运行；结束时间和耗时在 `finish` 时填写。
+    pub fn new(
+        query: &str,
+        mode: &'static str,
+        config: &Config,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            query: query.to_string(),
+            mode,
+            provider: config.provider,
+            model: config.model.clone(),
+            timings: Timings {
+                started_at,
+                finished_at: started_at,
+                elapsed_ms: 0,
+            },
+            tool_calls: Vec::new(),
+            search_results: Vec::new(),
+            report: None,
+            body: String::new(),
+        }
+    }
+
+    /// 添加搜索结果，跳过已经出现过的 URL。
+    pub fn add_search_results(&mut self, results: impl IntoIterator<Item = SearchResult>) {
+        for result in results {
+            if !self.search_results.iter().any(|r| r.url == result.url) {
+                self.search_results.push(result);
+            }
+        }
+    }
+
+    /// 从研究的事件日志中提取工具调用和搜索结果。
+    pub fn record_events(&mut self, log: &[ResearchEvent]) {
+        for event in log {
+            match event {
+                ResearchEvent::ToolCall { name, arguments } => self.tool_calls.push(ToolCall {
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                }),
+                ResearchEvent::SearchResults { results, .. } => {
+                    self.add_search_results(results.iter().cloned())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// 记录结束时间和耗时。
+    pub fn finish(mut self) -> Self {
+        let finished_at = Utc::now();
+        self.timings.finished_at = finished_at;
+        self.timings.elapsed_ms = (finished_at - self.timings.started_at).num_milliseconds();
+        self
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str) -> SearchResult {
+        SearchResult {
+            title: format!("Title of {}", url),
+            url: url.to_string(),
+            snippet: "snippet".to_string(),
+            engine: None,
+            published: None,
+        }
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert_eq!(
+            " TEXT ".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Text
+        );
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_run_output_json() {
+        let config = Config::default();
+        let mut output = RunOutput::new("rust async", "research", &config, Utc::now());
+        output.record_events(&[
+            ResearchEvent::status("正在研究"),
+            ResearchEvent::ToolCall {
+                name: "web_search".to_string(),
+                arguments: serde_json::json!({ "query": "rust async" }),
+            },
+            ResearchEvent::SearchResults {
+                query: "rust async".to_string(),
+                results: vec![result("https://tokio.rs"), result("https://async.rs")],
+            },
+            ResearchEvent::tool_result("web_search", "## 搜索结果"),
+            ResearchEvent::SearchResults {
+                query: "tokio".to_string(),
+                results: vec![result("https://tokio.rs"), result("https://docs.rs/tokio")],
+            },
+        ]);
+        output.body = "## 概述\nTokio [1]".to_string();
+
+        let json = serde_json::to_value(output.finish()).unwrap();
+        assert_eq!(json["query"], "rust async");
+        assert_eq!(json["mode"], "research");
+        assert_eq!(json["provider"], "ollama");
+        assert_eq!(json["model"], config.model);
+        assert!(json["timings"]["elapsed_ms"].as_i64().unwrap() >= 0);
+        assert_eq!(json["tool_calls"][0]["name"], "web_search");
+        assert_eq!(json["tool_calls"][0]["arguments"]["query"], "rust async");
+
+        let urls: Vec<&str> = json["search_results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["url"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://tokio.rs",
+                "https://async.rs",
+                "https://docs.rs/tokio"
+            ]
+        );
+        assert!(json.get("report").is_none());
+        assert_eq!(json["body"], "## 概述\nTokio [1]");
+    }
+}
@@ -0,0 +1,371 @@
+//! # 交互式研究模块
+//!
+//! 单次查询模式回答完就退出，所有上下文随之丢失。交互模式保持一个会话：
+//! 聊天历史和收集到的来源在各轮之间保留，所以用户可以追问
+//! "展开第 2 点"或"找一些反方观点"，而之前的 `[n]` 编号仍然有效。
+//!
+//! 以 `/` 开头的输入是命令：
+//! - `/sources`：列出会话中收集到的来源
+//! - `/save [文件]`：把最近一次回答保存为 Markdown
+//! - `/model <名称>`：切换模型（保留对话）
+//! - `/quick <查询>`：快速搜索，不调用 LLM，也不进入对话历史
+//! - `/help`、`/exit`
+//!
+//! 每一轮都会保存到会话数据库；`sessions resume <编号>` 可以恢复之前的会话。
+//!
+//! 它演示了：
+//! - 使用 tokio 异步读取标准输入的行
+//! - 把输入解析为枚举，再用 `match` 分派
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::warn;
+
+use crate::agent::{AgentError, Conversation, ResearchAgent};
+use crate::config::Config;
+use crate::events::{self, EventSink, ResearchEvent};
+use crate::models;
+use crate::report::ResearchReport;
+use crate::store::{Session, SessionStore};
+
+/// 每次读取输入前显示的提示符
+const PROMPT: &str = "研究> ";
+
+/// `/help` 显示的帮助文本
+const HELP: &str = r#"
+直接输入问题开始研究，之后的输入都是对同一个对话的追问，例如：
+  展开第 2 点
+  找一些反方观点
+
+命令：
+  /sources          列出本次会话收集到的来源
+  /save [文件]      把最近一次回答保存为 Markdown（默认 research-<时间>.md）
+  /model <名称>     切换模型，保留对话历史和来源
+  /quick <查询>     快速搜索（不综合，不计入对话）
+  /help             显示本帮助
+  /exit, /quit      退出
+"#;
+
+// =============================================================================
+// 输入解析
+// =============================================================================
+/// 用户输入的一行。
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// 空行
+    Empty,
+
+    /// 发给研究代理的消息（新问题或追问）
+    Message(String),
+
+    /// 斜杠命令
+    Command(SlashCommand),
+}
+
+/// 交互模式支持的斜杠命令。
+#[derive(Debug, PartialEq)]
+pub enum SlashCommand {
+    Sources,
+    Save(Option<String>),
+    Model(Option<String>),
+    Quick(String),
+    Help,
+    Exit,
+
+    /// 无法识别的命令或缺少必需参数（保存错误提示）
+    Invalid(String),
+}
+
+/// 把一行输入解析为消息或命令。
+///
+/// # Rust 概念：切片模式
+///
+/// `split_once` 返回 `Option<(&str, &str)>`，配合 `unwrap_or` 可以
+/// 统一处理"命令 + 参数"和"只有命令"两种情况，而不需要分配新字符串。
+pub fn parse_input(line: &str) -> Input {
+    let line = line.trim();
+    if line.is_empty() {
+        return Input::Empty;
+    }
+    let Some(command) = line.strip_prefix('/') else {
+        return Input::Message(line.to_string());
+    };
+
+    let (name, arg) = command
+        .split_once(char::is_whitespace)
+        .map(|(name, arg)| (name, arg.trim()))
+        .unwrap_or((command, ""));
+    let arg = (!arg.is_empty()).then(|| arg.to_string());
+
+    Input::Command(match name {
+        "sources" => SlashCommand::Sources,
+        "save" => SlashCommand::Save(arg),
+        "model" => SlashCommand::Model(arg),
+        "quick" => match arg {
+            Some(query) => SlashCommand::Quick(query),
+            None => SlashCommand::Invalid("用法：/quick <查询>".to_string()),
+        },
+        "help" | "?" => SlashCommand::Help,
+        "exit" | "quit" => SlashCommand::Exit,
+        _ => SlashCommand::Invalid(format!("未知命令：/{}（输入 /help 查看命令）", name)),
+    })
+}
+
+// =============================================================================
+// 交互循环
+// =============================================================================
+/// 运行交互式研究会话，直到用户输入 `/exit` 或关闭标准输入（Ctrl-D）。
+///
+/// - `stream` 为 false 时不实时打印令牌，每轮结束后打印完整报告
+/// - `pull` 为 true（`--pull`）时 `/model` 切换到没有拉取的模型会直接拉取
+/// - `store` 不为 None 时每一轮都保存到会话数据库
+/// - `resume` 不为 None 时从保存的会话继续（保留聊天历史和来源）
+pub async fn run(
+    config: Config,
+    stream: bool,
+    pull: bool,
+    mut store: Option<SessionStore>,
+    resume: Option<Session>,
+) -> Result<()> {
+    let mut agent = ResearchAgent::new(config)?;
+    let mut session_id = resume.as_ref().map(|s| s.summary.id);
+    let mut conversation = resume
+        .as_ref()
+        .map(Session::conversation)
+        .unwrap_or_default();
+    let mut last_report: Option<ResearchReport> =
+        resume.and_then(|s| s.turns.into_iter().last().map(|t| t.report));
+
+    println!(
+        "AI 研究代理交互模式（模型：{}）。输入 /help 查看命令。",
+        agent.config().model
+    );
+    if let Some(id) = session_id {
+        println!(
+            "已恢复会话 #{}（{} 个来源）。",
+            id,
+            conversation.sources.all().len()
+        );
+    }
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("\n{}", PROMPT);
+        std::io::stdout().flush()?;
+
+        let Some(line) = lines.next_line().await.context("读取输入失败")? else {
+            // 标准输入已关闭（Ctrl-D 或管道结束）
+            println!();
+            break;
+        };
+
+        match parse_input(&line) {
+            Input::Empty => {}
+            Input::Message(message) => {
+                // 单轮失败（例如模型超时）不应结束整个会话
+                match ask(&agent, &message, &mut conversation, stream).await {
+                    Ok((report, events)) => {
+                        if let Some(store) = store.as_mut() {
+                            // 保存失败只警告，不影响继续对话
+                            if let Err(e) = save_turn(
+                                store,
+                                &mut session_id,
+                                agent.config(),
+                                &message,
+                                &report,
+                                &events,
+                                &conversation,
+                            ) {
+                                warn!(error = %e, "保存会话失败");
+                            }
+                        }
+                        last_report = Some(report);
+                    }
+                    Err(e) => print_error("研究失败", &e),
+                }
+            }
+            Input::Command(SlashCommand::Sources) => print_sources(&conversation),
+            Input::Command(SlashCommand::Save(path)) => match &last_report {
+                Some(report) => {
+                    let path = path.unwrap_or_else(default_save_path);
+                    match std::fs::write(&path, report.to_markdown()) {
+                        Ok(()) => println!("已保存到 {}", path),
+                        Err(e) => eprintln!("保存失败: {}", e),
+                    }
+                }
+                None => println!("还没有可以保存的回答。"),
+            },
+            Input::Command(SlashCommand::Model(None)) => {
+                println!("当前模型：{}", agent.config().model);
+            }
+            Input::Command(SlashCommand::Model(Some(model))) => {
+                // LLM 客户端在 `ResearchAgent::new` 中按配置构建，换模型要用新配置重建代理；
+                // 对话历史和来源保存在 `conversation` 中，不受影响
+                let config = Config {
+                    model,
+                    ..agent.config().clone()
+                };
+                // 和启动时一样先确认模型已经拉取，而不是等到下一轮才失败
+                if let Err(e) = models::ensure_model(&config, pull).await {
+                    match e.downcast_ref::<AgentError>() {
+                        Some(e) => print_error("切换模型失败", e),
+                        None => eprintln!("\n❌ 切换模型失败: {:#}", e),
+                    }
+                    continue;
+                }
+                match ResearchAgent::new(config) {
+                    Ok(new_agent) => {
+                        println!("已切换到模型：{}", new_agent.config().model);
+                        agent = new_agent;
+                        // 恢复会话时使用新模型
+                        if let (Some(store), Some(id)) = (store.as_ref(), session_id) {
+                            if let Err(e) = store.set_model(id, agent.config()) {
+                                warn!(error = %e, "保存会话的模型失败");
+                            }
+                        }
+                    }
+                    Err(e) => print_error("切换模型失败", &e),
+                }
+            }
+            Input::Command(SlashCommand::Quick(query)) => match agent.quick_search(&query).await {
+                Ok(results) => println!("{}", results),
+                Err(e) => print_error("搜索失败", &e),
+            },
+            Input::Command(SlashCommand::Help) => println!("{}", HELP),
+            Input::Command(SlashCommand::Exit) => break,
+            Input::Command(SlashCommand::Invalid(message)) => println!("{}", message),
+        }
+    }
+
+    Ok(())
+}
+
+/// 执行一轮对话并打印结果，返回报告和这一轮的工具事件。
+async fn ask(
+    agent: &ResearchAgent,
+    message: &str,
+    conversation: &mut Conversation,
+    stream: bool,
+) -> Result<(ResearchReport, Vec<ResearchEvent>), AgentError> {
+    let (sink, receiver) = EventSink::channel();
+    let collector = tokio::spawn(events::collect(receiver, stream));
+
+    let result = agent.follow_up(message, conversation, &sink).await;
+
+    drop(sink);
+    let events = collector.await.unwrap_or_default();
+
+    let report = result?;
+    if stream {
+        println!("\n\n{}", report.stream_footer());
+    } else {
+        println!("\n{}", report.to_markdown());
+    }
+    Ok((report, events))
+}
+
+/// 保存一轮对话；第一轮时创建会话。
+fn save_turn(
+    store: &mut SessionStore,
+    session_id: &mut Option<i64>,
+    config: &Config,
+    message: &str,
+    report: &ResearchReport,
+    events: &[ResearchEvent],
+    conversation: &Conversation,
+) -> Result<()> {
+    let id = match *session_id {
+        Some(id) => id,
+        None => *session_id.insert(store.create("interactive", message, config)?),
+    };
+    store.record_turn(id, message, report, events, conversation)?;
+    Ok(())
+}
+
+/// 打印失败的操作和错误对应的建议；单轮失败不结束会话。
+fn print_error(action: &str, error: &AgentError) {
+    eprintln!("\n❌ {}: {}", action, error);
+    if let Some(hint) = error.hint() {
+        eprintln!("💡 提示：{}", hint);
+    }
+}
+
+/// 打印会话中收集到的所有来源。
+fn print_sources(conversation: &Conversation) {
+    let sources = conversation.sources.all();
+    if sources.is_empty() {
+        println!("还没有收集到来源。");
+        return;
+    }
+    for source in sources {
+        let title = if source.title.is_empty() {
+            &source.url
+        } else {
+            &source.title
+        };
+        println!("[{}] {}\n    {}", source.id, title, source.url);
+    }
+}
+
+/// `/save` 没有给出文件名时使用的默认路径
+fn default_save_path() -> String {
+    format!(
+        "research-{}.md",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    )
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages() {
+        assert_eq!(parse_input("   "), Input::Empty);
+        assert_eq!(
+            parse_input("  展开第 2 点 \n"),
+            Input::Message("展开第 2 点".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse_input("/sources"),
+            Input::Command(SlashCommand::Sources)
+        );
+        assert_eq!(
+            parse_input("/save"),
+            Input::Command(SlashCommand::Save(None))
+        );
+        assert_eq!(
+            parse_input("/save  notes.md "),
+            Input::Command(SlashCommand::Save(Some("notes.md".to_string())))
+        );
+        assert_eq!(
+            parse_input("/model qwen2.5"),
+            Input::Command(SlashCommand::Model(Some("qwen2.5".to_string())))
+        );
+        assert_eq!(
+            parse_input("/quick rust web 框架"),
+            Input::Command(SlashCommand::Quick("rust web 框架".to_string()))
+        );
+        assert_eq!(parse_input("/quit"), Input::Command(SlashCommand::Exit));
+    }
+
+    #[test]
+    fn test_parse_invalid_commands() {
+        assert!(matches!(
+            parse_input("/quick"),
+            Input::Command(SlashCommand::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_input("/frobnicate"),
+            Input::Command(SlashCommand::Invalid(m)) if m.contains("/frobnicate")
+        ));
+    }
+}
@@ -0,0 +1,1018 @@
+//! # 研究报告模块
+//!
+//! LLM 很容易"编造"看起来真实的链接。为了避免这种情况，工具在返回
+//! 搜索结果和页面时，会把每个 URL 登记到 `SourceRegistry` 并给它一个编号，
+//! LLM 在正文中只写 `[n]` 标记。最后我们把 LLM 的回答解析为结构化的
+//! `ResearchReport`，并且只根据登记簿生成参考文献列表——
+//! 所以列表中的每个 URL 都一定是工具真实返回过的。
+//!
+//! 它演示了：
+//! - 使用 `Arc<Mutex<T>>` 在多个工具之间共享可变状态
+//! - 手写的小型解析器（不依赖正则表达式）
+//! - 使用 chrono 记录时间戳
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::verify::{CitationCheck, Verdict};
+
+// =============================================================================
+// 来源登记簿
+// =============================================================================
+/// 工具返回过的一个来源。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    /// 引用编号（在报告中对应 `[id]`）
+    pub id: usize,
+    pub url: String,
+    pub title: String,
+
+    /// 工具获取该来源的时间
+    pub accessed_at: DateTime<Utc>,
+
+    /// 作者、网站名称和发布日期（页面提取或搜索结果提供时才有，用于生成参考文献条目）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+
+    /// 发布日期（保留页面中的原始格式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+}
+
+/// 一次研究中所有工具共享的来源登记簿。
+///
+/// # Rust 概念：Arc<Mutex<T>>
+///
+/// Rig 会克隆工具，而且工具调用是异步的，所以登记簿必须可以
+/// 安全地在多个所有者之间共享并修改。`Arc` 提供共享所有权，
+/// `Mutex` 保证同一时间只有一个调用者修改列表。克隆
+/// `SourceRegistry` 只会增加引用计数，所有克隆看到的是同一个列表。
+#[derive(Debug, Clone, Default)]
+pub struct SourceRegistry {
+    entries: Arc<Mutex<Vec<RegistryEntry>>>,
+}
+
+/// 登记簿中的一项：来源本身和工具返回给 LLM 的文本（用于核验引用）
+#[derive(Debug)]
+struct RegistryEntry {
+    source: Source,
+    content: String,
+}
+
+impl SourceRegistry {
+    /// 创建空的登记簿。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从保存的（来源，文本）列表重建登记簿，之后登记的新来源编号接在后面。
+    pub fn from_entries(entries: Vec<(Source, String)>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(source, content)| RegistryEntry { source, content })
+            .collect();
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+
+    /// 所有来源及工具为它们返回的文本（用于保存会话）。
+    pub fn entries(&self) -> Vec<(Source, String)> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .map(|e| (e.source.clone(), e.content.clone()))
+            .collect()
+    }
+
+    /// 登记一个 URL 并返回它的编号；同一个 URL 总是得到同一个编号。
+    ///
+    /// 如果之前登记时没有标题，这次提供的标题会补上。
+    pub fn register(&self, url: &str, title: &str) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(existing) = entries.iter_mut().find(|e| e.source.url == url) {
+            if existing.source.title.is_empty() {
+                existing.source.title = title.to_string();
+            }
+            return existing.source.id;
+        }
+
+        let id = entries.len() + 1;
+        entries.push(RegistryEntry {
+            source: Source {
+                id,
+                url: url.to_string(),
+                title: title.to_string(),
+                accessed_at: Utc::now(),
+                author: None,
+                site_name: None,
+                published: None,
+            },
+            content: String::new(),
+        });
+        id
+    }
+
+    /// 补充来源的作者、网站名称和发布日期；已有的值不会被覆盖。
+    pub fn add_metadata(
+        &self,
+        id: usize,
+        author: Option<&str>,
+        site_name: Option<&str>,
+        published: Option<&str>,
+    ) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|e| e.source.id == id) {
+            let source = &mut entry.source;
+            for (field, value) in [
+                (&mut source.author, author),
+                (&mut source.site_name, site_name),
+                (&mut source.published, published),
+            ] {
+                if field.is_none() {
+                    *field = value
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                        .map(String::from);
+                }
+            }
+        }
+    }
+
+    /// 记录工具为某个来源返回的文本（搜索片段、页面正文），供引用核验使用。
+    pub fn add_content(&self, id: usize, text: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|e| e.source.id == id) {
+            if !entry.content.contains(text) {
+                if !entry.content.is_empty() {
+                    entry.content.push_str("\n\n");
+                }
+                entry.content.push_str(text);
+            }
+        }
+    }
+
+    /// 按编号查找来源。
+    pub fn get(&self, id: usize) -> Option<Source> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .find(|e| e.source.id == id)
+            .map(|e| e.source.clone())
+    }
+
+    /// 所有已登记的来源（按编号排序）。
+    pub fn all(&self) -> Vec<Source> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().map(|e| e.source.clone()).collect()
+    }
+
+    /// 按 URL 查找工具为该来源返回过的文本。
+    pub fn content_for_url(&self, url: &str) -> Option<String> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .find(|e| e.source.url == url)
+            .map(|e| e.content.clone())
+            .filter(|c| !c.is_empty())
+    }
+}
+
+// =============================================================================
+// 结构化报告
+// =============================================================================
+/// 报告中的一条论断（一个段落或列表项）及其引用的来源编号。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    /// 论断文本，保留有效的 `[n]` 标记
+    pub text: String,
+
+    /// 引用的来源编号（对应 `ResearchReport::sources` 中的 id）
+    pub citations: Vec<usize>,
+
+    /// 每个引用的核验结果（未运行核验时为空）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<CitationCheck>,
+}
+
+/// 报告中的一个章节。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    /// 章节标题（LLM 在第一个标题之前写的内容没有标题）
+    pub heading: Option<String>,
+    pub claims: Vec<Claim>,
+}
+
+/// 结构化的研究报告。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResearchReport {
+    /// 原始研究问题
+    pub query: String,
+    pub sections: Vec<Section>,
+
+    /// 正文中实际引用的来源（按编号排序）
+    ///
+    /// 编号沿用登记簿中的编号而不是重新编号，这样流式输出时已经打印的
+    /// `[n]` 标记与最后的参考文献列表保持一致。
+    pub sources: Vec<Source>,
+}
+
+impl ResearchReport {
+    /// 把 LLM 的 Markdown 回答解析为结构化报告。
+    ///
+    /// - `##` 标题或单独一行的 `**粗体**` 开始新章节
+    /// - 其余每个非空行是一条论断，一个 ```` ``` ```` 代码块整体是一条论断
+    /// - 引用登记簿中存在的编号才是引用；`[1, 99]` 中不存在的编号会被删除，
+    ///   编号都不存在的方括号（例如 `arr[2024]`）保持原样
+    /// - 代码块和行内代码中的方括号不是引用
+    /// - LLM 自己写的"来源/参考文献"章节会被丢弃，由登记簿重新生成
+    pub fn from_response(query: &str, response: &str, registry: &SourceRegistry) -> Self {
+        let mut sections: Vec<Section> = Vec::new();
+        let mut sources: Vec<Source> = Vec::new();
+        let mut skipping = false;
+        // 正在收集的代码块（从开始的 ``` 行起）
+        let mut fence: Option<String> = None;
+
+        for line in response.lines().map(str::trim_end) {
+            if let Some(block) = &mut fence {
+                block.push('\n');
+                block.push_str(line);
+                if is_fence(line) {
+                    push_claim(&mut sections, fence.take().unwrap_or_default(), Vec::new());
+                }
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(heading) = parse_heading(line) {
+                skipping = is_reference_heading(&heading);
+                if !skipping {
+                    sections.push(Section {
+                        heading: Some(heading),
+                        claims: Vec::new(),
+                    });
+                }
+                continue;
+            }
+            if skipping {
+                continue;
+            }
+            if is_fence(line) {
+                fence = Some(line.to_string());
+                continue;
+            }
+
+            let mut citations = Vec::new();
+            let text = rewrite_citations(line, |id| {
+                if !sources.iter().any(|s| s.id == id) {
+                    sources.push(registry.get(id)?);
+                }
+                if !citations.contains(&id) {
+                    citations.push(id);
+                }
+                Some(id)
+            });
+            push_claim(&mut sections, text, citations);
+        }
+        // 没有闭合的代码块保持原样
+        if let Some(block) = fence {
+            push_claim(&mut sections, block, Vec::new());
+        }
+
+        sections.retain(|s| !s.claims.is_empty());
+        sources.sort_by_key(|s| s.id);
+
+        Self {
+            query: query.to_string(),
+            sections,
+            sources,
+        }
+    }
+
+    /// 所有论断（按出现顺序）。
+    pub fn claims(&self) -> impl Iterator<Item = &Claim> {
+        self.sections.iter().flat_map(|s| s.claims.iter())
+    }
+
+    /// 没有任何引用的论断数量
+    pub fn uncited_claims(&self) -> usize {
+        self.claims().filter(|c| c.citations.is_empty()).count()
+    }
+
+    /// 统计核验结果：（支持，较弱，不支持）
+    pub fn verdict_counts(&self) -> (usize, usize, usize) {
+        self.claims()
+            .flat_map(|c| c.checks.iter())
+            .fold((0, 0, 0), |(s, w, u), check| match check.verdict {
+                Verdict::Supported => (s + 1, w, u),
+                Verdict::Weak => (s, w + 1, u),
+                Verdict::Unsupported => (s, w, u + 1),
+            })
+    }
+
+    /// 渲染为 Markdown：正文（带核验标记）加上由登记簿生成的参考文献列表。
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+
+        for section in &self.sections {
+            if let Some(heading) = &section.heading {
+                out.push_str(&format!("## {}\n\n", heading));
+            }
+            let mut previous: Option<&str> = None;
+            for claim in &section.claims {
+                // 连续的列表项之间不空行，其他段落之间空一行
+                if let Some(prev) = previous {
+                    let both_list = is_list_item(prev) && is_list_item(&claim.text);
+                    out.push_str(if both_list { "\n" } else { "\n\n" });
+                }
+                out.push_str(&claim.text);
+                if !claim.checks.is_empty() {
+                    let flags: Vec<String> = claim
+                        .checks
+                        .iter()
+                        .map(|c| format!("[{}] {}", c.source, c.verdict))
+                        .collect();
+                    out.push_str(&format!(" （核验：{}）", flags.join("；")));
+                }
+                previous = Some(&claim.text);
+            }
+            out.push_str("\n\n");
+        }
+
+        out.push_str(&self.verification_summary());
+        out.push_str(&self.references());
+        out.trim_end().to_string()
+    }
+
+    /// 流式输出结束后打印的页脚。
+    ///
+    /// 正文已经以令牌的形式打印过了，所以这里只列出核验结果
+    /// （包括需要复查的论断）和参考文献列表。
+    pub fn stream_footer(&self) -> String {
+        let mut out = self.verification_summary();
+
+        let flagged: Vec<String> = self
+            .claims()
+            .filter_map(|claim| {
+                let flags: Vec<String> = claim
+                    .checks
+                    .iter()
+                    .filter(|c| c.verdict != Verdict::Supported)
+                    .map(|c| format!("[{}] {}", c.source, c.verdict))
+                    .collect();
+                let text = claim.text.trim_start().trim_start_matches(['-', '*']);
+                (!flags.is_empty()).then(|| format!("- {} （{}）", text.trim(), flags.join("；")))
+            })
+            .collect();
+        if !flagged.is_empty() {
+            out.push_str("### 需要复查的论断\n\n");
+            out.push_str(&flagged.join("\n"));
+            out.push_str("\n\n");
+        }
+
+        out.push_str(&self.references());
+        out.trim_end().to_string()
+    }
+
+    /// 渲染为 HTML 片段：正文（带核验标记）加上参考文献列表。
+    ///
+    /// 正文中的 `[n]` 链接到参考文献中的对应条目，http(s) 条目链接到来源 URL。
+    /// 只处理 LLM 回答中常见的 Markdown：标题、段落、列表、粗体、行内代码和代码块。
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+
+        for section in &self.sections {
+            if let Some(heading) = &section.heading {
+                out.push_str(&format!("<h2>{}</h2>\n", escape_html(heading)));
+            }
+            // 当前打开的列表标签（连续的列表项放在同一个列表中）
+            let mut open_list: Option<&str> = None;
+            for claim in &section.claims {
+                let item = split_list_marker(&claim.text);
+                if let Some(tag) = open_list.filter(|&tag| Some(tag) != item.map(|(t, _)| t)) {
+                    out.push_str(&format!("</{}>\n", tag));
+                    open_list = None;
+                }
+                let checks = checks_html(&claim.checks);
+                if let Some(code) = fenced_code(&claim.text) {
+                    out.push_str(&format!("<pre><code>{}</code></pre>\n", escape_html(code)));
+                    continue;
+                }
+                match item {
+                    Some((tag, text)) => {
+                        if open_list.is_none() {
+                            out.push_str(&format!("<{}>\n", tag));
+                            open_list = Some(tag);
+                        }
+                        out.push_str(&format!(
+                            "<li>{}{}</li>\n",
+                            inline_html(text, &self.sources),
+                            checks
+                        ));
+                    }
+                    None => out.push_str(&format!(
+                        "<p>{}{}</p>\n",
+                        inline_html(&claim.text, &self.sources),
+                        checks
+                    )),
+                }
+            }
+            if let Some(tag) = open_list {
+                out.push_str(&format!("</{}>\n", tag));
+            }
+        }
+
+        if self.claims().any(|c| !c.checks.is_empty()) {
+            let (supported, weak, unsupported) = self.verdict_counts();
+            out.push_str(&format!(
+                "<p class=\"verification\">引用核验：{} 支持，{} 较弱，{} 不支持</p>\n",
+                supported, weak, unsupported
+            ));
+        }
+
+        if !self.sources.is_empty() {
+            out.push_str("<section class=\"references\">\n<h2>参考文献</h2>\n<ol>\n");
+            for source in &self.sources {
+                let title = if source.title.is_empty() {
+                    "(无标题)"
+                } else {
+                    &source.title
+                };
+                let url = escape_html(&source.url);
+                // 只有 http(s) 地址才生成链接，`javascript:` 等其他协议按纯文本显示
+                let title = if is_web_url(&source.url) {
+                    format!("<a href=\"{}\">{}</a>", url, escape_html(title))
+                } else {
+                    escape_html(title)
+                };
+                out.push_str(&format!(
+                    "<li id=\"ref-{id}\" value=\"{id}\">{title} \
+                     <span class=\"url\">{url}</span> \
+                     <span class=\"accessed\">访问于 {date}</span></li>\n",
+                    id = source.id,
+                    date = source.accessed_at.format("%Y-%m-%d"),
+                ));
+            }
+            out.push_str("</ol>\n</section>\n");
+        }
+
+        out
+    }
+
+    /// 核验结果统计行（未运行核验时为空）
+    fn verification_summary(&self) -> String {
+        if !self.claims().any(|c| !c.checks.is_empty()) {
+            return String::new();
+        }
+        let (supported, weak, unsupported) = self.verdict_counts();
+        format!(
+            "> 引用核验：{} 支持，{} 较弱，{} 不支持\n\n",
+            supported, weak, unsupported
+        )
+    }
+
+    /// 由登记簿生成的参考文献列表
+    fn references(&self) -> String {
+        if self.sources.is_empty() {
+            return String::new();
+        }
+
+        let mut out = String::from("## 参考文献\n\n");
+        for source in &self.sources {
+            out.push_str(&format!(
+                "[{}] {}. {} （访问于 {}）\n",
+                source.id,
+                if source.title.is_empty() {
+                    "(无标题)"
+                } else {
+                    &source.title
+                },
+                source.url,
+                source.accessed_at.format("%Y-%m-%d")
+            ));
+        }
+        out
+    }
+}
+
+/// 论断后面的核验标记，例如 `（核验：[1] 支持；[2] 较弱）`（没有核验结果时为空）
+fn checks_html(checks: &[CitationCheck]) -> String {
+    if checks.is_empty() {
+        return String::new();
+    }
+    let flags: Vec<String> = checks
+        .iter()
+        .map(|c| {
+            let class = match c.verdict {
+                Verdict::Supported => "supported",
+                Verdict::Weak => "weak",
+                Verdict::Unsupported => "unsupported",
+            };
+            format!(
+                "<span class=\"{}\">[{}] {}</span>",
+                class, c.source, c.verdict
+            )
+        })
+        .collect();
+    format!(
+        " <span class=\"checks\">（核验：{}）</span>",
+        flags.join("；")
+    )
+}
+
+// =============================================================================
+// 解析辅助函数
+// =============================================================================
+/// 识别 Markdown 标题（`# ...`）或单独一行的粗体标题（`**概述**` / `**概述**：`）。
+fn parse_heading(line: &str) -> Option<String> {
+    let line = line.trim();
+
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+        return Some(line[hashes..].trim().trim_matches('*').trim().to_string());
+    }
+
+    let inner = line
+        .trim_end_matches([':', '：'])
+        .strip_prefix("**")?
+        .strip_suffix("**")?;
+    (!inner.is_empty() && !inner.contains("**"))
+        .then(|| inner.trim().trim_end_matches([':', '：']).to_string())
+}
+
+/// LLM 自己写的来源列表章节的标题（小写）
+const REFERENCE_HEADINGS: &[&str] = &[
+    "来源",
+    "参考",
+    "参考文献",
+    "参考资料",
+    "参考来源",
+    "引用",
+    "引用来源",
+    "资料来源",
+    "信息来源",
+    "source",
+    "sources",
+    "reference",
+    "references",
+    "citation",
+    "citations",
+    "bibliography",
+    "works cited",
+];
+
+/// LLM 自己写的来源列表章节（我们会用登记簿替换它）。
+///
+/// 只匹配完整的标题：包含这些词的普通章节（"Open Source 生态"、"引用与借用"）要保留。
+fn is_reference_heading(heading: &str) -> bool {
+    let lower = heading.trim().trim_end_matches([':', '：']).to_lowercase();
+    REFERENCE_HEADINGS.contains(&lower.as_str())
+}
+
+fn is_list_item(text: &str) -> bool {
+    split_list_marker(text).is_some()
+}
+
+/// 拆出列表项的标记，返回 HTML 列表标签（"ul" 或 "ol"）和项目文本。
+fn split_list_marker(text: &str) -> Option<(&'static str, &str)> {
+    let text = text.trim_start();
+    if let Some(rest) = text.strip_prefix("- ").or_else(|| text.strip_prefix("* ")) {
+        return Some(("ul", rest));
+    }
+    text.split_once(". ")
+        .filter(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        .map(|(_, rest)| ("ol", rest))
+}
+
+/// 把一条论断添加到最后一个章节（还没有章节时创建一个没有标题的章节）。
+fn push_claim(sections: &mut Vec<Section>, text: String, citations: Vec<usize>) {
+    if sections.is_empty() {
+        sections.push(Section {
+            heading: None,
+            claims: Vec::new(),
+        });
+    }
+    if let Some(section) = sections.last_mut() {
+        section.claims.push(Claim {
+            text,
+            citations,
+            checks: Vec::new(),
+        });
+    }
+}
+
+/// 是否是代码块的开始或结束行（```` ``` ```` 或 `~~~`）
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// 代码块论断中的代码（去掉首尾的围栏行）；不是代码块时返回 None。
+fn fenced_code(text: &str) -> Option<&str> {
+    if !is_fence(text) {
+        return None;
+    }
+    let (_, body) = text.split_once('\n')?;
+    Some(match body.rsplit_once('\n') {
+        Some((code, last)) if is_fence(last) => code,
+        _ if is_fence(body) => "",
+        _ => body,
+    })
+}
+
+/// 找出文本中的引用标记（`[1]`、`[1, 2]`、`[1][3]`），并用 `map` 改写每个编号。
+///
+/// `map` 返回 None 的编号会被删除；如果一个标记中的编号都返回 None，
+/// 它就不是引用（例如 `arr[2024]`），保持原样。行内代码、不是数字的方括号
+/// （例如 `[链接](url)` 或 `[注意]`）也保持不变。
+pub fn rewrite_citations(text: &str, mut map: impl FnMut(usize) -> Option<usize>) -> String {
+    replace_citations(text, |ids| {
+        let mapped: Vec<String> = ids
+            .into_iter()
+            .filter_map(&mut map)
+            .map(|id| id.to_string())
+            .collect();
+        (!mapped.is_empty()).then(|| format!("[{}]", mapped.join(", ")))
+    })
+}
+
+/// 删除文本中所有的引用标记（连同它们前面的空格）。
+pub fn strip_citations(text: &str) -> String {
+    replace_citations(text, |_| Some(String::new()))
+}
+
+/// 把文本中的每个引用标记替换为 `render` 的返回值，跳过行内代码。
+///
+/// `render` 返回 None 时标记保持原样；返回空字符串时，标记前面的空格也一起删除。
+fn replace_citations(text: &str, mut render: impl FnMut(Vec<usize>) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    // 成对的反引号之间是行内代码，原样保留
+    while let Some(start) = rest.find('`') {
+        let Some(len) = rest[start + 1..].find('`') else {
+            break;
+        };
+        replace_in_prose(&mut out, &rest[..start], &mut render);
+        let end = start + 1 + len + 1;
+        out.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    replace_in_prose(&mut out, rest, &mut render);
+    out
+}
+
+/// 替换一段（不含行内代码的）文本中的引用标记，结果追加到 `out`。
+fn replace_in_prose(
+    out: &mut String,
+    text: &str,
+    render: &mut impl FnMut(Vec<usize>) -> Option<String>,
+) {
+    let mut rest = text;
+
+    while let Some(start) = rest.find('[') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let ids = after.find(']').and_then(|end| {
+            let inner = &after[..end];
+            let ids: Option<Vec<usize>> = inner
+                .split(',')
+                .map(|part| part.trim().parse::<usize>().ok())
+                .collect();
+            // `[1](url)` 是 Markdown 链接，不是引用
+            let is_link = after[end + 1..].starts_with('(');
+            ids.filter(|ids| !ids.is_empty() && !is_link)
+                .map(|ids| (ids, end))
+        });
+
+        match ids.and_then(|(ids, end)| Some((render(ids)?, end))) {
+            Some((rendered, end)) => {
+                if rendered.is_empty() {
+                    let trimmed = out.trim_end().len();
+                    out.truncate(trimmed);
+                } else {
+                    out.push_str(&rendered);
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('[');
+                rest = after;
+            }
+        }
+    }
+
+    out.push_str(rest);
+}
+
+// =============================================================================
+// HTML 渲染辅助函数
+// =============================================================================
+/// 转义 HTML 特殊字符。
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 把一行 Markdown 文本渲染为 HTML：转义后先切出成对的 `` `代码` ``，
+/// 代码之外的部分再把 `[n]` 链接到 `sources` 中的参考文献、处理 `**粗体**`。
+fn inline_html(text: &str, sources: &[Source]) -> String {
+    let escaped = escape_html(text);
+    let parts: Vec<&str> = escaped.split('`').collect();
+    if parts.len() < 3 || parts.len().is_multiple_of(2) {
+        return prose_html(&escaped, sources);
+    }
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", part)
+            } else {
+                prose_html(part, sources)
+            }
+        })
+        .collect()
+}
+
+/// 渲染代码之外的（已转义的）文本：`[n]` 链接到参考文献，处理 `**粗体**`。
+/// 编号不在参考文献中的方括号不是引用，保持原样。
+fn prose_html(text: &str, sources: &[Source]) -> String {
+    let linked = replace_citations(text, |ids| {
+        if !ids.iter().all(|id| sources.iter().any(|s| s.id == *id)) {
+            return None;
+        }
+        let links: Vec<String> = ids
+            .iter()
+            .map(|id| format!("<a href=\"#ref-{}\">{}</a>", id, id))
+            .collect();
+        Some(format!("[{}]", links.join(", ")))
+    });
+    wrap_pairs(&linked, "**", "strong")
+}
+
+/// 把成对出现的 `marker` 之间的文本包装在 `<tag>` 中；不成对时保持原样。
+fn wrap_pairs(text: &str, marker: &str, tag: &str) -> String {
+    let parts: Vec<&str> = text.split(marker).collect();
+    if parts.len() < 3 || parts.len().is_multiple_of(2) {
+        return text.to_string();
+    }
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<{}>{}</{}>", tag, part, tag)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect()
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SourceRegistry {
+        let registry = SourceRegistry::new();
+        registry.register("https://tokio.rs", "Tokio");
+        registry.register("https://async.rs", "async-std");
+        registry.register("https://smol.rs", "smol");
+        registry
+    }
+
+    #[test]
+    fn test_registry_dedupes_urls() {
+        let registry = SourceRegistry::new();
+        assert_eq!(registry.register("https://a.com", ""), 1);
+        assert_eq!(registry.register("https://b.com", "B"), 2);
+        assert_eq!(registry.register("https://a.com", "A"), 1);
+
+        assert_eq!(registry.get(1).unwrap().title, "A");
+        assert!(registry.get(3).is_none());
+
+        // 文本按来源累积，重复的文本不会再次追加
+        assert_eq!(registry.content_for_url("https://a.com"), None);
+        registry.add_content(1, "snippet");
+        registry.add_content(1, "page body");
+        registry.add_content(1, "snippet");
+        assert_eq!(
+            registry.content_for_url("https://a.com").as_deref(),
+            Some("snippet\n\npage body")
+        );
+
+        // 元数据只补充缺失的字段
+        registry.add_metadata(1, None, Some("A Site"), Some("2024-03-01"));
+        registry.add_metadata(1, Some(" Jane Doe "), Some("Other"), Some(""));
+        let source = registry.get(1).unwrap();
+        assert_eq!(source.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(source.site_name.as_deref(), Some("A Site"));
+        assert_eq!(source.published.as_deref(), Some("2024-03-01"));
+
+        // 克隆共享同一个列表
+        let clone = registry.clone();
+        assert_eq!(clone.register("https://c.com", "C"), 3);
+        assert_eq!(registry.get(3).unwrap().url, "https://c.com");
+        assert_eq!(registry.all().len(), 3);
+    }
+
+    #[test]
+    fn test_rewrite_citations() {
+        let keep = |id: usize| (id < 10).then_some(id * 10);
+        assert_eq!(
+            rewrite_citations("A [1] B [2, 3].", keep),
+            "A [10] B [20, 30]."
+        );
+        assert_eq!(rewrite_citations("A [1][2]", keep), "A [10][20]");
+        assert_eq!(rewrite_citations("A [42].", keep), "A [42].");
+        assert_eq!(rewrite_citations("A [1, 42]", keep), "A [10]");
+        assert_eq!(
+            rewrite_citations(
+                "[注意] 见 [文档](https://x.com) 和 [1](https://y.com)",
+                keep
+            ),
+            "[注意] 见 [文档](https://x.com) 和 [1](https://y.com)"
+        );
+        assert_eq!(rewrite_citations("未闭合 [1", keep), "未闭合 [1");
+        // 行内代码中的方括号不是引用
+        assert_eq!(
+            rewrite_citations("`v[0]` 和 `a[1]` [1]", keep),
+            "`v[0]` 和 `a[1]` [10]"
+        );
+        assert_eq!(strip_citations("A `v[1]` [1, 2]。"), "A `v[1]`。");
+    }
+
+    #[test]
+    fn test_keeps_code_and_unregistered_brackets() {
+        let response = "\
+## 用法
+用 `v[0]` 取第一个元素，`arr[2024]` 越界时会 panic [1]。
+arr[2024] 不是引用 [99]。
+
+```rust
+let x = v[0]; // [1]
+
+## 不是标题
+```
+";
+        let report = ResearchReport::from_response("q", response, &registry());
+
+        assert_eq!(report.sections.len(), 1);
+        let claims = &report.sections[0].claims;
+        assert_eq!(
+            claims[0].text,
+            "用 `v[0]` 取第一个元素，`arr[2024]` 越界时会 panic [1]。"
+        );
+        assert_eq!(claims[0].citations, [1]);
+        assert_eq!(claims[1].text, "arr[2024] 不是引用 [99]。");
+        assert!(claims[1].citations.is_empty());
+        assert_eq!(
+            claims[2].text,
+            "```rust\nlet x = v[0]; // [1]\n\n## 不是标题\n```"
+        );
+        assert!(claims[2].citations.is_empty());
+        assert!(report
+            .to_markdown()
+            .contains("```rust\nlet x = v[0]; // [1]\n\n## 不是标题\n```"));
+
+        let html = report.to_html();
+        assert!(html.contains("<code>v[0]</code>"));
+        assert!(html.contains("<p>arr[2024] 不是引用 [99]。</p>"));
+        assert!(html.contains("<pre><code>let x = v[0]; // [1]\n\n## 不是标题</code></pre>"));
+    }
+
+    #[test]
+    fn test_parse_heading() {
+        assert_eq!(parse_heading("## 概述").as_deref(), Some("概述"));
+        assert_eq!(parse_heading("**摘要**：").as_deref(), Some("摘要"));
+        assert_eq!(parse_heading("**摘要:**").as_deref(), Some("摘要"));
+        assert_eq!(parse_heading("**粗体**开头的普通句子"), None);
+        assert_eq!(parse_heading("#hashtag"), None);
+    }
+
+    #[test]
+    fn test_report_from_response() {
+        let response = "\
+## 概述
+async-std 提供与标准库相似的 API [2]。
+
+## 详细发现
+- smol 很轻量 [3]
+- 它的 API 很小 [3, 2]
+- 虚构的论断 [9]
+
+## 来源
+- https://made-up.example.com
+";
+        let report = ResearchReport::from_response("运行时", response, &registry());
+
+        assert_eq!(report.sections.len(), 2);
+        assert_eq!(report.sections[0].heading.as_deref(), Some("概述"));
+        assert_eq!(
+            report.sections[0].claims[0],
+            Claim {
+                text: "async-std 提供与标准库相似的 API [2]。".to_string(),
+                citations: vec![2],
+                checks: Vec::new(),
+            }
+        );
+
+        // 参考文献只包含正文引用过且登记过的 URL
+        let urls: Vec<_> = report.sources.iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://async.rs", "https://smol.rs"]);
+        assert_eq!(report.sections[1].claims[1].citations, vec![3, 2]);
+        // 没有登记的编号不是引用，原样保留
+        assert_eq!(report.sections[1].claims[2].text, "- 虚构的论断 [9]");
+        assert!(report.sections[1].claims[2].citations.is_empty());
+        assert_eq!(report.uncited_claims(), 1);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("- smol 很轻量 [3]\n- 它的 API 很小 [3, 2]"));
+        assert!(markdown.contains("## 参考文献\n\n[2] async-std. https://async.rs"));
+        assert!(markdown.contains("[3] smol. https://smol.rs"));
+        assert!(!markdown.contains("made-up"));
+
+        // 页脚只包含参考文献（没有运行核验）
+        assert!(report.stream_footer().starts_with("## 参考文献"));
+    }
+
+    #[test]
+    fn test_keeps_sections_that_mention_references() {
+        let response = "\
+## 引用与借用
+借用检查器保证引用总是有效 [1]。
+
+## Open Source 生态
+crates.io 上有大量库 [2]。
+
+## Sources:
+- https://made-up.example.com
+";
+        let report = ResearchReport::from_response("Rust", response, &registry());
+
+        let headings: Vec<_> = report
+            .sections
+            .iter()
+            .map(|s| s.heading.as_deref().unwrap())
+            .collect();
+        assert_eq!(headings, ["引用与借用", "Open Source 生态"]);
+        assert!(!report.to_markdown().contains("made-up"));
+        assert!(is_reference_heading("参考文献"));
+        assert!(!is_reference_heading("数据来源分析"));
+        assert!(!is_reference_heading("Resources"));
+    }
+
+    #[test]
+    fn test_html_links_and_code() {
+        let registry = SourceRegistry::new();
+        registry.register("https://tokio.rs", "Tokio");
+        registry.register("javascript:alert(1)", "点我");
+        let response = "用 `a**b**c [1]` 计算 **乘积** [1, 2]";
+        let html = ResearchReport::from_response("q", response, &registry).to_html();
+
+        // 代码中的 `**` 和 `[n]` 保持原样
+        assert!(html.contains(
+            "用 <code>a**b**c [1]</code> 计算 <strong>乘积</strong> \
+             [<a href=\"#ref-1\">1</a>, <a href=\"#ref-2\">2</a>]"
+        ));
+        assert!(html.contains("<a href=\"https://tokio.rs\">Tokio</a>"));
+        // 非 http(s) 来源只显示为文本
+        assert!(!html.contains("href=\"javascript:"));
+        assert!(html.contains("value=\"2\">点我 <span class=\"url\">javascript:alert(1)</span>"));
+    }
+
+    #[test]
+    fn test_report_without_headings() {
+        let report = ResearchReport::from_response("q", "只有一段文字 [1]", &registry());
+        assert_eq!(report.sections.len(), 1);
+        assert_eq!(report.sections[0].heading, None);
+        assert_eq!(report.sources.len(), 1);
+        assert!(report.to_markdown().starts_with("只有一段文字 [1]"));
+    }
+}
@@ -0,0 +1,332 @@
+//! # DuckDuckGo 搜索提供商
+//!
+//! 通过抓取 DuckDuckGo 的 HTML 版本执行免费搜索，无需 API 密钥。
+
+use async_trait::async_trait;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::debug;
+
+use super::{SearchError, SearchOptions, SearchProvider, SearchResult};
+
+/// 使用 DuckDuckGo HTML 页面的搜索提供商。
+#[derive(Debug, Clone)]
+pub struct DuckDuckGoProvider {
+    /// 单次搜索请求的超时时间
+    timeout: Duration,
+}
+
+impl DuckDuckGoProvider {
+    /// 创建新的 DuckDuckGo 提供商（超时 30 秒）。
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// 设置搜索请求的超时时间。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for DuckDuckGoProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SearchProvider for DuckDuckGoProvider {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    /// 通过 HTML 抓取执行 DuckDuckGo 搜索。
+    ///
+    /// 注意：我们使用 HTML 抓取，因为 DuckDuckGo 没有免费的网络搜索 API。
+    /// duckduckgo_search 库的 API 返回空结果。
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        // 限速：在发出请求之前等待一下
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+            .build()?;
+
+        let url = format!(
+            "https://html.duckduckgo.com/html/?q={}",
+            urlencoding::encode(query)
+        );
+
+        debug!(url = %url, "Fetching search results");
+
+        let response = client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Err(SearchError::RateLimited);
+            }
+            return Err(SearchError::SearchFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        let body = response.text().await?;
+        Ok(parse_html(&body, options.max_results))
+    }
+}
+
+/// 解析 DuckDuckGo HTML 以提取结果。
+///
+/// # Rust 概念：CSS 选择器
+///
+/// 我们用 scraper 把 HTML 解析成 DOM 树，然后用 CSS 选择器找到每个结果块：
+/// - `.result__a`：结果标题链接（href 指向 DuckDuckGo 重定向 URL）
+/// - `.result__snippet`：摘要片段
+/// - `.result__url`：显示的 URL（当标题链接缺失时作为后备）
+///
+/// 广告（`.result--ad`）和重复的 URL 会被跳过。
+fn parse_html(html: &str, max_results: usize) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+
+    // 这些选择器是常量字符串，解析不会失败
+    let result_selector = Selector::parse("div.result").expect("valid selector");
+    let title_selector = Selector::parse("a.result__a").expect("valid selector");
+    let snippet_selector = Selector::parse(".result__snippet").expect("valid selector");
+    let url_selector = Selector::parse("a.result__url").expect("valid selector");
+
+    let mut results = Vec::new();
+    let mut seen_urls = HashSet::new();
+
+    for block in document.select(&result_selector) {
+        if results.len() >= max_results {
+            break;
+        }
+
+        // 跳过赞助商结果
+        if block.value().classes().any(|c| c == "result--ad") {
+            continue;
+        }
+
+        let title_link = block.select(&title_selector).next();
+        let url_link = block.select(&url_selector).next();
+
+        // 优先使用标题链接，其次是显示 URL 的链接，最后是显示的 URL 文本
+        let url = title_link
+            .and_then(|a| a.value().attr("href"))
+            .and_then(resolve_href)
+            .or_else(|| {
+                url_link
+                    .and_then(|a| a.value().attr("href"))
+                    .and_then(resolve_href)
+            })
+            .or_else(|| url_link.and_then(|a| resolve_href(&collapse_text(a.text()))));
+
+        let Some(url) = url else {
+            continue;
+        };
+        if !seen_urls.insert(url.clone()) {
+            continue;
+        }
+
+        let title = title_link
+            .map(|a| collapse_text(a.text()))
+            .filter(|t| !t.is_empty())
+            .or_else(|| extract_domain(&url))
+            .unwrap_or_else(|| "Result".to_string());
+
+        let snippet = block
+            .select(&snippet_selector)
+            .next()
+            .map(|e| collapse_text(e.text()))
+            .unwrap_or_default();
+
+        results.push(SearchResult {
+            title,
+            url,
+            snippet,
+            engine: None,
+            published: None,
+        });
+    }
+
+    results
+}
+
+/// 把 DuckDuckGo 的链接解析为目标 URL。
+///
+/// DuckDuckGo 通常把结果包装成 `//duckduckgo.com/l/?uddg=<编码后的 URL>`，
+/// 有时也直接给出目标 URL。指向 DuckDuckGo 自身的链接（例如广告的
+/// `y.js`）返回 `None`。
+fn resolve_href(href: &str) -> Option<String> {
+    let href = href.trim();
+    let absolute = if href.starts_with("//") {
+        format!("https:{}", href)
+    } else if href.starts_with("http://") || href.starts_with("https://") {
+        href.to_string()
+    } else if !href.is_empty() && !href.contains(char::is_whitespace) && href.contains('.') {
+        // 显示的 URL 文本没有协议，例如 "tokio.rs/tokio/tutorial"
+        format!("https://{}", href)
+    } else {
+        return None;
+    };
+
+    let parsed = Url::parse(&absolute).ok()?;
+    let host = parsed.host_str()?;
+
+    if host == "duckduckgo.com" || host.ends_with(".duckduckgo.com") {
+        if parsed.path() != "/l/" {
+            return None;
+        }
+        let target = parsed
+            .query_pairs()
+            .find(|(key, _)| key == "uddg")
+            .map(|(_, value)| value.into_owned())?;
+        return target.starts_with("http").then_some(target);
+    }
+
+    Some(absolute)
+}
+
+/// 把元素的文本节点合并为单行，折叠多余空白。
+fn collapse_text<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 从 URL 中提取域名。
+fn extract_domain(url: &str) -> Option<String> {
+    url.split("//")
+        .nth(1)?
+        .split('/')
+        .next()
+        .map(|s| s.to_string())
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_domain() {
+        assert_eq!(
+            extract_domain("https://www.example.com/page"),
+            Some("www.example.com".to_string())
+        );
+        assert_eq!(
+            extract_domain("https://rust-lang.org/learn"),
+            Some("rust-lang.org".to_string())
+        );
+    }
+
+    const RESULTS_HTML: &str = include_str!("../../tests/fixtures/duckduckgo/results.html");
+    const NO_RESULTS_HTML: &str = include_str!("../../tests/fixtures/duckduckgo/no_results.html");
+    const DIRECT_LINKS_HTML: &str =
+        include_str!("../../tests/fixtures/duckduckgo/direct_links.html");
+
+    #[test]
+    fn test_parse_html_extracts_titles_and_snippets() {
+        let results = parse_html(RESULTS_HTML, 10);
+
+        // 广告和重复的 URL 被跳过
+        assert_eq!(results.len(), 4);
+
+        assert_eq!(results[0].url, "https://tokio.rs/tokio/tutorial");
+        assert_eq!(
+            results[0].title,
+            "Tutorial | Tokio - An asynchronous Rust runtime"
+        );
+        assert!(results[0]
+            .snippet
+            .starts_with("Tokio is an asynchronous runtime for the Rust programming language."));
+
+        // HTML 实体被解码
+        assert_eq!(
+            results[1].url,
+            "https://rust-lang.github.io/async-book/01_getting_started/02_why_async.html"
+        );
+        assert!(results[1]
+            .snippet
+            .contains("Let's look at the trade-offs & alternatives."));
+
+        assert_eq!(results[2].url, "https://github.com/smol-rs/smol");
+        assert_eq!(
+            results[2].title,
+            "GitHub - smol-rs/smol: A small and fast async runtime for Rust"
+        );
+
+        // 没有摘要片段的结果保留空字符串
+        assert_eq!(results[3].title, "async_std - Rust");
+        assert_eq!(results[3].snippet, "");
+    }
+
+    #[test]
+    fn test_parse_html_respects_max_results() {
+        let results = parse_html(RESULTS_HTML, 2);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://tokio.rs/tokio/tutorial");
+    }
+
+    #[test]
+    fn test_parse_html_no_results_page() {
+        assert!(parse_html(NO_RESULTS_HTML, 10).is_empty());
+    }
+
+    #[test]
+    fn test_parse_html_direct_links_and_url_fallback() {
+        let results = parse_html(DIRECT_LINKS_HTML, 10);
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(
+            results[0].url,
+            "https://component-model.bytecodealliance.org/"
+        );
+        assert_eq!(
+            results[0].title,
+            "Introduction - The WebAssembly Component Model"
+        );
+
+        // 标题链接为空时回退到 result__url 链接
+        assert_eq!(
+            results[1].url,
+            "https://github.com/WebAssembly/component-model"
+        );
+        assert_eq!(results[1].title, "Broken result without a link");
+        assert_eq!(
+            results[1].snippet,
+            "Repository for the component model specification."
+        );
+    }
+
+    #[test]
+    fn test_resolve_href() {
+        assert_eq!(
+            resolve_href("//duckduckgo.com/l/?uddg=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&rut=x"),
+            Some("https://example.com/a?b=1".to_string())
+        );
+        assert_eq!(
+            resolve_href("https://duckduckgo.com/y.js?ad_domain=example.com"),
+            None
+        );
+        assert_eq!(
+            resolve_href("tokio.rs/tokio/tutorial"),
+            Some("https://tokio.rs/tokio/tutorial".to_string())
+        );
+        assert_eq!(resolve_href(""), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! # 搜索模块
+//!
+//! 本模块定义了可插拔的搜索后端抽象。
+//! 它演示了几个重要的 Rust 模式：
+//! - 使用 async-trait 的异步特征
+//! - 特征对象（`Arc<dyn Trait>`）用于运行时多态
+//! - 根据配置选择实现的工厂函数
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::{Config, SearchBackend};
+
+mod duckduckgo;
+mod searxng;
+
+pub use duckduckgo::DuckDuckGoProvider;
+pub use searxng::SearxngProvider;
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// # Rust 概念：使用 thiserror 的自定义错误类型
+///
+/// thiserror 是一个派生宏，使创建自定义错误类型变得容易。
+/// 每个变体代表可能发生的不同种类的错误。
+/// #[error("...")] 属性定义了错误消息。
+///
+/// 这比使用字符串更好，因为：
+/// 1. 编译器检查我们是否处理了所有错误情况
+/// 2. 我们可以匹配特定的错误类型
+/// 3. 错误是自文档化的
+///
+/// 注意：对于 Rig 的 Tool 特征，我们的错误必须实现 std::error::Error，
+/// thiserror 通过派生宏自动提供这个。
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("执行网络搜索失败: {0}")]
+    SearchFailed(String),
+
+    #[error("被搜索提供商限速，请等待")]
+    RateLimited,
+
+    #[allow(dead_code)] // 可能在未来的增强中使用
+    #[error("未找到查询结果: {0}")]
+    NoResults(String),
+
+    #[error("网络错误: {0}")]
+    NetworkError(#[from] reqwest::Error),
+}
+
+// =============================================================================
+// 搜索结果结构体
+// =============================================================================
+/// 表示来自网络的一个搜索结果。
+///
+/// # Rust 概念：序列化的派生宏
+///
+/// - Serialize：将结构体转换为 JSON（或其他格式）
+/// - Deserialize：将 JSON 解析为结构体
+/// - Clone：创建深拷贝
+/// - Debug：使用 {:?} 漂亮地打印
+/// - PartialEq：使用 == 比较（研究事件中携带搜索结果，事件需要比较）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// 搜索结果的标题
+    pub title: String,
+
+    /// 结果的 URL
+    pub url: String,
+
+    /// 内容的片段/描述
+    pub snippet: String,
+
+    /// 产生此结果的上游搜索引擎（如果后端提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+
+    /// 内容的发布日期（如果后端提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+}
+
+// =============================================================================
+// 搜索选项
+// =============================================================================
+/// 单次搜索请求的选项。
+///
+/// 与提供商相关的设置（例如实例 URL）放在提供商自身中，
+/// 这里只保留每次查询都可能不同的参数。
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// 返回的最大结果数
+    pub max_results: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self { max_results: 5 }
+    }
+}
+
+// =============================================================================
+// 搜索提供商特征
+// =============================================================================
+/// 搜索后端的通用接口。
+///
+/// # Rust 概念：async-trait
+///
+/// Rust 原生的 `async fn` 特征方法还不能用于特征对象（`dyn Trait`）。
+/// `#[async_trait]` 宏把异步方法改写为返回 `Pin<Box<dyn Future>>`，
+/// 这样我们就可以在运行时通过 `Arc<dyn SearchProvider>` 选择后端。
+///
+/// `Send + Sync` 约束让提供商可以在 tokio 的多线程运行时中共享。
+#[async_trait]
+pub trait SearchProvider: Send + Sync + std::fmt::Debug {
+    /// 提供商的名称（用于日志和工具描述）
+    fn name(&self) -> &'static str;
+
+    /// 执行搜索并返回结果。
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, SearchError>;
+}
+
+/// 根据配置创建搜索提供商。
+///
+/// 这是代理选择后端的唯一入口：新增后端只需要在这里添加一个分支，
+/// 代理和工具的代码都不需要改动。
+pub fn provider_from_config(config: &Config) -> Arc<dyn SearchProvider> {
+    let timeout = Duration::from_secs(config.search_timeout_secs);
+    match config.search_provider {
+        SearchBackend::DuckDuckGo => Arc::new(DuckDuckGoProvider::new().with_timeout(timeout)),
+        SearchBackend::Searxng => Arc::new(
+            SearxngProvider::new(&config.searxng_url, config.searxng_categories.clone())
+                .with_timeout(timeout),
+        ),
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_result_serialization() {
+        let result = SearchResult {
+            title: "Test".to_string(),
+            url: "https://test.com".to_string(),
+            snippet: "A test result".to_string(),
+            engine: None,
+            published: None,
+        };
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("Test"));
+        // 缺失的可选字段不会出现在 JSON 中
+        assert!(!json.contains("engine"));
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        let config = Config::default();
+        let provider = provider_from_config(&config);
+        assert_eq!(provider.name(), "duckduckgo");
+
+        let config = Config {
+            search_provider: SearchBackend::Searxng,
+            ..Default::default()
+        };
+        assert_eq!(provider_from_config(&config).name(), "searxng");
+    }
+}
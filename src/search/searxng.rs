@@ -0,0 +1,237 @@
+//! # SearXNG 搜索提供商
+//!
+//! 查询自托管 SearXNG 实例的 JSON API（`/search?format=json`）。
+//! 实例必须在 `settings.yml` 的 `search.formats` 中启用 `json`。
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::debug;
+
+use super::{SearchError, SearchOptions, SearchProvider, SearchResult};
+
+/// 使用自托管 SearXNG 实例的搜索提供商。
+#[derive(Debug, Clone)]
+pub struct SearxngProvider {
+    /// 实例的基础 URL，例如 `http://localhost:8888`
+    base_url: String,
+
+    /// 要搜索的类别（例如 `general`、`news`、`science`）
+    categories: Vec<String>,
+
+    /// 单次搜索请求的超时时间
+    timeout: Duration,
+}
+
+impl SearxngProvider {
+    /// 为给定实例和类别创建新的 SearXNG 提供商（超时 30 秒）。
+    pub fn new(base_url: impl Into<String>, categories: Vec<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            categories,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// 设置搜索请求的超时时间。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 构建搜索请求的 URL
+    fn search_url(&self, query: &str) -> String {
+        let mut url = format!(
+            "{}/search?q={}&format=json",
+            self.base_url,
+            urlencoding::encode(query)
+        );
+        if !self.categories.is_empty() {
+            url.push_str("&categories=");
+            url.push_str(&urlencoding::encode(&self.categories.join(",")));
+        }
+        url
+    }
+}
+
+// =============================================================================
+// API 响应结构体
+// =============================================================================
+/// SearXNG JSON 响应中我们关心的部分
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+/// 单个 SearXNG 结果
+///
+/// # Rust 概念：serde 属性
+///
+/// `#[serde(default)]` 在字段缺失时使用默认值，
+/// `#[serde(rename = "...")]` 把 JSON 中的驼峰命名映射到 Rust 的蛇形命名。
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    url: String,
+
+    #[serde(default)]
+    title: String,
+
+    #[serde(default)]
+    content: Option<String>,
+
+    #[serde(default)]
+    engine: Option<String>,
+
+    #[serde(default, rename = "publishedDate")]
+    published_date: Option<String>,
+}
+
+impl From<SearxngResult> for SearchResult {
+    fn from(r: SearxngResult) -> Self {
+        Self {
+            title: r.title.trim().to_string(),
+            url: r.url,
+            snippet: r.content.unwrap_or_default().trim().to_string(),
+            engine: r.engine,
+            published: r.published_date.filter(|d| !d.is_empty()),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+
+        let url = self.search_url(query);
+        debug!(url = %url, "Fetching SearXNG results");
+
+        let response = client.get(&url).send().await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(SearchError::RateLimited);
+        }
+        if status == reqwest::StatusCode::FORBIDDEN {
+            // SearXNG 在未启用 JSON 格式时返回 403
+            return Err(SearchError::SearchFailed(
+                "SearXNG 返回 403，请在 settings.yml 的 search.formats 中启用 json".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(SearchError::SearchFailed(format!("HTTP {}", status)));
+        }
+
+        let body: SearxngResponse = response
+            .json()
+            .await
+            .map_err(|e| SearchError::SearchFailed(format!("无法解析 SearXNG 响应: {}", e)))?;
+
+        Ok(body
+            .results
+            .into_iter()
+            .filter(|r| r.url.starts_with("http"))
+            .take(options.max_results)
+            .map(SearchResult::from)
+            .collect())
+    }
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{StubResponse, StubServer};
+
+    const SAMPLE_RESPONSE: &str = r#"{
+        "query": "rust async",
+        "number_of_results": 0,
+        "results": [
+            {
+                "url": "https://blog.rust-lang.org/2024/async",
+                "title": "Async Rust in 2024 ",
+                "content": "An update on the async working group.",
+                "engine": "duckduckgo",
+                "engines": ["duckduckgo", "brave"],
+                "publishedDate": "2024-03-01T00:00:00",
+                "category": "general"
+            },
+            {
+                "url": "https://tokio.rs/",
+                "title": "Tokio",
+                "content": null,
+                "engine": "google",
+                "publishedDate": null
+            },
+            {
+                "url": "https://example.com/third",
+                "title": "Third"
+            }
+        ],
+        "answers": [],
+        "suggestions": []
+    }"#;
+
+    #[test]
+    fn test_search_url_includes_format_and_categories() {
+        let provider = SearxngProvider::new(
+            "http://localhost:8888/",
+            vec!["general".to_string(), "news".to_string()],
+        );
+        assert_eq!(
+            provider.search_url("rust async"),
+            "http://localhost:8888/search?q=rust%20async&format=json&categories=general%2Cnews"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_maps_results() {
+        let server = StubServer::spawn(|_| StubResponse::json(SAMPLE_RESPONSE)).await;
+        let provider = SearxngProvider::new(&server.url, vec!["general".to_string()]);
+
+        let results = provider
+            .search("rust async", &SearchOptions { max_results: 2 })
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "Async Rust in 2024");
+        assert_eq!(results[0].snippet, "An update on the async working group.");
+        assert_eq!(results[0].engine.as_deref(), Some("duckduckgo"));
+        assert_eq!(results[0].published.as_deref(), Some("2024-03-01T00:00:00"));
+        assert_eq!(results[1].snippet, "");
+        assert!(results[1].published.is_none());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+        assert!(requests[0].body.is_empty());
+        assert!(requests[0]
+            .path
+            .starts_with("/search?q=rust%20async&format=json"));
+    }
+
+    #[tokio::test]
+    async fn test_search_reports_disabled_json_format() {
+        let server =
+            StubServer::spawn(|_| StubResponse::with_type(403, "text/html", "Forbidden")).await;
+        let provider = SearxngProvider::new(&server.url, Vec::new());
+
+        let err = provider
+            .search("rust", &SearchOptions::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("search.formats"));
+    }
+}
@@ -0,0 +1,1034 @@
+//! # HTTP API 模块
+//!
+//! `serve` 子命令把研究代理以 HTTP API 的形式提供给其他工具：
+//! - `POST /search`：快速搜索，直接返回结果
+//! - `POST /research`：在后台启动一次研究（或深度研究），立即返回任务
+//! - `GET /jobs/{id}`：查询任务的状态、已经发生的事件和最终结果
+//! - `GET /jobs/{id}/events`：用 Server-Sent Events 实时推送任务的事件（包括生成的令牌）
+//!
+//! 请求体的字段与 CLI 参数同名（`--max-turns` 对应 `max_turns`），
+//! 结果与 `--format json` 输出的 JSON 文档相同。
+//! 只有不影响服务器本身的参数可以按请求覆盖；LLM 和搜索服务器地址、
+//! 会话数据库、本地文件访问等仍由服务器的配置决定。
+//!
+//! 它演示了：
+//! - 使用 axum 定义路由，用提取器（`Json`、`Path`、`State`）解析请求
+//! - 通过 `Arc` 在处理函数和后台任务之间共享状态
+//! - 为自定义错误类型实现 `IntoResponse`，把错误转换为 HTTP 状态码
+//! - 使用 `broadcast` 通道把同一个事件流分发给多个订阅者
+
+use anyhow::{Context, Result};
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+use crate::agent::{self, AgentError, Conversation, ResearchAgent};
+use crate::config::{Config, ConfigSource};
+use crate::events::{EventSink, ResearchEvent};
+use crate::output::RunOutput;
+use crate::store::SessionStore;
+
+/// 最多同时运行多少个研究任务。每个任务都会占用 LLM 服务器，
+/// 超出时拒绝新任务（429），而不是让它们一起变慢
+const MAX_RUNNING_JOBS: usize = 4;
+
+/// 最多保留多少个已经结束的任务（更早的任务会被丢弃，正在运行的任务不受影响）
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// 每个任务的事件通道能缓冲多少个事件。订阅者读得太慢时会跳过最早的事件
+/// （通常是令牌），最终报告不受影响
+const EVENT_BUFFER: usize = 1024;
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 处理请求时可能发生的错误。
+///
+/// 每种错误对应一个 HTTP 状态码，响应体是
+/// `{"error": {"message": "...", "hint": "..."}}`，提示与 CLI 打印的相同。
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("找不到任务 {0}")]
+    JobNotFound(u64),
+
+    #[error("已经有 {0} 个研究任务在运行，请稍后再试")]
+    TooManyJobs(usize),
+
+    #[error(transparent)]
+    Agent(#[from] AgentError),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::JobNotFound(_) => StatusCode::NOT_FOUND,
+            Self::TooManyJobs(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Agent(AgentError::SearchRateLimited) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Agent(AgentError::Timeout { .. }) => StatusCode::GATEWAY_TIMEOUT,
+            Self::Agent(
+                AgentError::LlmUnreachable { .. }
+                | AgentError::ModelNotFound { .. }
+                | AgentError::Search(_),
+            ) => StatusCode::BAD_GATEWAY,
+            Self::Agent(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// # Rust 概念：IntoResponse
+///
+/// 处理函数可以返回任何实现了 `IntoResponse` 的类型。为错误类型实现它之后，
+/// 处理函数就能返回 `Result<_, ApiError>` 并直接使用 `?`。
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let hint = match &self {
+            Self::Agent(e) => e.hint(),
+            _ => None,
+        };
+        let body = JobError {
+            message: self.to_string(),
+            hint,
+        };
+        (self.status(), Json(serde_json::json!({ "error": body }))).into_response()
+    }
+}
+
+/// 请求体或路径无法解析时，axum 默认返回纯文本；转换为 `ApiError` 以使用统一的 JSON 格式。
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+// =============================================================================
+// 请求体
+// =============================================================================
+/// `POST /search` 的请求体。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchRequest {
+    pub query: String,
+    pub max_search_results: Option<usize>,
+    pub preview_chars: Option<usize>,
+}
+
+/// `POST /research` 的请求体。
+///
+/// 未知字段（包括服务器不允许覆盖的参数，例如 `ollama_host`）会被拒绝，
+/// 而不是静默忽略。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResearchRequest {
+    pub query: String,
+
+    /// 深度研究（`--deep`）
+    pub deep: bool,
+
+    /// 不保存会话（`--no-save`）
+    pub no_save: bool,
+
+    /// 跳过引用核验（`--no-verify`）
+    pub no_verify: bool,
+
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub seed: Option<u64>,
+    pub num_ctx: Option<u32>,
+    pub max_tokens: Option<u64>,
+    pub max_turns: Option<usize>,
+    pub max_search_results: Option<usize>,
+    pub page_token_budget: Option<usize>,
+    pub deep_max_questions: Option<usize>,
+    pub deep_max_turns: Option<usize>,
+}
+
+/// 把请求中提供了的字段写入配置，并记录它们来自请求（用于验证错误）。
+///
+/// `optional` 之后的字段在配置中本身是 `Option`。
+macro_rules! apply_fields {
+    ($request:expr => $config:ident: $($field:ident),* ; optional: $($optional:ident),* $(,)?) => {
+        $(
+            if let Some(value) = $request.$field {
+                $config.$field = value;
+                $config.set_origin(stringify!($field), ConfigSource::Request(stringify!($field)));
+            }
+        )*
+        $(
+            if $request.$optional.is_some() {
+                $config.$optional = $request.$optional;
+                $config.set_origin(stringify!($optional), ConfigSource::Request(stringify!($optional)));
+            }
+        )*
+    };
+}
+
+impl SearchRequest {
+    /// 在服务器配置的基础上应用请求的参数，并验证结果。
+    fn config(self, base: &Config) -> Result<(String, Config), ApiError> {
+        let query = require_query(&self.query)?;
+        let mut config = base.clone();
+        apply_fields!(self => config: max_search_results, preview_chars; optional:);
+        validate(&config)?;
+        Ok((query, config))
+    }
+}
+
+impl ResearchRequest {
+    /// 在服务器配置的基础上应用请求的参数，并验证结果。
+    fn config(self, base: &Config) -> Result<(String, Config), ApiError> {
+        let query = require_query(&self.query)?;
+        let mut config = base.clone();
+        if self.no_verify {
+            config.verify_citations = false;
+            config.set_origin("verify_citations", ConfigSource::Request("no_verify"));
+        }
+        apply_fields!(
+            self => config:
+            model,
+            temperature,
+            max_turns,
+            max_search_results,
+            page_token_budget,
+            deep_max_questions,
+            deep_max_turns;
+            optional: top_p, seed, num_ctx, max_tokens,
+        );
+        validate(&config)?;
+        Ok((query, config))
+    }
+}
+
+fn require_query(query: &str) -> Result<String, ApiError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(ApiError::BadRequest("query 不能为空".to_string()));
+    }
+    Ok(query.to_string())
+}
+
+fn validate(config: &Config) -> Result<(), ApiError> {
+    config
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("{:#}", e)))
+}
+
+// =============================================================================
+// 后台任务
+// =============================================================================
+/// 任务的状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// 失败的原因和给用户的建议。
+#[derive(Debug, Serialize)]
+pub struct JobError {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// 一次在后台运行的研究。`GET /jobs/{id}` 返回它的 JSON。
+#[derive(Debug, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub status: JobStatus,
+    pub query: String,
+
+    /// "research" 或 "deep"
+    pub mode: &'static str,
+
+    pub created_at: DateTime<Utc>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// 到目前为止发生的事件（不含令牌），可以在任务运行时轮询进度
+    pub events: Vec<ResearchEvent>,
+
+    /// 保存的会话编号（可以用 `sessions resume` 继续追问）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<i64>,
+
+    /// 成功时的结果，与 `--format json` 的输出相同
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<RunOutput>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobError>,
+
+    /// 把事件实时分发给 SSE 订阅者；任务结束时关闭（设为 None）
+    #[serde(skip)]
+    channel: Option<broadcast::Sender<ResearchEvent>>,
+}
+
+impl Job {
+    /// 任务结束后推送的最后一个事件：成功时是结果，失败时是错误。
+    fn final_event(&self) -> Option<serde_json::Value> {
+        if let Some(result) = &self.result {
+            return Some(serde_json::json!({
+                "type": "report",
+                "session_id": self.session_id,
+                "result": result,
+            }));
+        }
+        let error = self.error.as_ref()?;
+        Some(serde_json::json!({
+            "type": "error",
+            "message": error.message,
+            "hint": error.hint,
+        }))
+    }
+}
+
+/// 所有任务，按编号排序（编号递增，所以也是创建顺序）。
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl Jobs {
+    /// 添加一个正在运行的任务，并丢弃超出上限的最早结束的任务。
+    ///
+    /// 已经有 `MAX_RUNNING_JOBS` 个任务在运行时返回错误。检查和添加在同一次加锁中完成，
+    /// 所以并发的请求不会一起越过上限。
+    fn insert(&mut self, query: &str, mode: &'static str) -> Result<u64, ApiError> {
+        let running = self
+            .jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Running)
+            .count();
+        if running >= MAX_RUNNING_JOBS {
+            return Err(ApiError::TooManyJobs(MAX_RUNNING_JOBS));
+        }
+
+        self.next_id += 1;
+        let id = self.next_id;
+        self.jobs.insert(
+            id,
+            Job {
+                id,
+                status: JobStatus::Running,
+                query: query.to_string(),
+                mode,
+                created_at: Utc::now(),
+                finished_at: None,
+                events: Vec::new(),
+                session_id: None,
+                result: None,
+                error: None,
+                channel: Some(broadcast::channel(EVENT_BUFFER).0),
+            },
+        );
+
+        let finished: Vec<u64> = self
+            .jobs
+            .values()
+            .filter(|job| job.status != JobStatus::Running)
+            .map(|job| job.id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            self.jobs.remove(id);
+        }
+        Ok(id)
+    }
+}
+
+/// 研究成功时任务记录的结果
+struct Completed {
+    output: RunOutput,
+    session_id: Option<i64>,
+}
+
+// =============================================================================
+// 服务器状态
+// =============================================================================
+/// 所有处理函数共享的状态。
+///
+/// # Rust 概念：Arc<Mutex<T>>
+///
+/// axum 在多个线程上并发调用处理函数，后台任务也在其他线程上运行，
+/// 所以状态放在 `Arc` 中共享，可变的部分用 `Mutex` 保护。
+/// 锁只在读写内存中的数据时短暂持有，从不跨越 `.await`，所以使用标准库的 `Mutex`。
+pub struct ServerState {
+    /// 服务器的配置，每个请求在它的副本上应用自己的参数
+    config: Config,
+
+    jobs: Mutex<Jobs>,
+
+    /// 会话数据库（`--no-save` 或打开失败时为 None）。
+    /// SQLite 连接不能在线程之间共享引用，所以也放在 `Mutex` 中；
+    /// 读写数据库会阻塞线程，所以在 `spawn_blocking` 的线程上进行
+    store: Option<Mutex<SessionStore>>,
+}
+
+type SharedState = Arc<ServerState>;
+
+impl ServerState {
+    pub fn new(config: Config, store: Option<SessionStore>) -> Self {
+        Self {
+            config,
+            jobs: Mutex::default(),
+            store: store.map(Mutex::new),
+        }
+    }
+
+    /// 对任务执行一个操作（任务已经被丢弃时什么也不做）。
+    fn update_job(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().unwrap().jobs.get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// 记录任务的一个事件，并推送给订阅者。
+    fn record_event(&self, id: u64, event: ResearchEvent) {
+        self.update_job(id, |job| {
+            // 生成的文本会出现在最终报告中，只推送，不保存
+            if !matches!(event, ResearchEvent::Token { .. }) {
+                job.events.push(event.clone());
+            }
+            if let Some(channel) = &job.channel {
+                let _ = channel.send(event);
+            }
+        });
+    }
+
+    /// 记录任务的结果，并关闭事件通道（订阅者收完剩余的事件后收到最终结果）。
+    fn finish_job(&self, id: u64, result: Result<Completed, AgentError>) {
+        self.update_job(id, |job| {
+            job.finished_at = Some(Utc::now());
+            job.channel = None;
+            match result {
+                Ok(completed) => {
+                    job.status = JobStatus::Succeeded;
+                    job.session_id = completed.session_id;
+                    job.result = Some(completed.output);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(JobError {
+                        message: e.to_string(),
+                        hint: e.hint(),
+                    });
+                }
+            }
+        });
+    }
+
+    /// 订阅任务的事件：返回已经发生的事件和之后事件的接收器
+    /// （任务已经结束时没有接收器）。
+    ///
+    /// 两者在同一次加锁中取得，所以事件既不会遗漏也不会重复。
+    fn subscribe(
+        &self,
+        id: u64,
+    ) -> Result<
+        (
+            Vec<ResearchEvent>,
+            Option<broadcast::Receiver<ResearchEvent>>,
+        ),
+        ApiError,
+    > {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.jobs.get(&id).ok_or(ApiError::JobNotFound(id))?;
+        Ok((
+            job.events.clone(),
+            job.channel.as_ref().map(broadcast::Sender::subscribe),
+        ))
+    }
+
+    fn final_event(&self, id: u64) -> Option<serde_json::Value> {
+        self.jobs.lock().unwrap().jobs.get(&id)?.final_event()
+    }
+}
+
+// =============================================================================
+// 路由和处理函数
+// =============================================================================
+/// 创建路由。
+pub fn router(state: SharedState) -> Router {
+    Router::new()
+        .route("/search", post(search))
+        .route("/research", post(start_research))
+        .route("/jobs/{id}", get(get_job))
+        .route("/jobs/{id}/events", get(job_events))
+        .with_state(state)
+}
+
+/// 在 `listen` 上提供 HTTP API，直到收到 Ctrl+C。
+pub async fn serve(config: Config, store: Option<SessionStore>, listen: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("无法监听 {}", listen))?;
+    let address = listener.local_addr()?;
+    info!(%address, "HTTP API 已启动");
+    eprintln!("🌐 正在监听 http://{}（按 Ctrl+C 停止）", address);
+
+    let state = Arc::new(ServerState::new(config, store));
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// `POST /search`：快速搜索，返回与 `--quick --format json` 相同的文档。
+async fn search(
+    State(state): State<SharedState>,
+    request: Result<Json<SearchRequest>, JsonRejection>,
+) -> Result<Json<RunOutput>, ApiError> {
+    let Json(request) = request?;
+    let (query, config) = request.config(&state.config)?;
+
+    let agent = ResearchAgent::new(config)?;
+    let mut output = RunOutput::new(&query, "quick", agent.config(), Utc::now());
+    let results = agent.quick_results(&query).await?;
+    output.body = agent::format_quick_results(&query, &results);
+    output.add_search_results(results.into_iter().map(|(result, _)| result));
+    Ok(Json(output.finish()))
+}
+
+/// `POST /research`：在后台启动研究，返回 `202 Accepted` 和新任务。
+async fn start_research(
+    State(state): State<SharedState>,
+    request: Result<Json<ResearchRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(request) = request?;
+    let deep = request.deep;
+    let save = !request.no_save;
+    let (query, config) = request.config(&state.config)?;
+    let agent = ResearchAgent::new(config)?;
+
+    let mode = if deep { "deep" } else { "research" };
+    let id = state.jobs.lock().unwrap().insert(&query, mode)?;
+    info!(job = id, mode, query = %query, "研究任务已创建");
+
+    tokio::spawn(run_job(state.clone(), id, agent, query, mode, save));
+
+    let location = format!("/jobs/{}", id);
+    let body = job_json(&state, id)?;
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(body),
+    )
+        .into_response())
+}
+
+/// `GET /jobs/{id}`：任务的当前状态。
+async fn get_job(
+    State(state): State<SharedState>,
+    id: Result<Path<u64>, PathRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let Path(id) = id?;
+    Ok(Json(job_json(&state, id)?))
+}
+
+/// `GET /jobs/{id}/events`：用 Server-Sent Events 推送任务的事件。
+///
+/// 先补发已经发生的事件，然后实时推送新事件（包括令牌），最后推送
+/// `report` 或 `error` 事件并结束。每个事件的 `event` 字段是事件类型，
+/// `data` 是带 `type` 字段的 JSON。
+///
+/// # Rust 概念：impl Stream
+///
+/// `Sse` 接受任何产生事件的异步流。这里由一个后台任务把事件写入 mpsc 通道，
+/// 再把通道的接收端包装成流；客户端断开时流被丢弃，后台任务的发送失败并退出。
+async fn job_events(
+    State(state): State<SharedState>,
+    id: Result<Path<u64>, PathRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let Path(id) = id?;
+    let (backlog, receiver) = state.subscribe(id)?;
+
+    let (sender, events) = mpsc::channel(EVENT_BUFFER);
+    tokio::spawn(forward_events(state, id, backlog, receiver, sender));
+
+    let stream = futures::stream::unfold(events, |mut events| async move {
+        let event = events.recv().await?;
+        Some((Ok(event), events))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// 把任务的事件依次写入 `sender`，直到任务结束或客户端断开。
+async fn forward_events(
+    state: SharedState,
+    id: u64,
+    backlog: Vec<ResearchEvent>,
+    receiver: Option<broadcast::Receiver<ResearchEvent>>,
+    sender: mpsc::Sender<Event>,
+) {
+    for event in &backlog {
+        if sender.send(sse_event(to_json(event))).await.is_err() {
+            return;
+        }
+    }
+
+    if let Some(mut receiver) = receiver {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if sender.send(sse_event(to_json(&event))).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(job = id, skipped, "SSE 订阅者跟不上，跳过了一些事件");
+                }
+                // 任务结束时关闭通道
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    if let Some(event) = state.final_event(id) {
+        let _ = sender.send(sse_event(event)).await;
+    }
+}
+
+fn to_json(event: &ResearchEvent) -> serde_json::Value {
+    serde_json::to_value(event).unwrap_or_default()
+}
+
+/// 以 JSON 中的 `type` 作为 SSE 事件名。
+fn sse_event(data: serde_json::Value) -> Event {
+    let name = data["type"].as_str().unwrap_or("message").to_string();
+    Event::default().event(name).data(data.to_string())
+}
+
+/// 在持有锁时把任务序列化，避免复制整个任务。
+fn job_json(state: &ServerState, id: u64) -> Result<serde_json::Value, ApiError> {
+    let jobs = state.jobs.lock().unwrap();
+    let job = jobs.jobs.get(&id).ok_or(ApiError::JobNotFound(id))?;
+    Ok(serde_json::to_value(job).unwrap_or_default())
+}
+
+// =============================================================================
+// 执行研究
+// =============================================================================
+/// 运行研究任务，并把结果（或错误）记录到任务中。
+async fn run_job(
+    state: SharedState,
+    id: u64,
+    agent: ResearchAgent,
+    query: String,
+    mode: &'static str,
+    save: bool,
+) {
+    let result = research(&state, id, &agent, &query, mode, save).await;
+    if let Err(e) = &result {
+        warn!(job = id, error = %e, "研究任务失败");
+    } else {
+        info!(job = id, "研究任务完成");
+    }
+    state.finish_job(id, result);
+}
+
+/// 执行研究：事件实时记录到任务中，结束后保存会话。
+async fn research(
+    state: &SharedState,
+    id: u64,
+    agent: &ResearchAgent,
+    query: &str,
+    mode: &'static str,
+    save: bool,
+) -> Result<Completed, AgentError> {
+    let (events, mut receiver) = EventSink::channel();
+    let recorder = tokio::spawn({
+        let state = state.clone();
+        async move {
+            while let Some(event) = receiver.recv().await {
+                state.record_event(id, event);
+            }
+        }
+    });
+
+    let mut output = RunOutput::new(query, mode, agent.config(), Utc::now());
+    let mut conversation = Conversation::default();
+    let report = if mode == "deep" {
+        agent.deep_research(query, &mut conversation, &events).await
+    } else {
+        agent.research(query, &mut conversation, &events).await
+    };
+
+    // 关闭事件通道并等待记录完剩余的事件
+    drop(events);
+    let _ = recorder.await;
+    let report = report?;
+
+    let log = state
+        .jobs
+        .lock()
+        .unwrap()
+        .jobs
+        .get(&id)
+        .map(|job| job.events.clone())
+        .unwrap_or_default();
+    output.record_events(&log);
+
+    let session_id = if save && state.store.is_some() {
+        let state = state.clone();
+        let (query, config, report) = (query.to_string(), agent.config().clone(), report.clone());
+        // # Rust 概念：spawn_blocking
+        //
+        // SQLite 的读写是同步的，直接在异步任务中执行会占住运行时的工作线程，
+        // 拖慢同一线程上的其他请求。`spawn_blocking` 把闭包放到专门的阻塞线程池中运行，
+        // 闭包需要拥有它使用的数据，所以移入副本
+        let saved = tokio::task::spawn_blocking(move || {
+            let mut store = state.store.as_ref()?.lock().unwrap();
+            let saved = store.create(mode, &query, &config).and_then(|session| {
+                store.record_turn(session, &query, &report, &log, &conversation)?;
+                Ok(session)
+            });
+            match saved {
+                Ok(session) => Some(session),
+                Err(e) => {
+                    warn!(job = id, error = %e, "保存研究会话失败");
+                    None
+                }
+            }
+        })
+        .await;
+        saved.unwrap_or_else(|e| {
+            warn!(job = id, error = %e, "保存研究会话的线程异常退出");
+            None
+        })
+    } else {
+        None
+    };
+
+    output.body = report.to_markdown();
+    output.report = Some(report);
+    Ok(Completed {
+        output: output.finish(),
+        session_id,
+    })
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LlmProvider, SearchBackend};
+    use crate::test_util::{StubResponse, StubServer};
+    use std::time::Duration;
+
+    /// 在随机端口上启动 API 服务器，返回它的基础 URL。
+    async fn spawn_api(config: Config, store: Option<SessionStore>) -> String {
+        serve_state(Arc::new(ServerState::new(config, store))).await
+    }
+
+    /// 用给定的状态启动 API 服务器（测试可以直接操作任务）。
+    async fn serve_state(state: SharedState) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        url
+    }
+
+    /// 把 SSE 响应体解析为（事件名，JSON 数据）列表。
+    fn parse_sse(body: &str) -> Vec<(String, serde_json::Value)> {
+        body.split("\n\n")
+            .filter_map(|message| {
+                let field = |name: &str| {
+                    message
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(str::trim)
+                };
+                Some((
+                    field("event:")?.to_string(),
+                    serde_json::from_str(field("data:")?).unwrap(),
+                ))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_endpoint() {
+        let searxng = StubServer::spawn(|_| {
+            StubResponse::json(
+                r#"{"results":[
+                    {"title":"Tokio","url":"https://tokio.rs","content":"An async runtime"},
+                    {"title":"smol","url":"https://smol.rs","content":"A small runtime"}
+                ]}"#,
+            )
+        })
+        .await;
+        let api = spawn_api(
+            Config {
+                search_provider: SearchBackend::Searxng,
+                searxng_url: searxng.url.clone(),
+                ..Config::default()
+            },
+            None,
+        )
+        .await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/search", api))
+            .json(&serde_json::json!({ "query": "rust async", "preview_chars": 0 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(json["mode"], "quick");
+        assert_eq!(json["query"], "rust async");
+        assert_eq!(json["search_results"][1]["url"], "https://smol.rs");
+        assert!(json["body"].as_str().unwrap().contains("Tokio"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_requests() {
+        let api = spawn_api(Config::default(), None).await;
+        let client = reqwest::Client::new();
+        let post =
+            |body: serde_json::Value| client.post(format!("{}/research", api)).json(&body).send();
+
+        // 服务器地址不能按请求覆盖
+        let response = post(serde_json::json!({ "query": "q", "ollama_host": "http://x" }))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("ollama_host"));
+
+        // 验证错误指出无效值来自请求
+        let response = post(serde_json::json!({ "query": "q", "temperature": 5.0 }))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("HTTP 请求字段 temperature"));
+
+        let response = post(serde_json::json!({ "query": "  " })).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let response = client.get(format!("{}/jobs/42", api)).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_research_job_runs_in_background() {
+        // 模拟 OpenAI 兼容服务器的流式回答
+        let chunks = [
+            serde_json::json!({ "choices": [{ "delta": { "content": "## 概述\nRust 是一门系统编程语言。" }, "finish_reason": "stop" }] }),
+        ];
+        let body: String = chunks
+            .iter()
+            .map(|chunk| format!("data: {}\n\n", chunk))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        let llm = StubServer::spawn(move |_| {
+            StubResponse::with_type(200, "text/event-stream", body.clone())
+        })
+        .await;
+        let config = Config {
+            provider: LlmProvider::OpenAiCompatible,
+            openai_base_url: format!("{}/v1", llm.url),
+            model: "local-model".to_string(),
+            ..Config::default()
+        };
+        let api = spawn_api(config, Some(SessionStore::open_in_memory().unwrap())).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/research", api))
+            .json(&serde_json::json!({ "query": "什么是 Rust？", "no_verify": true, "seed": 7 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+        let location = response.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(location, "/jobs/1");
+
+        // 轮询直到任务结束
+        let mut job = serde_json::Value::Null;
+        for _ in 0..100 {
+            job = client
+                .get(format!("{}{}", api, location))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if job["status"] != "running" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(job["status"], "succeeded", "{}", job);
+        assert_eq!(job["mode"], "research");
+        assert_eq!(job["session_id"], 1);
+        assert_eq!(job["result"]["model"], "local-model");
+        assert!(job["result"]["body"]
+            .as_str()
+            .unwrap()
+            .contains("Rust 是一门系统编程语言。"));
+
+        let request: serde_json::Value = serde_json::from_str(&llm.requests()[0].body).unwrap();
+        assert_eq!(request["seed"], 7);
+
+        // 任务结束后订阅：补发记录的事件，最后是完整结果
+        let body = client
+            .get(format!("{}{}/events", api, location))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let (name, data) = parse_sse(&body).pop().unwrap();
+        assert_eq!(name, "report");
+        assert_eq!(data["session_id"], 1);
+        assert_eq!(data["result"], job["result"]);
+    }
+
+    #[tokio::test]
+    async fn test_limits_running_jobs() {
+        let state = Arc::new(ServerState::new(Config::default(), None));
+        let ids: Vec<u64> = (0..MAX_RUNNING_JOBS)
+            .map(|_| {
+                state
+                    .jobs
+                    .lock()
+                    .unwrap()
+                    .insert("rust", "research")
+                    .unwrap()
+            })
+            .collect();
+        let api = serve_state(state.clone()).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/research", api))
+            .json(&serde_json::json!({ "query": "rust" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("研究任务在运行"));
+
+        // 有任务结束后可以再创建新任务
+        state.finish_job(ids[0], Err(AgentError::TurnLimit { max_turns: 8 }));
+        assert!(state
+            .jobs
+            .lock()
+            .unwrap()
+            .insert("rust", "research")
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_job_events_stream() {
+        let state = Arc::new(ServerState::new(Config::default(), None));
+        let id = state.jobs.lock().unwrap().insert("rust", "deep").unwrap();
+        state.record_event(
+            id,
+            ResearchEvent::PlanCreated {
+                questions: vec!["Tokio 是什么？".to_string()],
+            },
+        );
+        let api = serve_state(state.clone()).await;
+
+        let response = reqwest::get(format!("{}/jobs/{}/events", api, id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // 订阅之后发生的事件（包括令牌）实时推送，任务结束时推送错误并关闭流
+        state.record_event(
+            id,
+            ResearchEvent::SearchIssued {
+                query: "tokio".to_string(),
+            },
+        );
+        state.record_event(
+            id,
+            ResearchEvent::Token {
+                text: "Tokio".to_string(),
+            },
+        );
+        state.finish_job(id, Err(AgentError::TurnLimit { max_turns: 8 }));
+
+        let events = parse_sse(&response.text().await.unwrap());
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["plan_created", "search_issued", "token", "error"]);
+        assert_eq!(events[0].1["questions"][0], "Tokio 是什么？");
+        assert_eq!(events[1].1["type"], "search_issued");
+        assert_eq!(events[2].1["text"], "Tokio");
+        assert!(events[3].1["hint"]
+            .as_str()
+            .unwrap()
+            .contains("--max-turns"));
+
+        // 令牌不保存，之后订阅的客户端只收到记录的事件和错误
+        let body = reqwest::get(format!("{}/jobs/{}/events", api, id))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let names: Vec<String> = parse_sse(&body).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["plan_created", "search_issued", "error"]);
+    }
+}
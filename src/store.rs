@@ -0,0 +1,598 @@
+//! # 会话存储模块
+//!
+//! 每次研究都会保存到本地 SQLite 数据库：问题、模型、当时的配置快照、
+//! 工具调用、搜索结果和读取过的页面，以及最终报告。保存的会话可以
+//! 列出、查看、删除，也可以恢复到交互模式中继续追问。
+//!
+//! 数据库结构：
+//! - `sessions`：一行一个会话（配置快照和聊天历史以 JSON 保存）
+//! - `turns`：会话中的每一轮（问题和结构化报告）
+//! - `events`：每一轮的工具调用、工具结果和状态事件
+//! - `sources`：会话中登记的来源，以及搜索片段和页面正文
+//!
+//! 它演示了：
+//! - 使用 rusqlite 执行参数化 SQL 和事务
+//! - 在关系表中保存 serde 序列化的 JSON
+
+use chrono::{DateTime, Utc};
+use rig::message::Message;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::agent::Conversation;
+use crate::config::Config;
+use crate::events::ResearchEvent;
+use crate::report::{ResearchReport, Source, SourceRegistry};
+
+/// 建表语句。`IF NOT EXISTS` 让每次打开数据库时都可以安全执行。
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS sessions (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at  TEXT NOT NULL,
+    updated_at  TEXT NOT NULL,
+    mode        TEXT NOT NULL,
+    query       TEXT NOT NULL,
+    model       TEXT NOT NULL,
+    config      TEXT NOT NULL,
+    history     TEXT NOT NULL DEFAULT '[]'
+);
+
+CREATE TABLE IF NOT EXISTS turns (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id  INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    created_at  TEXT NOT NULL,
+    query       TEXT NOT NULL,
+    report      TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS events (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    turn_id     INTEGER NOT NULL REFERENCES turns(id) ON DELETE CASCADE,
+    event       TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sources (
+    session_id  INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    id          INTEGER NOT NULL,
+    url         TEXT NOT NULL,
+    title       TEXT NOT NULL,
+    accessed_at TEXT NOT NULL,
+    content     TEXT NOT NULL,
+    author      TEXT,
+    site_name   TEXT,
+    published   TEXT,
+    PRIMARY KEY (session_id, id)
+);
+"#;
+
+/// 在旧版本创建的表上补上后来新增的列（`CREATE TABLE IF NOT EXISTS` 不会修改已有的表）。
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("sources", "author TEXT"),
+    ("sources", "site_name TEXT"),
+    ("sources", "published TEXT"),
+];
+
+// =============================================================================
+// 自定义错误类型
+// =============================================================================
+/// 读写会话数据库时可能发生的错误。
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("会话 #{0} 不存在")]
+    NotFound(i64),
+
+    #[error("数据库错误: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("会话数据格式错误: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("创建数据库目录失败: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// =============================================================================
+// 会话数据
+// =============================================================================
+/// 会话列表中的一行。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+
+    /// 研究模式：research、deep 或 interactive
+    pub mode: String,
+
+    /// 第一个问题
+    pub query: String,
+    pub model: String,
+    pub turns: usize,
+}
+
+/// 会话中的一轮：问题、工具活动和报告。
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub created_at: DateTime<Utc>,
+    pub query: String,
+    pub report: ResearchReport,
+    pub events: Vec<ResearchEvent>,
+}
+
+/// 一个完整的已保存会话。
+#[derive(Debug)]
+pub struct Session {
+    pub summary: SessionSummary,
+
+    /// 创建会话时的配置快照
+    pub config: serde_json::Value,
+
+    pub turns: Vec<Turn>,
+    history: Vec<Message>,
+    sources: Vec<(Source, String)>,
+}
+
+impl Session {
+    /// 重建对话状态（聊天历史和来源登记簿），用于继续追问。
+    pub fn conversation(&self) -> Conversation {
+        Conversation {
+            history: self.history.clone(),
+            sources: SourceRegistry::from_entries(self.sources.clone()),
+        }
+    }
+
+    /// 渲染为人类可读的 Markdown：会话信息、每轮的工具活动和报告。
+    pub fn render(&self) -> String {
+        let summary = &self.summary;
+        let mut out = format!(
+            "# 会话 #{}：{}\n\n- 模式：{}\n- 模型：{}\n- 创建于：{}\n- 更新于：{}\n",
+            summary.id,
+            summary.query,
+            summary.mode,
+            summary.model,
+            summary.created_at.format("%Y-%m-%d %H:%M"),
+            summary.updated_at.format("%Y-%m-%d %H:%M"),
+        );
+
+        for (i, turn) in self.turns.iter().enumerate() {
+            out.push_str(&format!(
+                "\n---\n\n## 第 {} 轮：{}\n\n时间：{}\n",
+                i + 1,
+                turn.query,
+                turn.created_at.format("%Y-%m-%d %H:%M")
+            ));
+            if !turn.events.is_empty() {
+                out.push_str("\n```text");
+                for event in &turn.events {
+                    out.push_str(&event.to_terminal());
+                }
+                out.push_str("```\n");
+            }
+            out.push('\n');
+            out.push_str(&turn.report.to_markdown());
+            out.push('\n');
+        }
+
+        out.push_str(&format!(
+            "\n---\n\n## 配置快照\n\n```json\n{}\n```\n",
+            serde_json::to_string_pretty(&self.config).unwrap_or_default()
+        ));
+        out
+    }
+}
+
+// =============================================================================
+// 会话存储
+// =============================================================================
+/// SQLite 会话数据库。
+///
+/// # Rust 概念：同步 API
+///
+/// rusqlite 是同步的。本地 SQLite 的读写只需要几毫秒，
+/// 所以在 CLI 中直接调用即可，不需要 `spawn_blocking`。
+pub struct SessionStore {
+    conn: Connection,
+}
+
+impl SessionStore {
+    /// 打开（必要时创建）数据库文件并确保表结构存在。
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    /// 打开内存数据库（用于测试）。
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        // SQLite 默认不检查外键，需要为每个连接打开
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        for (table, column) in ADDED_COLUMNS {
+            let name = column.split_whitespace().next().unwrap_or_default();
+            let exists: bool = conn.query_row(
+                &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{table}') WHERE name = ?1"),
+                [name],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column};"))?;
+            }
+        }
+        Ok(Self { conn })
+    }
+
+    /// 创建新会话，返回会话编号。
+    pub fn create(&self, mode: &str, query: &str, config: &Config) -> Result<i64, StoreError> {
+        let now = Utc::now();
+        self.conn.execute(
+            "INSERT INTO sessions (created_at, updated_at, mode, query, model, config)
+             VALUES (?1, ?1, ?2, ?3, ?4, ?5)",
+            params![
+                now,
+                mode,
+                query,
+                config.model,
+                serde_json::to_string(config)?
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// 保存一轮研究：报告、工具事件，以及更新后的聊天历史和来源。
+    ///
+    /// 所有写入在一个事务中完成，中途失败不会留下半轮数据。
+    pub fn record_turn(
+        &mut self,
+        session_id: i64,
+        query: &str,
+        report: &ResearchReport,
+        events: &[ResearchEvent],
+        conversation: &Conversation,
+    ) -> Result<(), StoreError> {
+        let now = Utc::now();
+        let tx = self.conn.transaction()?;
+
+        let updated = tx.execute(
+            "UPDATE sessions SET updated_at = ?1, history = ?2 WHERE id = ?3",
+            params![
+                now,
+                serde_json::to_string(&conversation.history)?,
+                session_id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(StoreError::NotFound(session_id));
+        }
+
+        tx.execute(
+            "INSERT INTO turns (session_id, created_at, query, report) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, now, query, serde_json::to_string(report)?],
+        )?;
+        let turn_id = tx.last_insert_rowid();
+
+        for event in events {
+            tx.execute(
+                "INSERT INTO events (turn_id, event) VALUES (?1, ?2)",
+                params![turn_id, serde_json::to_string(event)?],
+            )?;
+        }
+
+        // 登记簿只会增长，直接覆盖写入当前的全部来源
+        for (source, content) in conversation.sources.entries() {
+            tx.execute(
+                "INSERT OR REPLACE INTO sources
+                     (session_id, id, url, title, accessed_at, content, author, site_name, published)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    session_id,
+                    source.id as i64,
+                    source.url,
+                    source.title,
+                    source.accessed_at,
+                    content,
+                    source.author,
+                    source.site_name,
+                    source.published
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 列出所有会话（最近更新的在前）。
+    pub fn list(&self) -> Result<Vec<SessionSummary>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.created_at, s.updated_at, s.mode, s.query, s.model,
+                    (SELECT COUNT(*) FROM turns t WHERE t.session_id = s.id)
+             FROM sessions s ORDER BY s.updated_at DESC, s.id DESC",
+        )?;
+        let rows = stmt.query_map([], summary_from_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// 读取一个完整的会话。
+    pub fn load(&self, id: i64) -> Result<Session, StoreError> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT s.id, s.created_at, s.updated_at, s.mode, s.query, s.model,
+                        (SELECT COUNT(*) FROM turns t WHERE t.session_id = s.id),
+                        s.config, s.history
+                 FROM sessions s WHERE s.id = ?1",
+                [id],
+                |row| {
+                    Ok((
+                        summary_from_row(row)?,
+                        row.get::<_, String>(7)?,
+                        row.get::<_, String>(8)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((summary, config, history)) = row else {
+            return Err(StoreError::NotFound(id));
+        };
+
+        let mut turns = Vec::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT id, created_at, query, report FROM turns WHERE session_id = ?1 ORDER BY id",
+        )?;
+        let mut events_stmt = self
+            .conn
+            .prepare("SELECT event FROM events WHERE turn_id = ?1 ORDER BY id")?;
+        let rows = stmt.query_map([id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, DateTime<Utc>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (turn_id, created_at, query, report) = row?;
+            let events = events_stmt
+                .query_map([turn_id], |row| row.get::<_, String>(0))?
+                .map(|event| Ok(serde_json::from_str(&event?)?))
+                .collect::<Result<_, StoreError>>()?;
+            turns.push(Turn {
+                created_at,
+                query,
+                report: serde_json::from_str(&report)?,
+                events,
+            });
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, title, accessed_at, content, author, site_name, published
+             FROM sources WHERE session_id = ?1 ORDER BY id",
+        )?;
+        let sources = stmt
+            .query_map([id], |row| {
+                Ok((
+                    Source {
+                        id: row.get::<_, i64>(0)? as usize,
+                        url: row.get(1)?,
+                        title: row.get(2)?,
+                        accessed_at: row.get(3)?,
+                        author: row.get(5)?,
+                        site_name: row.get(6)?,
+                        published: row.get(7)?,
+                    },
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(Session {
+            summary,
+            config: serde_json::from_str(&config)?,
+            turns,
+            history: serde_json::from_str(&history)?,
+            sources,
+        })
+    }
+
+    /// 更新会话使用的模型和配置（交互模式中用 `/model` 切换模型之后）。
+    pub fn set_model(&self, id: i64, config: &Config) -> Result<(), StoreError> {
+        let updated = self.conn.execute(
+            "UPDATE sessions SET model = ?1, config = ?2 WHERE id = ?3",
+            params![config.model, serde_json::to_string(config)?, id],
+        )?;
+        match updated {
+            0 => Err(StoreError::NotFound(id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 删除一个会话及其所有轮次、事件和来源。会话不存在时返回 `NotFound`。
+    pub fn delete(&self, id: i64) -> Result<(), StoreError> {
+        match self
+            .conn
+            .execute("DELETE FROM sessions WHERE id = ?1", [id])?
+        {
+            0 => Err(StoreError::NotFound(id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 从 `list` 和 `load` 共用的前 7 列读取会话摘要。
+fn summary_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionSummary> {
+    Ok(SessionSummary {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        updated_at: row.get(2)?,
+        mode: row.get(3)?,
+        query: row.get(4)?,
+        model: row.get(5)?,
+        turns: row.get::<_, i64>(6)? as usize,
+    })
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟一轮研究：登记来源并生成引用它的报告
+    fn research_turn(conversation: &mut Conversation, query: &str, url: &str) -> ResearchReport {
+        let id = conversation.sources.register(url, "标题");
+        conversation.sources.add_content(id, "页面正文");
+        let response = format!("## 概述\n关于 {} 的发现 [{}]", query, id);
+        conversation.history.push(Message::user(query));
+        conversation
+            .history
+            .push(Message::assistant(response.as_str()));
+        ResearchReport::from_response(query, &response, &conversation.sources)
+    }
+
+    #[test]
+    fn test_record_and_load_session() {
+        let mut store = SessionStore::open_in_memory().unwrap();
+        let config = Config::default();
+        let mut conversation = Conversation::default();
+
+        let id = store
+            .create("interactive", "什么是 Rust？", &config)
+            .unwrap();
+        let report = research_turn(&mut conversation, "什么是 Rust？", "https://rust-lang.org");
+        conversation
+            .sources
+            .add_metadata(1, Some("Ferris"), Some("Rust"), Some("2024-01-01"));
+        let events = vec![
+            ResearchEvent::ToolCall {
+                name: "web_search".to_string(),
+                arguments: serde_json::json!({ "query": "rust" }),
+            },
+            ResearchEvent::tool_result("web_search", "## 搜索结果"),
+        ];
+        store
+            .record_turn(id, "什么是 Rust？", &report, &events, &conversation)
+            .unwrap();
+
+        let report = research_turn(
+            &mut conversation,
+            "展开第 1 点",
+            "https://doc.rust-lang.org",
+        );
+        store
+            .record_turn(id, "展开第 1 点", &report, &[], &conversation)
+            .unwrap();
+
+        let session = store.load(id).unwrap();
+        assert_eq!(session.summary.query, "什么是 Rust？");
+        assert_eq!(session.summary.model, config.model);
+        assert_eq!(session.summary.turns, 2);
+        assert_eq!(session.turns[0].events, events);
+        assert_eq!(session.turns[1].report, report);
+        assert_eq!(session.config["model"], config.model.as_str());
+
+        // 恢复的对话保留历史和来源，新来源的编号接在后面
+        let restored = session.conversation();
+        assert_eq!(restored.history, conversation.history);
+        assert_eq!(restored.sources.entries(), conversation.sources.entries());
+        assert_eq!(restored.sources.register("https://new.example", ""), 3);
+
+        let rendered = session.render();
+        assert!(rendered.contains("## 第 2 轮：展开第 1 点"));
+        assert!(rendered.contains("🔎 searching: rust"));
+
+        // 切换模型后恢复会话使用新模型
+        let switched = Config {
+            model: "qwen2.5".to_string(),
+            ..config
+        };
+        store.set_model(id, &switched).unwrap();
+        let session = store.load(id).unwrap();
+        assert_eq!(session.summary.model, "qwen2.5");
+        assert_eq!(session.config["model"], "qwen2.5");
+        assert!(matches!(
+            store.set_model(999, &switched),
+            Err(StoreError::NotFound(999))
+        ));
+    }
+
+    #[test]
+    fn test_adds_columns_to_old_databases() {
+        let path =
+            std::env::temp_dir().join(format!("ai-research-agent-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // 旧版本的 sources 表没有元数据列
+        let old = Connection::open(&path).unwrap();
+        old.execute_batch(
+            "CREATE TABLE sources (session_id INTEGER NOT NULL, id INTEGER NOT NULL,
+                 url TEXT NOT NULL, title TEXT NOT NULL, accessed_at TEXT NOT NULL,
+                 content TEXT NOT NULL, PRIMARY KEY (session_id, id));",
+        )
+        .unwrap();
+        drop(old);
+
+        let store = SessionStore::open(&path).unwrap();
+        let columns: i64 = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('sources')
+                 WHERE name IN ('author', 'site_name', 'published')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(columns, 3);
+
+        // 再次打开不会重复添加
+        drop(store);
+        SessionStore::open(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_list_and_delete_sessions() {
+        let mut store = SessionStore::open_in_memory().unwrap();
+        let config = Config::default();
+
+        let first = store.create("research", "第一个问题", &config).unwrap();
+        let second = store.create("deep", "第二个问题", &config).unwrap();
+        let mut conversation = Conversation::default();
+        let report = research_turn(&mut conversation, "第一个问题", "https://a.example");
+        store
+            .record_turn(first, "第一个问题", &report, &[], &conversation)
+            .unwrap();
+
+        // 刚更新的会话排在前面
+        let sessions = store.list().unwrap();
+        assert_eq!(
+            sessions.iter().map(|s| s.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(sessions[0].turns, 1);
+        assert_eq!(sessions[1].mode, "deep");
+
+        store.delete(first).unwrap();
+        assert!(matches!(store.delete(first), Err(StoreError::NotFound(_))));
+        assert!(matches!(store.load(first), Err(StoreError::NotFound(_))));
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        // 外键级联删除了该会话的轮次和来源
+        let orphans: i64 = store
+            .conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM turns) + (SELECT COUNT(*) FROM sources)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+}
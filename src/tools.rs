@@ -1,91 +1,56 @@
 //! # 工具模块
 //!
-//! 本模块使用 DuckDuckGo 实现网络搜索工具。
+//! 本模块把可插拔的搜索后端和页面获取器包装成 Rig 工具。
 //! 它演示了几个重要的 Rust 和异步模式：
 //! - 特征实现（Rig 的 Tool 特征）
 //! - 异步/等待用于非阻塞 I/O
-//! - 使用 thiserror 进行结构化错误处理
+//! - 特征对象用于在运行时选择搜索后端
 //! - Serde 用于 JSON 序列化/反序列化
 
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use thiserror::Error;
-use tracing::{debug, info, warn};
+use std::sync::Arc;
+use tracing::{info, warn};
 
-// =============================================================================
-// 自定义错误类型
-// =============================================================================
-/// # Rust 概念：使用 thiserror 的自定义错误类型
-///
-/// thiserror 是一个派生宏，使创建自定义错误类型变得容易。
-/// 每个变体代表可能发生的不同种类的错误。
-/// #[error("...")] 属性定义了错误消息。
-///
-/// 这比使用字符串更好，因为：
-/// 1. 编译器检查我们是否处理了所有错误情况
-/// 2. 我们可以匹配特定的错误类型
-/// 3. 错误是自文档化的
-///
-/// 注意：对于 Rig 的 Tool 特征，我们的错误必须实现 std::error::Error，
-/// thiserror 通过派生宏自动提供这个。
-#[derive(Error, Debug)]
-pub enum SearchError {
-    #[error("执行网络搜索失败: {0}")]
-    SearchFailed(String),
-
-    #[error("被搜索提供商限速，请等待")]
-    RateLimited,
-
-    #[allow(dead_code)] // 可能在未来的增强中使用
-    #[error("未找到查询结果: {0}")]
-    NoResults(String),
-
-    #[error("网络错误: {0}")]
-    NetworkError(#[from] reqwest::Error),
-}
-
-// =============================================================================
-// 搜索结果结构体
-// =============================================================================
-/// 表示来自网络的一个搜索结果。
-///
-/// # Rust 概念：序列化的派生宏
-///
-/// - Serialize：将结构体转换为 JSON（或其他格式）
-/// - Deserialize：将 JSON 解析为结构体
-/// - Clone：创建深拷贝
-/// - Debug：使用 {:?} 漂亮地打印
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchResult {
-    /// 搜索结果的标题
-    pub title: String,
-
-    /// 结果的 URL
-    pub url: String,
-
-    /// 内容的片段/描述
-    pub snippet: String,
-}
+use crate::config::Config;
+use crate::document;
+use crate::events::{EventSink, ResearchEvent};
+use crate::fetch::{FetchError, PageFetcher};
+use crate::report::SourceRegistry;
+use crate::search::{self, SearchError, SearchOptions, SearchProvider, SearchResult};
 
 // =============================================================================
 // 网络搜索工具
 // =============================================================================
-/// 使用 DuckDuckGo 进行免费搜索的网络搜索工具。
+/// 把搜索提供商包装成 Rig 工具的网络搜索工具。
 ///
 /// # Rust 概念：带私有字段的结构体
 ///
 /// 通过不将字段设为 `pub`，我们封装了实现。
 /// 用户只能通过 `new()` 创建这个，并使用公共方法。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// # Rust 概念：特征对象
+///
+/// `Arc<dyn SearchProvider>` 可以保存任何实现了 `SearchProvider` 的类型。
+/// 工具本身不关心后端是 DuckDuckGo 还是其他服务。
+#[derive(Debug, Clone)]
 pub struct WebSearchTool {
+    /// 实际执行搜索的后端
+    provider: Arc<dyn SearchProvider>,
+
     /// 每次搜索返回的最大结果数
     max_results: usize,
+
+    /// 登记返回给 LLM 的每个 URL，以便引用 `[n]`
+    sources: SourceRegistry,
+
+    /// 完整的搜索结果作为事件发送到这里
+    events: EventSink,
 }
 
 impl WebSearchTool {
-    /// 使用指定的最大结果数创建新的 WebSearchTool。
+    /// 使用指定的搜索提供商和最大结果数创建新的 WebSearchTool。
     ///
     /// # Rust 概念：关联函数（构造函数）
     ///
@@ -94,17 +59,43 @@ impl WebSearchTool {
     /// 它们使用 `Type::new()` 语法调用。
     ///
     /// # 参数
+    /// * `provider` - 执行搜索的后端
     /// * `max_results` - 返回的最大搜索结果数
     ///
     /// # 示例
     /// ```
-    /// let search_tool = WebSearchTool::new(5);
+    /// let search_tool = WebSearchTool::new(Arc::new(DuckDuckGoProvider::new()), 5);
     /// ```
-    pub fn new(max_results: usize) -> Self {
-        Self { max_results }
+    pub fn new(provider: Arc<dyn SearchProvider>, max_results: usize) -> Self {
+        Self {
+            provider,
+            max_results,
+            sources: SourceRegistry::new(),
+            events: EventSink::none(),
+        }
     }
 
-    /// 使用 DuckDuckGo 执行网络搜索。
+    /// 使用配置中选择的搜索提供商创建 WebSearchTool。
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            search::provider_from_config(config),
+            config.max_search_results,
+        )
+    }
+
+    /// 返回使用给定来源登记簿的工具副本（每次研究使用一个新的登记簿）。
+    pub fn with_sources(mut self, sources: SourceRegistry) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// 返回把搜索结果发送到给定事件接收端的工具副本。
+    pub fn with_events(mut self, events: EventSink) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 使用配置的提供商执行网络搜索。
     ///
     /// # Rust 概念：异步函数
     ///
@@ -112,13 +103,14 @@ impl WebSearchTool {
     /// 在异步函数内部，您使用 `.await` 等待异步操作。
     /// 这允许高效处理 I/O 而不阻塞线程。
     pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>, SearchError> {
-        info!(query = %query, "Performing web search");
+        info!(query = %query, provider = self.provider.name(), "Performing web search");
 
-        // 限速：在发出请求之前等待一下
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        let options = SearchOptions {
+            max_results: self.max_results,
+        };
 
-        // 使用 DuckDuckGo HTML 搜索
-        let results = self.search_duckduckgo(query).await?;
+        let mut results = self.provider.search(query, &options).await?;
+        results.truncate(self.max_results);
 
         if results.is_empty() {
             warn!(query = %query, "No search results found");
@@ -128,155 +120,6 @@ impl WebSearchTool {
 
         Ok(results)
     }
-
-    /// 通过 HTML 抓取执行 DuckDuckGo 搜索的内部方法。
-    ///
-    /// 注意：我们使用 HTML 抓取，因为 DuckDuckGo 没有免费的网络搜索 API。
-    /// duckduckgo_search 库的 API 返回空结果。
-    async fn search_duckduckgo(&self, query: &str) -> Result<Vec<SearchResult>, SearchError> {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-            .build()?;
-
-        let url = format!(
-            "https://html.duckduckgo.com/html/?q={}",
-            urlencoding::encode(query)
-        );
-
-        debug!(url = %url, "Fetching search results");
-
-        let response = client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                return Err(SearchError::RateLimited);
-            }
-            return Err(SearchError::SearchFailed(format!(
-                "HTTP {}",
-                response.status()
-            )));
-        }
-
-        let body = response.text().await?;
-        let results = self.parse_html(&body);
-
-        Ok(results.into_iter().take(self.max_results).collect())
-    }
-
-    /// 解析 DuckDuckGo HTML 以提取结果。
-    /// 使用多种策略来处理不同的 HTML 格式。
-    fn parse_html(&self, html: &str) -> Vec<SearchResult> {
-        let mut results = Vec::new();
-        let mut seen_urls = std::collections::HashSet::new();
-
-        // 策略 1：查找带有 uddg 参数的结果链接（重定向 URL）
-        for segment in html.split("uddg=") {
-            if results.len() >= self.max_results {
-                break;
-            }
-
-            // 找到编码 URL 的结尾
-            if let Some(end) = segment.find(|c| c == '&' || c == '"' || c == '\'') {
-                let encoded_url = &segment[..end];
-                if let Ok(url) = urlencoding::decode(encoded_url) {
-                    let url_str = url.to_string();
-                    if url_str.starts_with("http")
-                        && !url_str.contains("duckduckgo.com")
-                        && !seen_urls.contains(&url_str)
-                    {
-                        seen_urls.insert(url_str.clone());
-                        results.push(SearchResult {
-                            title: extract_domain(&url_str).unwrap_or_else(|| "Result".to_string()),
-                            url: url_str,
-                            snippet: "Search result from DuckDuckGo".to_string(),
-                        });
-                    }
-                }
-            }
-        }
-
-        // 策略 2：查找包含可见 URL 的 result__url 类
-        if results.len() < self.max_results {
-            for segment in html.split("result__url") {
-                if results.len() >= self.max_results {
-                    break;
-                }
-
-                // 在这个标记后查找 href
-                if let Some(href_start) = segment.find("href=\"") {
-                    let after_href = &segment[href_start + 6..];
-                    if let Some(href_end) = after_href.find('"') {
-                        let href = &after_href[..href_end];
-                        let url = if href.starts_with("//") {
-                            format!("https:{}", href)
-                        } else if href.starts_with("http") {
-                            href.to_string()
-                        } else {
-                            continue;
-                        };
-
-                        if !url.contains("duckduckgo.com") && !seen_urls.contains(&url) {
-                            seen_urls.insert(url.clone());
-                            results.push(SearchResult {
-                                title: extract_domain(&url).unwrap_or_else(|| "Result".to_string()),
-                                url,
-                                snippet: "Search result".to_string(),
-                            });
-                        }
-                    }
-                }
-            }
-        }
-
-        // 策略 3：直接 URL 提取 - 查找任何 https:// URL
-        if results.len() < self.max_results {
-            for segment in html.split("https://") {
-                if results.len() >= self.max_results {
-                    break;
-                }
-
-                if let Some(end) = segment.find(|c: char| {
-                    c == '"' || c == '\'' || c == '<' || c == '>' || c == ' ' || c == ')'
-                }) {
-                    let domain_path = &segment[..end];
-                    // 过滤内部/跟踪 URL
-                    if !domain_path.starts_with("duckduckgo")
-                        && !domain_path.starts_with("improving.duckduckgo")
-                        && !domain_path.contains("cdn.")
-                        && !domain_path.contains(".js")
-                        && !domain_path.contains(".css")
-                        && !domain_path.contains(".png")
-                        && !domain_path.contains(".ico")
-                        && domain_path.contains('.')
-                        && domain_path.len() > 5
-                    {
-                        let url = format!("https://{}", domain_path);
-                        if !seen_urls.contains(&url) {
-                            seen_urls.insert(url.clone());
-                            results.push(SearchResult {
-                                title: extract_domain(&url).unwrap_or_else(|| "Result".to_string()),
-                                url,
-                                snippet: "Search result".to_string(),
-                            });
-                        }
-                    }
-                }
-            }
-        }
-
-        // 去重并返回
-        results.into_iter().take(self.max_results).collect()
-    }
-}
-
-/// 从 URL 中提取域名。
-fn extract_domain(url: &str) -> Option<String> {
-    url.split("//")
-        .nth(1)?
-        .split('/')
-        .next()
-        .map(|s| s.to_string())
 }
 
 // =============================================================================
@@ -313,7 +156,11 @@ impl Tool for WebSearchTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: Self::NAME.to_string(),
-            description: "使用 DuckDuckGo 搜索网络。使用此工具查找关于任何主题的当前信息。".to_string(),
+            description: format!(
+                "使用 {} 搜索网络。使用此工具查找关于任何主题的当前信息。\
+                 每个结果都有来源编号 [n]，引用时使用该编号。",
+                self.provider.name()
+            ),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -331,7 +178,14 @@ impl Tool for WebSearchTool {
     ///
     /// 注意：在 Rig 0.27 中，call() 只接受 &self 和 args（没有状态参数）。
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.events.emit(ResearchEvent::SearchIssued {
+            query: args.query.clone(),
+        });
         let results = self.search(&args.query).await?;
+        self.events.emit(ResearchEvent::SearchResults {
+            query: args.query.clone(),
+            results: results.clone(),
+        });
 
         if results.is_empty() {
             return Ok(format!("未找到结果: {}", args.query));
@@ -339,23 +193,155 @@ impl Tool for WebSearchTool {
 
         let formatted: String = results
             .iter()
-            .enumerate()
-            .map(|(i, r)| {
-                format!(
-                    "{}. **{}**\n   URL: {}\n   {}\n",
-                    i + 1,
-                    r.title,
-                    r.url,
-                    r.snippet
-                )
+            .map(|r| {
+                let id = self.sources.register(&r.url, &r.title);
+                self.sources.add_content(id, &r.snippet);
+                self.sources
+                    .add_metadata(id, None, None, r.published.as_deref());
+                let mut entry = format!(
+                    "[{}] **{}**\n   URL: {}\n   {}\n",
+                    id, r.title, r.url, r.snippet
+                );
+                if let Some(published) = &r.published {
+                    entry.push_str(&format!("   发布日期: {}\n", published));
+                }
+                entry
             })
             .collect::<Vec<_>>()
             .join("\n");
 
-        Ok(format!(
-            "## 搜索结果: {}\n\n{}",
-            args.query, formatted
-        ))
+        Ok(format!("## 搜索结果: {}\n\n{}", args.query, formatted))
+    }
+}
+
+// =============================================================================
+// 页面获取工具
+// =============================================================================
+/// 下载网页或 PDF 并返回其正文文本的工具。
+///
+/// 搜索结果只有标题和片段；这个工具让 LLM 可以阅读来源本身，
+/// 从而基于真实内容而不是标题来撰写摘要。PDF 和分页文本的正文
+/// 带有 `[p. N]` 页码标记，方便 LLM 引用具体页面。
+#[derive(Debug, Clone)]
+pub struct FetchPageTool {
+    fetcher: PageFetcher,
+
+    /// 与 WebSearchTool 共享的来源登记簿
+    sources: SourceRegistry,
+
+    /// 读取的每个页面作为事件发送到这里
+    events: EventSink,
+}
+
+impl FetchPageTool {
+    /// 使用给定的页面获取器创建 FetchPageTool。
+    pub fn new(fetcher: PageFetcher) -> Self {
+        Self {
+            fetcher,
+            sources: SourceRegistry::new(),
+            events: EventSink::none(),
+        }
+    }
+
+    /// 返回使用给定来源登记簿的工具副本。
+    pub fn with_sources(mut self, sources: SourceRegistry) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// 返回把读取的页面发送到给定事件接收端的工具副本。
+    pub fn with_events(mut self, events: EventSink) -> Self {
+        self.events = events;
+        self
+    }
+}
+
+/// 工具输出中最多列出的页面链接数
+const MAX_LISTED_LINKS: usize = 10;
+
+/// 页面获取工具的输入参数。
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FetchArgs {
+    /// 要读取的页面 URL（启用本地文件时也可以是文件路径）
+    pub url: String,
+}
+
+impl Tool for FetchPageTool {
+    const NAME: &'static str = "fetch_page";
+
+    type Args = FetchArgs;
+    type Output = String;
+    type Error = FetchError;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description:
+                "下载网页或 PDF 并返回其可读的正文文本（PDF 带有 [p. N] 页码标记）。在 web_search 之后使用此工具阅读最相关的结果。"
+                    .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "要读取的页面或 PDF 的 URL（来自搜索结果）"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let page = self.fetcher.fetch(&args.url).await?;
+        let article = &page.article;
+        let title = article.title.as_deref().unwrap_or("(无标题)");
+        let id = self
+            .sources
+            .register(&page.url, article.title.as_deref().unwrap_or_default());
+        self.sources.add_metadata(
+            id,
+            article.byline.as_deref(),
+            article.site_name.as_deref(),
+            article.published.as_deref(),
+        );
+
+        let mut output = format!(
+            "## 页面内容: {}\n来源编号: [{}]\nURL: {}\n",
+            title, id, page.url
+        );
+        if let Some(byline) = &article.byline {
+            output.push_str(&format!("作者: {}\n", byline));
+        }
+        if let Some(published) = &article.published {
+            output.push_str(&format!("发布日期: {}\n", published));
+        }
+        output.push('\n');
+        let body = document::render_chunks(&page.chunks);
+        self.sources.add_content(id, &body);
+        self.events.emit(ResearchEvent::PageFetched {
+            url: page.url.clone(),
+            title: article.title.clone(),
+            source_id: id,
+            chars: body.chars().count(),
+        });
+        output.push_str(&body);
+        if page.truncated {
+            output.push_str("\n\n[内容已截断]");
+        }
+
+        // 列出正文中的链接，让 LLM 可以继续追踪相关来源；其他网站的链接排在前面
+        if !article.links.is_empty() {
+            output.push_str("\n\n### 页面中的链接\n");
+            let (outbound, same_site): (Vec<_>, Vec<_>) =
+                article.links.iter().partition(|link| link.is_external);
+            for link in outbound.iter().chain(&same_site).take(MAX_LISTED_LINKS) {
+                let mark = if link.is_external { "" } else { "（站内）" };
+                output.push_str(&format!("- {}: {}{}\n", link.text, link.url, mark));
+            }
+        }
+
+        Ok(output)
     }
 }
 
@@ -365,33 +351,122 @@ impl Tool for WebSearchTool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::DuckDuckGoProvider;
+    use crate::test_util::{StubResponse, StubServer};
+    use async_trait::async_trait;
+
+    /// 返回固定结果的测试提供商，不访问网络
+    #[derive(Debug)]
+    struct FixedProvider(Vec<SearchResult>);
+
+    #[async_trait]
+    impl SearchProvider for FixedProvider {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _options: &SearchOptions,
+        ) -> Result<Vec<SearchResult>, SearchError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn result(n: usize) -> SearchResult {
+        SearchResult {
+            title: format!("Result {}", n),
+            url: format!("https://example.com/{}", n),
+            snippet: format!("Snippet {}", n),
+            engine: None,
+            published: None,
+        }
+    }
 
     #[test]
     fn test_web_search_tool_creation() {
-        let tool = WebSearchTool::new(5);
+        let tool = WebSearchTool::new(Arc::new(DuckDuckGoProvider::new()), 5);
         assert_eq!(tool.max_results, 5);
+        assert_eq!(tool.provider.name(), "duckduckgo");
     }
 
-    #[test]
-    fn test_extract_domain() {
-        assert_eq!(
-            extract_domain("https://www.example.com/page"),
-            Some("www.example.com".to_string())
-        );
-        assert_eq!(
-            extract_domain("https://rust-lang.org/learn"),
-            Some("rust-lang.org".to_string())
-        );
+    #[tokio::test]
+    async fn test_search_uses_provider_and_truncates() {
+        let provider = FixedProvider((0..10).map(result).collect());
+        let tool = WebSearchTool::new(Arc::new(provider), 3);
+
+        let results = tool.search("anything").await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "Result 0");
     }
 
-    #[test]
-    fn test_search_result_serialization() {
-        let result = SearchResult {
-            title: "Test".to_string(),
-            url: "https://test.com".to_string(),
-            snippet: "A test result".to_string(),
+    #[tokio::test]
+    async fn test_tool_call_formats_results() {
+        let tool = WebSearchTool::new(Arc::new(FixedProvider(vec![result(1)])), 5);
+
+        let output = tool
+            .call(SearchArgs {
+                query: "rust".to_string(),
+            })
+            .await
+            .unwrap();
+        assert!(output.contains("[1] **Result 1**"));
+        assert!(output.contains("https://example.com/1"));
+    }
+
+    #[tokio::test]
+    async fn test_tools_share_source_registry() {
+        let server = StubServer::spawn(|_| {
+            StubResponse::with_type(
+                200,
+                "text/html",
+                "<html><head><title>Stub Page</title></head><body><p>Body text.</p></body></html>",
+            )
+        })
+        .await;
+
+        let sources = SourceRegistry::new();
+        let (events, mut receiver) = EventSink::channel();
+        let search = WebSearchTool::new(Arc::new(FixedProvider(vec![result(1), result(2)])), 5)
+            .with_sources(sources.clone())
+            .with_events(events.clone());
+        let fetch = FetchPageTool::new(PageFetcher::from_config(&Config::default()))
+            .with_sources(sources.clone())
+            .with_events(events);
+
+        let args = || SearchArgs {
+            query: "rust".to_string(),
         };
-        let json = serde_json::to_string(&result).unwrap();
-        assert!(json.contains("Test"));
+        search.call(args()).await.unwrap();
+        // 同一个 URL 再次出现时编号不变
+        let output = search.call(args()).await.unwrap();
+        assert!(output.contains("[2] **Result 2**"));
+
+        let output = fetch
+            .call(FetchArgs {
+                url: server.url.clone(),
+            })
+            .await
+            .unwrap();
+        assert!(output.contains("来源编号: [3]"));
+        assert_eq!(sources.get(3).unwrap().title, "Stub Page");
+
+        // 每次搜索发出开始和结果两个事件，读取页面发出一个事件
+        assert_eq!(
+            receiver.recv().await,
+            Some(ResearchEvent::SearchIssued {
+                query: "rust".to_string()
+            })
+        );
+        let mut remaining = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            remaining.push(event);
+        }
+        assert_eq!(remaining.len(), 4);
+        assert!(matches!(
+            &remaining[3],
+            ResearchEvent::PageFetched { title: Some(title), source_id: 3, .. } if title == "Stub Page"
+        ));
     }
 }
@@ -0,0 +1,518 @@
+//! # 引用核验模块
+//!
+//! 报告草稿完成后，逐条检查每个引用：论断是否真的能被它引用的来源支持？
+//! 核验结合了两种信号：
+//! - **字符串重叠**：论断中的词（中文使用双字组）有多少出现在来源文本中
+//! - **LLM 蕴含判断**：把论断和来源中最相关的片段交给 LLM，问它"来源是否支持该论断"
+//!
+//! 每个引用最终被标记为 支持 / 较弱 / 不支持，并显示在输出中。
+//!
+//! 它演示了：
+//! - 使用特征抽象 LLM 调用，使核验逻辑可以在测试中使用假的判断器
+//! - `HashSet` 用于计算集合重叠
+//! - 同时借用结构体的不同字段（一个可变，一个不可变）
+
+use async_trait::async_trait;
+use rig::agent::Agent;
+use rig::completion::{CompletionModel, Prompt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use tracing::{debug, info, warn};
+
+use crate::report::{strip_citations, ResearchReport, SourceRegistry};
+
+/// 蕴含判断的系统提示
+pub const ENTAILMENT_PROMPT: &str = r#"
+你是一个严格的事实核查员。你会收到一条论断和一段来源摘录。
+判断来源摘录是否支持该论断（论断和摘录可能使用不同语言）。
+
+只回答以下三个词之一，不要解释：
+SUPPORTED - 摘录明确支持论断
+PARTIAL - 摘录只支持论断的一部分，或支持得不够明确
+UNSUPPORTED - 摘录没有提到或与论断矛盾
+"#;
+
+/// 重叠率达到此值时，即使没有 LLM 判断也视为支持
+const STRONG_OVERLAP: f32 = 0.6;
+
+/// 重叠率达到此值时，没有 LLM 判断视为较弱
+const WEAK_OVERLAP: f32 = 0.3;
+
+/// 交给 LLM 的来源摘录最多包含的段落数
+const EXCERPT_PARAGRAPHS: usize = 3;
+
+/// 每个摘录段落的最大字符数
+const EXCERPT_PARAGRAPH_CHARS: usize = 600;
+
+// =============================================================================
+// 核验结果
+// =============================================================================
+/// 一个引用的核验结论。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Supported,
+    Weak,
+    Unsupported,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Supported => write!(f, "支持"),
+            Self::Weak => write!(f, "较弱"),
+            Self::Unsupported => write!(f, "不支持"),
+        }
+    }
+}
+
+/// 对论断中一个引用的核验结果。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationCheck {
+    /// 被引用的来源编号（对应 `ResearchReport::sources`）
+    pub source: usize,
+
+    /// 论断词语在来源文本中出现的比例（0.0 - 1.0）
+    pub overlap: f32,
+    pub verdict: Verdict,
+}
+
+/// LLM 对"来源是否支持论断"的回答。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entailment {
+    Supported,
+    Partial,
+    Unsupported,
+}
+
+impl Entailment {
+    /// 从 LLM 回复中解析判断结果；无法识别时返回 None。
+    ///
+    /// 只看第一行，按完整的单词匹配提示要求的三个标签，而不是查找子串：
+    /// `NOT SUPPORTED`、`isn't supported` 这样被否定的 SUPPORTED 视为不支持。
+    pub fn parse(response: &str) -> Option<Self> {
+        let line = response.lines().map(str::trim).find(|l| !l.is_empty())?;
+
+        let line = line.trim_start_matches(|c: char| !c.is_alphanumeric());
+        if line.starts_with("不支持") {
+            return Some(Self::Unsupported);
+        } else if line.starts_with("部分") {
+            return Some(Self::Partial);
+        } else if line.starts_with("支持") {
+            return Some(Self::Supported);
+        }
+
+        let upper = line.to_uppercase().replace('’', "'");
+        let words: Vec<&str> = upper
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|w| !w.is_empty())
+            .collect();
+        words.iter().enumerate().find_map(|(i, &word)| match word {
+            "UNSUPPORTED" => Some(Self::Unsupported),
+            "PARTIAL" | "PARTIALLY" => Some(Self::Partial),
+            "SUPPORTED" => {
+                let negated = i
+                    .checked_sub(1)
+                    .is_some_and(|prev| words[prev] == "NOT" || words[prev].ends_with("N'T"));
+                Some(if negated {
+                    Self::Unsupported
+                } else {
+                    Self::Supported
+                })
+            }
+            _ => None,
+        })
+    }
+}
+
+// =============================================================================
+// 蕴含判断器
+// =============================================================================
+/// 判断来源摘录是否支持论断。
+///
+/// # Rust 概念：用特征隔离外部依赖
+///
+/// 核验流程只依赖这个特征，而不直接依赖 LLM。生产代码使用 `LlmJudge`，
+/// 测试使用返回固定结果的假实现，无需运行 Ollama。
+#[async_trait]
+pub trait EntailmentJudge: Send + Sync {
+    /// 返回判断结果；LLM 调用失败或回复无法解析时返回 None。
+    async fn judge(&self, claim: &str, excerpt: &str) -> Option<Entailment>;
+}
+
+/// 使用 Rig 代理进行蕴含判断。
+pub struct LlmJudge<M: CompletionModel> {
+    agent: Agent<M>,
+}
+
+impl<M: CompletionModel> LlmJudge<M> {
+    /// 使用已配置 `ENTAILMENT_PROMPT` 前导语的代理创建判断器。
+    pub fn new(agent: Agent<M>) -> Self {
+        Self { agent }
+    }
+}
+
+#[async_trait]
+impl<M: CompletionModel + 'static> EntailmentJudge for LlmJudge<M> {
+    async fn judge(&self, claim: &str, excerpt: &str) -> Option<Entailment> {
+        let prompt = format!("论断：{}\n\n来源摘录：\n{}", claim, excerpt);
+        match self.agent.prompt(prompt).await {
+            Ok(response) => Entailment::parse(&response),
+            Err(e) => {
+                warn!(error = %e, "Entailment check failed");
+                None
+            }
+        }
+    }
+}
+
+// =============================================================================
+// 核验流程
+// =============================================================================
+/// 核验报告中的每个引用，并把结果写入对应论断的 `checks`。
+pub async fn verify_report(
+    report: &mut ResearchReport,
+    registry: &SourceRegistry,
+    judge: &dyn EntailmentJudge,
+) {
+    // 同时借用 sources（只读）和 sections（可变）：它们是不同的字段，
+    // 所以借用检查器允许这样做
+    let sources = &report.sources;
+
+    for claim in report.sections.iter_mut().flat_map(|s| s.claims.iter_mut()) {
+        let statement = claim_statement(&claim.text);
+        let mut checks = Vec::new();
+
+        for &id in &claim.citations {
+            let content = sources
+                .iter()
+                .find(|s| s.id == id)
+                .and_then(|s| registry.content_for_url(&s.url));
+
+            let check = match content {
+                Some(content) => {
+                    let overlap = overlap_score(&statement, &content);
+                    let excerpt = best_excerpt(&statement, &content);
+                    let entailment = judge.judge(&statement, &excerpt).await;
+                    CitationCheck {
+                        source: id,
+                        overlap,
+                        verdict: combine(overlap, entailment),
+                    }
+                }
+                // 工具没有为这个来源返回任何文本，无法核验
+                None => CitationCheck {
+                    source: id,
+                    overlap: 0.0,
+                    verdict: Verdict::Unsupported,
+                },
+            };
+            debug!(source = id, overlap = check.overlap, verdict = %check.verdict, "Checked citation");
+            checks.push(check);
+        }
+
+        claim.checks = checks;
+    }
+
+    let (supported, weak, unsupported) = report.verdict_counts();
+    info!(
+        supported,
+        weak, unsupported, "Citation verification finished"
+    );
+}
+
+/// 结合重叠率和 LLM 判断得出最终结论。
+///
+/// LLM 判断优先（报告和来源常常使用不同语言，此时重叠率很低），
+/// 但很高的重叠率可以把"不支持"提升为"较弱"。没有 LLM 判断时只看重叠率。
+pub fn combine(overlap: f32, entailment: Option<Entailment>) -> Verdict {
+    match entailment {
+        Some(Entailment::Supported) => Verdict::Supported,
+        Some(Entailment::Partial) => Verdict::Weak,
+        Some(Entailment::Unsupported) if overlap >= STRONG_OVERLAP => Verdict::Weak,
+        Some(Entailment::Unsupported) => Verdict::Unsupported,
+        None if overlap >= STRONG_OVERLAP => Verdict::Supported,
+        None if overlap >= WEAK_OVERLAP => Verdict::Weak,
+        None => Verdict::Unsupported,
+    }
+}
+
+// =============================================================================
+// 字符串重叠
+// =============================================================================
+/// 去掉引用标记、列表符号和粗体标记，得到要核验的陈述。
+fn claim_statement(text: &str) -> String {
+    let text = strip_citations(text);
+    let text = text.trim_start();
+    let text = text
+        .strip_prefix("- ")
+        .or_else(|| text.strip_prefix("* "))
+        .or_else(|| {
+            let (number, rest) = text.split_once(". ")?;
+            (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit())).then_some(rest)
+        })
+        .unwrap_or(text);
+    text.replace("**", "").trim().to_string()
+}
+
+/// 论断中的词语出现在来源中的比例。
+pub fn overlap_score(claim: &str, source: &str) -> f32 {
+    let claim_terms = terms(claim);
+    if claim_terms.is_empty() {
+        return 0.0;
+    }
+    let source_terms = terms(source);
+    let shared = claim_terms.intersection(&source_terms).count();
+    shared as f32 / claim_terms.len() as f32
+}
+
+/// 选出来源中与论断重叠最多的几个段落作为摘录（保持原来的顺序）。
+///
+/// 没有任何段落与论断重叠时（例如跨语言），使用来源开头的段落。
+fn best_excerpt(claim: &str, source: &str) -> String {
+    let claim_terms = terms(claim);
+    let paragraphs: Vec<&str> = source
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    let mut scored: Vec<(usize, usize)> = paragraphs
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i, terms(p).intersection(&claim_terms).count()))
+        .collect();
+    scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut chosen: Vec<usize> = scored
+        .into_iter()
+        .filter(|&(_, score)| score > 0)
+        .take(EXCERPT_PARAGRAPHS)
+        .map(|(i, _)| i)
+        .collect();
+    if chosen.is_empty() {
+        chosen = (0..paragraphs.len().min(EXCERPT_PARAGRAPHS)).collect();
+    }
+    chosen.sort_unstable();
+
+    chosen
+        .into_iter()
+        .map(|i| {
+            let p = paragraphs[i];
+            match p.char_indices().nth(EXCERPT_PARAGRAPH_CHARS) {
+                Some((idx, _)) => format!("{}…", &p[..idx]),
+                None => p.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 常见的英文虚词，不参与重叠计算
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "are", "was", "were", "from", "has", "have",
+    "its", "into", "than", "which", "but", "not", "can", "also", "such",
+];
+
+/// 把文本拆分为用于比较的词语集合。
+///
+/// - ASCII 字母数字串按小写单词处理（去掉短词和虚词）
+/// - 中文等非 ASCII 文字没有空格分词，使用相邻字符组成的双字组
+fn terms(text: &str) -> HashSet<String> {
+    let mut terms = HashSet::new();
+    let mut word = String::new();
+    let mut cjk: Vec<char> = Vec::new();
+
+    let flush_word = |word: &mut String, terms: &mut HashSet<String>| {
+        if word.len() >= 3 && !STOP_WORDS.contains(&word.as_str()) {
+            terms.insert(std::mem::take(word));
+        }
+        word.clear();
+    };
+    let flush_cjk = |cjk: &mut Vec<char>, terms: &mut HashSet<String>| {
+        match cjk.len() {
+            0 => {}
+            1 => {
+                terms.insert(cjk[0].to_string());
+            }
+            _ => {
+                for pair in cjk.windows(2) {
+                    terms.insert(pair.iter().collect());
+                }
+            }
+        }
+        cjk.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            flush_cjk(&mut cjk, &mut terms);
+            word.push(c.to_ascii_lowercase());
+        } else if !c.is_ascii() && c.is_alphanumeric() {
+            flush_word(&mut word, &mut terms);
+            cjk.push(c);
+        } else {
+            flush_word(&mut word, &mut terms);
+            flush_cjk(&mut cjk, &mut terms);
+        }
+    }
+    flush_word(&mut word, &mut terms);
+    flush_cjk(&mut cjk, &mut terms);
+
+    terms
+}
+
+// =============================================================================
+// 单元测试
+// =============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// 返回固定判断并记录收到的摘录的测试判断器
+    struct FixedJudge {
+        answer: Option<Entailment>,
+        excerpts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl EntailmentJudge for FixedJudge {
+        async fn judge(&self, _claim: &str, excerpt: &str) -> Option<Entailment> {
+            self.excerpts.lock().unwrap().push(excerpt.to_string());
+            self.answer
+        }
+    }
+
+    #[test]
+    fn test_terms_and_overlap() {
+        let claim = "Tokio is the most popular async runtime";
+        let source = "Tokio, an async runtime, is very popular among Rust developers.";
+        // tokio / most / popular / async / runtime：5 个词中 4 个出现在来源中
+        assert!((overlap_score(claim, source) - 0.8).abs() < 1e-6);
+
+        assert!((overlap_score("异步运行时", "Tokio 是一个异步运行时") - 1.0).abs() < 1e-6);
+        assert_eq!(overlap_score("完全无关", "Tokio runtime"), 0.0);
+        assert_eq!(overlap_score("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn test_claim_statement() {
+        assert_eq!(
+            claim_statement("- **Tokio** 很流行 [1, 2]。"),
+            "Tokio 很流行。"
+        );
+        assert_eq!(claim_statement("2. 第二点 [3]"), "第二点");
+        assert_eq!(claim_statement("2024 年发布 [1]"), "2024 年发布");
+    }
+
+    #[test]
+    fn test_entailment_parse() {
+        assert_eq!(Entailment::parse("SUPPORTED"), Some(Entailment::Supported));
+        assert_eq!(
+            Entailment::parse("unsupported."),
+            Some(Entailment::Unsupported)
+        );
+        assert_eq!(Entailment::parse("PARTIAL"), Some(Entailment::Partial));
+        assert_eq!(Entailment::parse("不支持"), Some(Entailment::Unsupported));
+        assert_eq!(Entailment::parse("我不确定"), None);
+
+        // 被否定的 SUPPORTED 不能当作支持
+        for reply in [
+            "NOT SUPPORTED",
+            "not supported",
+            "Not_Supported.",
+            "It isn't supported",
+            "doesn’t SUPPORTED",
+        ] {
+            assert_eq!(
+                Entailment::parse(reply),
+                Some(Entailment::Unsupported),
+                "{}",
+                reply
+            );
+        }
+        assert_eq!(
+            Entailment::parse("**Supported**"),
+            Some(Entailment::Supported)
+        );
+        assert_eq!(
+            Entailment::parse("PARTIALLY supported"),
+            Some(Entailment::Partial)
+        );
+        // 只看第一行：解释中出现的标签不算
+        assert_eq!(
+            Entailment::parse(
+                "UNSUPPORTED\nThe excerpt is not about this, though SUPPORTED elsewhere."
+            ),
+            Some(Entailment::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_combine() {
+        assert_eq!(
+            combine(0.0, Some(Entailment::Supported)),
+            Verdict::Supported
+        );
+        assert_eq!(combine(0.9, Some(Entailment::Partial)), Verdict::Weak);
+        assert_eq!(combine(0.9, Some(Entailment::Unsupported)), Verdict::Weak);
+        assert_eq!(
+            combine(0.1, Some(Entailment::Unsupported)),
+            Verdict::Unsupported
+        );
+        assert_eq!(combine(0.7, None), Verdict::Supported);
+        assert_eq!(combine(0.4, None), Verdict::Weak);
+        assert_eq!(combine(0.1, None), Verdict::Unsupported);
+    }
+
+    #[test]
+    fn test_best_excerpt_picks_relevant_paragraphs() {
+        let source = "Intro about cooking.\n\nTokio is an async runtime.\n\nUnrelated sports news.\n\nAsync runtime benchmarks for Tokio.\n\nWeather today.";
+        let excerpt = best_excerpt("Tokio async runtime", source);
+        assert!(excerpt.starts_with("Tokio is an async runtime."));
+        assert!(excerpt.contains("benchmarks"));
+        assert_eq!(excerpt.split("\n\n").count(), 2);
+
+        // 没有重叠时退回到开头的段落
+        let excerpt = best_excerpt("异步运行时", source);
+        assert!(excerpt.starts_with("Intro about cooking."));
+        assert_eq!(excerpt.split("\n\n").count(), EXCERPT_PARAGRAPHS);
+    }
+
+    #[tokio::test]
+    async fn test_verify_report() {
+        let registry = SourceRegistry::new();
+        registry.register("https://tokio.rs", "Tokio");
+        registry.add_content(1, "Tokio is an async runtime for Rust.");
+        registry.register("https://empty.example.com", "Empty");
+
+        let response =
+            "## 摘要\n- Tokio is an async runtime for Rust [1]\n- 来源没有正文 [2]\n- 没有引用";
+        let mut report = ResearchReport::from_response("q", response, &registry);
+
+        let judge = FixedJudge {
+            answer: None,
+            excerpts: Mutex::new(Vec::new()),
+        };
+        verify_report(&mut report, &registry, &judge).await;
+
+        let claims: Vec<_> = report.claims().collect();
+        assert_eq!(claims[0].checks.len(), 1);
+        assert_eq!(claims[0].checks[0].verdict, Verdict::Supported);
+        assert_eq!(claims[1].checks[0].verdict, Verdict::Unsupported);
+        assert!(claims[2].checks.is_empty());
+
+        // 没有正文的来源不会调用判断器
+        assert_eq!(judge.excerpts.lock().unwrap().len(), 1);
+        assert_eq!(report.verdict_counts(), (1, 0, 1));
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("- Tokio is an async runtime for Rust [1] （核验：[1] 支持）"));
+        assert!(markdown.contains("引用核验：1 支持，0 较弱，1 不支持"));
+
+        let footer = report.stream_footer();
+        assert!(footer.contains("### 需要复查的论断\n\n- 来源没有正文 [2] （[2] 不支持）"));
+    }
+}